@@ -0,0 +1,124 @@
+//! Reading a VMF-shaped file into structs with `String` fields versus borrowed `&str` fields.
+//!
+//! Also prints how many allocations each one makes, which is the real point. What the borrowed version
+//! still allocates is the `Vec`s and the deserializer's own bookkeeping, not the strings.
+
+#![allow(dead_code)] //The fields are only there to be deserialized
+
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use criterion::black_box;
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::Criterion;
+use serde::Deserialize;
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		System.alloc(layout)
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		System.dealloc(ptr, layout)
+	}
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+#[derive(Deserialize)]
+struct OwnedWorld {
+	#[serde(rename = "solid", with = "vdf::repeated")]
+	solids: Vec<OwnedSolid>,
+}
+
+#[derive(Deserialize)]
+struct OwnedSolid {
+	id: String,
+	#[serde(rename = "side", with = "vdf::repeated")]
+	sides: Vec<OwnedSide>,
+}
+
+#[derive(Deserialize)]
+struct OwnedSide {
+	id: String,
+	plane: String,
+	material: String,
+	uaxis: String,
+	vaxis: String,
+	rotation: String,
+	lightmapscale: String,
+	smoothing_groups: String,
+}
+
+#[derive(Deserialize)]
+struct BorrowedWorld<'a> {
+	#[serde(borrow, rename = "solid", with = "vdf::repeated")]
+	solids: Vec<BorrowedSolid<'a>>,
+}
+
+#[derive(Deserialize)]
+struct BorrowedSolid<'a> {
+	id: &'a str,
+	#[serde(borrow, rename = "side", with = "vdf::repeated")]
+	sides: Vec<BorrowedSide<'a>>,
+}
+
+#[derive(Deserialize)]
+struct BorrowedSide<'a> {
+	id: &'a str,
+	plane: &'a str,
+	material: &'a str,
+	uaxis: &'a str,
+	vaxis: &'a str,
+	rotation: &'a str,
+	lightmapscale: &'a str,
+	smoothing_groups: &'a str,
+}
+
+fn world(solids: usize) -> String {
+	let mut out = String::new();
+	for solid in 0..solids {
+		out.push_str(&format!("\"solid\"\n{{\n\t\"id\" \"{}\"\n", solid));
+		for side in 0..6 {
+			out.push_str(&format!(
+				"\t\"side\"\n\t{{\n\t\t\"id\" \"{}\"\n\t\t\"plane\" \"(0 0 64) (64 0 64) (64 -64 64)\"\n\t\t\"material\" \"TILE/WHITE_WALL_TILE003A\"\n\t\t\"uaxis\" \"[1 0 0 0] 0.25\"\n\t\t\"vaxis\" \"[0 -1 0 0] 0.25\"\n\t\t\"rotation\" \"0\"\n\t\t\"lightmapscale\" \"16\"\n\t\t\"smoothing_groups\" \"0\"\n\t}}\n",
+				solid * 6 + side
+			));
+		}
+		out.push_str("}\n");
+	}
+	out
+}
+
+/// How many allocations it takes to run the function once.
+fn allocations(f: impl FnOnce()) -> usize {
+	let before = ALLOCATIONS.load(Ordering::Relaxed);
+	f();
+	ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn bench(c: &mut Criterion) {
+	let input = world(500);
+
+	let owned = allocations(|| drop(black_box(vdf::de::from_str::<OwnedWorld>(&input).unwrap())));
+	let borrowed = allocations(|| drop(black_box(vdf::de::from_str::<BorrowedWorld>(&input).unwrap())));
+	println!("allocations for 500 solids: {} with String fields, {} with &str fields", owned, borrowed);
+
+	let mut group = c.benchmark_group("500 solids");
+	group.bench_function("String fields", |b| b.iter(|| vdf::de::from_str::<OwnedWorld>(black_box(&input)).unwrap().solids.len()));
+	group.bench_function("&str fields", |b| b.iter(|| vdf::de::from_str::<BorrowedWorld>(black_box(&input)).unwrap().solids.len()));
+	group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -0,0 +1,61 @@
+//! Converts JSON back into a VDF file. See `vdf::json` for how repeated keys and conditionals go in.
+
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::process::exit;
+
+use vdf::ser::FormatSettings;
+
+const USAGE: &str = "\
+Usage: json2vdf [--style vmf|p2c|beemod|gameinfo] [FILE]
+
+Converts JSON to a VDF file. With no file, reads stdin. Writes to stdout.
+
+  --style STYLE   which preset to format with (default: p2c)";
+
+fn main() {
+	let mut settings = FormatSettings::p2c_like();
+	let mut file = None;
+
+	let mut args = std::env::args().skip(1);
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--style" => {
+				let style = args.next().unwrap_or_default();
+				settings = FormatSettings::preset(&style).unwrap_or_else(|| usage(&format!("unknown style {:?}", style)));
+			},
+			"--help" | "-h" => {
+				println!("{}", USAGE);
+				return;
+			},
+			flag if flag.starts_with("--") => usage(&format!("unknown option {}", flag)),
+			_ if file.is_some() => usage("only one file at a time"),
+			_ => file = Some(arg),
+		}
+	}
+
+	let name = file.as_deref().unwrap_or("stdin");
+	let input = match &file {
+		Some(file) => fs::read_to_string(file),
+		None => {
+			let mut input = String::new();
+			io::stdin().read_to_string(&mut input).map(|_| input)
+		},
+	}
+	.unwrap_or_else(|e| fail(name, e));
+
+	let vdf = vdf::json::from_str(&input).and_then(|object| object.to_string(&settings)).unwrap_or_else(|e| fail(name, e));
+	io::stdout().write_all(vdf.as_bytes()).unwrap_or_else(|e| fail("stdout", e));
+}
+
+fn usage(problem: &str) -> ! {
+	eprintln!("json2vdf: {}\n\n{}", problem, USAGE);
+	exit(2)
+}
+
+fn fail(what: &str, err: impl std::fmt::Display) -> ! {
+	eprintln!("json2vdf: {}: {}", what, err);
+	exit(2)
+}
@@ -0,0 +1,124 @@
+//! Reformats VDF files with one of the `FormatSettings` presets.
+//!
+//! Comments wouldn't survive, since this goes through `vdf::Object`, so files with comments are left alone unless asked.
+//! Conditionals and repeated keys do survive.
+
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::process::exit;
+
+use vdf::cst::Document;
+use vdf::ser::FormatSettings;
+
+const USAGE: &str = "\
+Usage: vdf-fmt [--style STYLE] [--input-style STYLE] [--check | --in-place] [--drop-comments] [FILE]...
+
+Reformats VDF files. With no files, reads stdin and writes to stdout.
+
+  --style STYLE         which preset to format with: vmf, p2c, beemod or gameinfo (default: p2c)
+  --input-style STYLE   which preset to read with, which only matters for escape sequences: the vmf style doesn't have them
+                        (default: vmf for .vmf files, p2c otherwise)
+  --check               don't write anything, just exit with status 1 if any file isn't formatted already
+  --in-place            overwrite the files instead of printing them
+  --drop-comments       format files with comments in them too, throwing the comments away
+
+Files with comments in them are an error without --drop-comments.";
+
+#[derive(PartialEq)]
+enum Mode {
+	Print,
+	Check,
+	InPlace,
+}
+
+fn main() {
+	let mut settings = FormatSettings::p2c_like();
+	let mut input_settings = None;
+	let mut drop_comments = false;
+	let mut mode = Mode::Print;
+	let mut files = Vec::new();
+
+	let mut args = std::env::args().skip(1);
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--style" => {
+				let style = args.next().unwrap_or_default();
+				settings = preset(&style);
+			},
+			"--input-style" => input_settings = Some(preset(&args.next().unwrap_or_default())),
+			"--drop-comments" => drop_comments = true,
+			"--check" => mode = Mode::Check,
+			"--in-place" | "-i" => mode = Mode::InPlace,
+			"--help" | "-h" => {
+				println!("{}", USAGE);
+				return;
+			},
+			flag if flag.starts_with("--") => usage(&format!("unknown option {}", flag)),
+			_ => files.push(arg),
+		}
+	}
+
+	if files.is_empty() {
+		if mode == Mode::InPlace {
+			usage("--in-place needs some files to work on");
+		}
+
+		let mut input = Vec::new();
+		io::stdin().read_to_end(&mut input).unwrap_or_else(|e| fail("stdin", e));
+		let input_settings = input_settings.unwrap_or_else(FormatSettings::p2c_like);
+		let formatted = format(&input, &input_settings, &settings, drop_comments).unwrap_or_else(|e| fail("stdin", e));
+		match mode {
+			Mode::Check if formatted.as_bytes() != input => exit(1),
+			Mode::Check => {},
+			_ => io::stdout().write_all(formatted.as_bytes()).unwrap_or_else(|e| fail("stdout", e)),
+		}
+		return;
+	}
+
+	let mut unformatted = false;
+	for file in &files {
+		let input = fs::read(file).unwrap_or_else(|e| fail(file, e));
+		let input_settings =
+			input_settings.clone().unwrap_or_else(|| if file.ends_with(".vmf") { FormatSettings::vmf_like() } else { FormatSettings::p2c_like() });
+		let formatted = format(&input, &input_settings, &settings, drop_comments).unwrap_or_else(|e| fail(file, e));
+		match mode {
+			Mode::Print => io::stdout().write_all(formatted.as_bytes()).unwrap_or_else(|e| fail("stdout", e)),
+			Mode::Check if formatted.as_bytes() != input => {
+				println!("{}", file);
+				unformatted = true;
+			},
+			Mode::Check => {},
+			Mode::InPlace if formatted.as_bytes() != input => fs::write(file, formatted).unwrap_or_else(|e| fail(file, e)),
+			Mode::InPlace => {},
+		}
+	}
+
+	if unformatted {
+		exit(1);
+	}
+}
+
+fn format(input: &[u8], input_settings: &FormatSettings, settings: &FormatSettings, drop_comments: bool) -> Result<String, Box<dyn std::error::Error>> {
+	let text = vdf::de::decode(input)?;
+	let document = Document::parse_with_settings(&text, input_settings)?;
+	if document.has_comments() && !drop_comments {
+		return Err("it has comments, which would be thrown away (use --drop-comments to format it anyway)".into());
+	}
+	Ok(document.to_object().to_string(settings)?)
+}
+
+fn preset(style: &str) -> FormatSettings {
+	FormatSettings::preset(style).unwrap_or_else(|| usage(&format!("unknown style {:?}", style)))
+}
+
+fn usage(problem: &str) -> ! {
+	eprintln!("vdf-fmt: {}\n\n{}", problem, USAGE);
+	exit(2)
+}
+
+fn fail(what: &str, err: impl std::fmt::Display) -> ! {
+	eprintln!("vdf-fmt: {}: {}", what, err);
+	exit(2)
+}
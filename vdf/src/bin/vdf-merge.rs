@@ -0,0 +1,91 @@
+//! A git merge driver for VDF files, built on `vdf::diff::merge`.
+//!
+//! This goes through `vdf::Object`, which has nowhere to keep comments, so files with comments are refused with status 2 and git
+//! falls back to its usual text merge. Conditionals and repeated keys survive.
+
+use std::fs;
+use std::process::exit;
+
+use vdf::cst::Document;
+use vdf::diff::DiffOptions;
+use vdf::ser::FormatSettings;
+use vdf::value::Object;
+
+const USAGE: &str = "\
+Usage: vdf-merge [--style STYLE] [--input-style STYLE] [--id-key KEY]... BASE OURS THEIRS
+
+Three-way merges VDF files, writing the result over OURS. Exits with status 1 if there were any conflicts, and lists them;
+our side of each one is what ends up in the file, unless we removed something they changed, which is kept. Exits with status 2,
+leaving OURS alone, if any of the files has comments, since they'd be thrown away.
+
+  --style STYLE         which preset to write the result with: vmf, p2c, beemod or gameinfo (default: p2c)
+  --input-style STYLE   which preset to read the files with, which only matters for escape sequences: use vmf for Hammer files,
+                        which don't have them (default: p2c)
+  --id-key KEY          line up repeated blocks by the value of this child key, instead of by position (can be given more than once)
+
+To use it as a git merge driver:
+
+  git config merge.vdf.driver \"vdf-merge --id-key Type %O %A %B\"
+  echo \"editoritems.txt merge=vdf\" >> .gitattributes";
+
+fn main() {
+	let mut settings = FormatSettings::p2c_like();
+	let mut input_settings = FormatSettings::p2c_like();
+	let mut options = DiffOptions::new();
+	let mut files = Vec::new();
+
+	let mut args = std::env::args().skip(1);
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--style" => settings = preset(&args.next().unwrap_or_default()),
+			"--input-style" => input_settings = preset(&args.next().unwrap_or_default()),
+			"--id-key" => options = options.with_id_key(args.next().unwrap_or_else(|| usage("--id-key needs a key"))),
+			"--help" | "-h" => {
+				println!("{}", USAGE);
+				return;
+			},
+			flag if flag.starts_with("--") => usage(&format!("unknown option {}", flag)),
+			_ => files.push(arg),
+		}
+	}
+
+	let (base, ours, theirs) = match files.as_slice() {
+		[base, ours, theirs] => (base, ours, theirs),
+		_ => usage("expected three files"),
+	};
+
+	let merge = vdf::diff::merge(&load(base, &input_settings), &load(ours, &input_settings), &load(theirs, &input_settings), &options);
+	let merged = merge.merged.to_string(&settings).unwrap_or_else(|e| fail(ours, e));
+	fs::write(ours, merged).unwrap_or_else(|e| fail(ours, e));
+
+	if !merge.is_clean() {
+		for conflict in &merge.conflicts {
+			eprintln!("vdf-merge: conflict at {}", conflict);
+		}
+		exit(1);
+	}
+}
+
+fn load(file: &str, settings: &FormatSettings) -> Object {
+	let input = fs::read(file).unwrap_or_else(|e| fail(file, e));
+	let text = vdf::de::decode(&input).unwrap_or_else(|e| fail(file, e));
+	let document = Document::parse_with_settings(&text, settings).unwrap_or_else(|e| fail(file, e));
+	if document.has_comments() {
+		fail(file, "it has comments, which would be thrown away");
+	}
+	document.to_object()
+}
+
+fn preset(style: &str) -> FormatSettings {
+	FormatSettings::preset(style).unwrap_or_else(|| usage(&format!("unknown style {:?}", style)))
+}
+
+fn usage(problem: &str) -> ! {
+	eprintln!("vdf-merge: {}\n\n{}", problem, USAGE);
+	exit(2)
+}
+
+fn fail(what: &str, err: impl std::fmt::Display) -> ! {
+	eprintln!("vdf-merge: {}: {}", what, err);
+	exit(2)
+}
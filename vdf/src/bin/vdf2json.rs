@@ -0,0 +1,73 @@
+//! Converts a VDF file to JSON. See `vdf::json` for how repeated keys and conditionals come out.
+
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::process::exit;
+
+use vdf::ser::FormatSettings;
+
+const USAGE: &str = "\
+Usage: vdf2json [--compact] [--style vmf|p2c|beemod|gameinfo] [FILE]
+
+Converts a VDF file to JSON. With no file, reads stdin. Writes to stdout.
+
+  --compact       all on one line, instead of indented
+  --style STYLE   only matters for escape sequences: the vmf style doesn't have them (default: p2c)";
+
+fn main() {
+	let mut settings = FormatSettings::p2c_like();
+	let mut compact = false;
+	let mut file = None;
+
+	let mut args = std::env::args().skip(1);
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--style" => {
+				let style = args.next().unwrap_or_default();
+				settings = FormatSettings::preset(&style).unwrap_or_else(|| usage(&format!("unknown style {:?}", style)));
+			},
+			"--compact" => compact = true,
+			"--help" | "-h" => {
+				println!("{}", USAGE);
+				return;
+			},
+			flag if flag.starts_with("--") => usage(&format!("unknown option {}", flag)),
+			_ if file.is_some() => usage("only one file at a time"),
+			_ => file = Some(arg),
+		}
+	}
+
+	let name = file.as_deref().unwrap_or("stdin");
+	let input = match &file {
+		Some(file) => fs::read(file),
+		None => {
+			let mut input = Vec::new();
+			io::stdin().read_to_end(&mut input).map(|_| input)
+		},
+	}
+	.unwrap_or_else(|e| fail(name, e));
+
+	let result = vdf::de::decode(&input).and_then(|text| vdf::de::VdfDeserializer::with_settings(&text, settings).parse_object()).and_then(|object| {
+		let stdout = io::stdout();
+		let mut out = stdout.lock();
+		if compact {
+			vdf::json::to_writer(&mut out, &object)?;
+		} else {
+			vdf::json::to_writer_pretty(&mut out, &object)?;
+		}
+		Ok(writeln!(out)?)
+	});
+	result.unwrap_or_else(|e| fail(name, e));
+}
+
+fn usage(problem: &str) -> ! {
+	eprintln!("vdf2json: {}\n\n{}", problem, USAGE);
+	exit(2)
+}
+
+fn fail(what: &str, err: impl std::fmt::Display) -> ! {
+	eprintln!("vdf2json: {}: {}", what, err);
+	exit(2)
+}
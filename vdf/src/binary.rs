@@ -0,0 +1,947 @@
+//! Binary VDF, the format Steam uses for `appinfo.vdf`, `shortcuts.vdf` and friends.
+//!
+//! A binary VDF file is a list of entries, ended by a `0x08` byte. Each entry is a type byte, a NUL-terminated key, and a value
+//! whose encoding depends on the type:
+//!
+//! * `0x00`: a nested block, which is another list of entries ended by `0x08`.
+//! * `0x01`: a NUL-terminated string.
+//! * `0x02`: a little-endian 32-bit integer.
+//! * `0x03`: a little-endian 32-bit float.
+//! * `0x07`: a little-endian unsigned 64-bit integer.
+//! * `0x0A`: a little-endian signed 64-bit integer.
+//!
+//! The same Serde structures used with the text format work here, too. Sequences are blocks with the keys `"0"`, `"1"`, `"2"`...
+//! (which is exactly how `shortcuts.vdf` stores its list of shortcuts). Unlike the text format, newtype structs are transparent,
+//! since there's no sensible place to put their name.
+
+#![allow(unused_variables)]
+
+use serde::de;
+use serde::ser;
+use serde::serde_if_integer128;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::error::VdfErr;
+use crate::ser::ScalarSerializer;
+
+const SUBKEY: u8 = 0x00;
+const STRING: u8 = 0x01;
+const INT32: u8 = 0x02;
+const FLOAT32: u8 = 0x03;
+const UINT64: u8 = 0x07;
+const END: u8 = 0x08;
+const INT64: u8 = 0x0A;
+
+/// Serialize the given data structure as binary VDF. It must serialize as a block, like a struct or a map.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to fail, if `T` isn't a block, or if a string contains a NUL byte.
+pub fn to_vec<T>(value: &T) -> Result<Vec<u8>, VdfErr>
+where
+	T: ?Sized + Serialize,
+{
+	let mut out = Vec::new();
+	value.serialize(ValueSerializer { out: &mut out, key: None })?;
+	Ok(out)
+}
+
+/// Serialize the given data structure as binary VDF, where the name of the top-level block is provided.
+///
+/// See `vdf::ser::to_string_with_toplevel_block`.
+pub fn to_vec_with_toplevel_block<T>(value: &T, toplevel_block_name: &str) -> Result<Vec<u8>, VdfErr>
+where
+	T: ?Sized + Serialize,
+{
+	let mut out = Vec::new();
+	value.serialize(ValueSerializer { out: &mut out, key: Some(toplevel_block_name) })?;
+	out.push(END);
+	Ok(out)
+}
+
+/// Deserialize an instance of type `T` from binary VDF.
+pub fn from_slice<'a, T>(input: &'a [u8]) -> Result<T, VdfErr>
+where
+	T: Deserialize<'a>,
+{
+	let mut de = BinaryDeserializer { input };
+	let value = T::deserialize(ValueDeserializer { de: &mut de, ty: None })?;
+	de.end(true)?;
+	Ok(value)
+}
+
+/// Deserialize an instance of type `T` from binary VDF, where the file consists of one top-level block with the given name.
+///
+/// See `vdf::de::from_str_with_toplevel_block`.
+pub fn from_slice_with_toplevel_block<'a, T>(input: &'a [u8], toplevel_block_name: &str) -> Result<T, VdfErr>
+where
+	T: Deserialize<'a>,
+{
+	let mut de = BinaryDeserializer { input };
+	let ty = de.byte()?;
+	let key = de.cstr()?;
+	if key != toplevel_block_name {
+		return Err(VdfErr::UnexpectedKey { expected: toplevel_block_name.to_string(), found: key.to_string() });
+	}
+	let value = T::deserialize(ValueDeserializer { de: &mut de, ty: Some(ty) })?;
+	de.end(true)?;
+	Ok(value)
+}
+
+// Serialization
+
+/// Serializes one value. The key is written along with the type byte, right before the value.
+///
+/// At the top level of the file there's no key, and only blocks can be written.
+struct ValueSerializer<'a> {
+	out: &'a mut Vec<u8>,
+	key: Option<&'a str>,
+}
+
+impl<'a> ValueSerializer<'a> {
+	fn header(&mut self, ty: u8) -> Result<(), VdfErr> {
+		match self.key {
+			Some(key) => {
+				self.out.push(ty);
+				write_cstr(self.out, key)
+			},
+			None if ty == SUBKEY => Ok(()),
+			None => Err(VdfErr::ExpectedBlock),
+		}
+	}
+
+	fn block(mut self) -> Result<BlockSerializer<'a>, VdfErr> {
+		self.header(SUBKEY)?;
+		Ok(BlockSerializer { out: self.out, key: String::new(), index: 0, ends: 1 })
+	}
+
+	fn int32(mut self, v: i32) -> Result<(), VdfErr> {
+		self.header(INT32)?;
+		self.out.extend_from_slice(&v.to_le_bytes());
+		Ok(())
+	}
+}
+
+fn write_cstr(out: &mut Vec<u8>, s: &str) -> Result<(), VdfErr> {
+	if s.contains('\0') {
+		return Err(VdfErr::Unrepresentable(s.to_string()));
+	}
+	out.extend_from_slice(s.as_bytes());
+	out.push(0);
+	Ok(())
+}
+
+impl<'a> ser::Serializer for ValueSerializer<'a> {
+	type Ok = ();
+	type Error = VdfErr;
+
+	type SerializeSeq = BlockSerializer<'a>;
+	type SerializeTuple = BlockSerializer<'a>;
+	type SerializeTupleStruct = BlockSerializer<'a>;
+	type SerializeTupleVariant = BlockSerializer<'a>;
+	type SerializeMap = BlockSerializer<'a>;
+	type SerializeStruct = BlockSerializer<'a>;
+	type SerializeStructVariant = BlockSerializer<'a>;
+
+	fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+		self.int32(v as i32)
+	}
+
+	fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+		self.int32(v.into())
+	}
+
+	fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+		self.int32(v.into())
+	}
+
+	fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+		self.int32(v)
+	}
+
+	fn serialize_i64(mut self, v: i64) -> Result<Self::Ok, Self::Error> {
+		self.header(INT64)?;
+		self.out.extend_from_slice(&v.to_le_bytes());
+		Ok(())
+	}
+
+	fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+		self.int32(v.into())
+	}
+
+	fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+		self.int32(v.into())
+	}
+
+	fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+		//Steam stores unsigned 32-bit numbers (like app IDs) in the signed type, so do the same
+		self.int32(v as i32)
+	}
+
+	fn serialize_u64(mut self, v: u64) -> Result<Self::Ok, Self::Error> {
+		self.header(UINT64)?;
+		self.out.extend_from_slice(&v.to_le_bytes());
+		Ok(())
+	}
+
+	serde_if_integer128! {
+		fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+			self.serialize_str(&v.to_string())
+		}
+
+		fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+			self.serialize_str(&v.to_string())
+		}
+	}
+
+	fn serialize_f32(mut self, v: f32) -> Result<Self::Ok, Self::Error> {
+		self.header(FLOAT32)?;
+		self.out.extend_from_slice(&v.to_le_bytes());
+		Ok(())
+	}
+
+	/// There's no 64-bit float type, so only numbers that fit in an `f32` exactly can be written.
+	fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+		if v as f32 as f64 != v && !v.is_nan() {
+			return Err(VdfErr::Unrepresentable(v.to_string()));
+		}
+		self.serialize_f32(v as f32)
+	}
+
+	fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+		self.serialize_str(v.encode_utf8(&mut [0; 4]))
+	}
+
+	fn serialize_str(mut self, v: &str) -> Result<Self::Ok, Self::Error> {
+		self.header(STRING)?;
+		write_cstr(self.out, v)
+	}
+
+	fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+		let s = std::str::from_utf8(v).map_err(VdfErr::Utf8)?;
+		self.serialize_str(s)
+	}
+
+	fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+		self.serialize_str("")
+	}
+
+	fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+	where
+		T: ?Sized + Serialize,
+	{
+		value.serialize(self)
+	}
+
+	fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+		self.serialize_str("")
+	}
+
+	fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Self::Error> {
+		self.serialize_unit()
+	}
+
+	fn serialize_unit_variant(self, name: &'static str, variant_index: u32, variant: &'static str) -> Result<Self::Ok, Self::Error> {
+		self.serialize_str(variant)
+	}
+
+	fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Self::Ok, Self::Error>
+	where
+		T: ?Sized + Serialize,
+	{
+		value.serialize(self)
+	}
+
+	fn serialize_newtype_variant<T>(self, name: &'static str, variant_index: u32, variant: &'static str, value: &T) -> Result<Self::Ok, Self::Error>
+	where
+		T: ?Sized + Serialize,
+	{
+		let block = self.block()?;
+		value.serialize(ValueSerializer { out: &mut *block.out, key: Some(variant) })?;
+		ser::SerializeMap::end(block)
+	}
+
+	fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+		self.block()
+	}
+
+	fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+		self.block()
+	}
+
+	fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct, Self::Error> {
+		self.block()
+	}
+
+	fn serialize_tuple_variant(
+		self,
+		name: &'static str,
+		variant_index: u32,
+		variant: &'static str,
+		len: usize,
+	) -> Result<Self::SerializeTupleVariant, Self::Error> {
+		let block = self.block()?;
+		let mut inner = ValueSerializer { out: block.out, key: Some(variant) }.block()?;
+		inner.ends = 2;
+		Ok(inner)
+	}
+
+	fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+		self.block()
+	}
+
+	fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct, Self::Error> {
+		self.block()
+	}
+
+	fn serialize_struct_variant(
+		self,
+		name: &'static str,
+		variant_index: u32,
+		variant: &'static str,
+		len: usize,
+	) -> Result<Self::SerializeStructVariant, Self::Error> {
+		self.serialize_tuple_variant(name, variant_index, variant, len)
+	}
+}
+
+/// Writes the entries of a block, then the `0x08` that ends it.
+struct BlockSerializer<'a> {
+	out: &'a mut Vec<u8>,
+	/// The key of the next map entry.
+	key: String,
+	/// The key of the next sequence element.
+	index: u32,
+	/// How many blocks to close at the end. Enum variants are written as a block inside a block.
+	ends: usize,
+}
+
+impl<'a> BlockSerializer<'a> {
+	fn element<T>(&mut self, value: &T) -> Result<(), VdfErr>
+	where
+		T: ?Sized + Serialize,
+	{
+		let key = self.index.to_string();
+		self.index += 1;
+		value.serialize(ValueSerializer { out: self.out, key: Some(&key) })
+	}
+
+	fn finish(self) -> Result<(), VdfErr> {
+		for _ in 0..self.ends {
+			self.out.push(END);
+		}
+		Ok(())
+	}
+}
+
+impl<'a> ser::SerializeSeq for BlockSerializer<'a> {
+	type Ok = ();
+	type Error = VdfErr;
+
+	fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+	where
+		T: ?Sized + Serialize,
+	{
+		self.element(value)
+	}
+
+	fn end(self) -> Result<Self::Ok, Self::Error> {
+		self.finish()
+	}
+}
+
+impl<'a> ser::SerializeTuple for BlockSerializer<'a> {
+	type Ok = ();
+	type Error = VdfErr;
+
+	fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+	where
+		T: ?Sized + Serialize,
+	{
+		self.element(value)
+	}
+
+	fn end(self) -> Result<Self::Ok, Self::Error> {
+		self.finish()
+	}
+}
+
+impl<'a> ser::SerializeTupleStruct for BlockSerializer<'a> {
+	type Ok = ();
+	type Error = VdfErr;
+
+	fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+	where
+		T: ?Sized + Serialize,
+	{
+		self.element(value)
+	}
+
+	fn end(self) -> Result<Self::Ok, Self::Error> {
+		self.finish()
+	}
+}
+
+impl<'a> ser::SerializeTupleVariant for BlockSerializer<'a> {
+	type Ok = ();
+	type Error = VdfErr;
+
+	fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+	where
+		T: ?Sized + Serialize,
+	{
+		self.element(value)
+	}
+
+	fn end(self) -> Result<Self::Ok, Self::Error> {
+		self.finish()
+	}
+}
+
+impl<'a> ser::SerializeMap for BlockSerializer<'a> {
+	type Ok = ();
+	type Error = VdfErr;
+
+	fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
+	where
+		T: ?Sized + Serialize,
+	{
+		self.key = key.serialize(ScalarSerializer)?;
+		Ok(())
+	}
+
+	fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
+	where
+		T: ?Sized + Serialize,
+	{
+		value.serialize(ValueSerializer { out: self.out, key: Some(&self.key) })
+	}
+
+	fn end(self) -> Result<Self::Ok, Self::Error> {
+		self.finish()
+	}
+}
+
+impl<'a> ser::SerializeStruct for BlockSerializer<'a> {
+	type Ok = ();
+	type Error = VdfErr;
+
+	fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+	where
+		T: ?Sized + Serialize,
+	{
+		value.serialize(ValueSerializer { out: self.out, key: Some(key) })
+	}
+
+	fn end(self) -> Result<Self::Ok, Self::Error> {
+		self.finish()
+	}
+}
+
+impl<'a> ser::SerializeStructVariant for BlockSerializer<'a> {
+	type Ok = ();
+	type Error = VdfErr;
+
+	fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+	where
+		T: ?Sized + Serialize,
+	{
+		value.serialize(ValueSerializer { out: self.out, key: Some(key) })
+	}
+
+	fn end(self) -> Result<Self::Ok, Self::Error> {
+		self.finish()
+	}
+}
+
+// Deserialization
+
+struct BinaryDeserializer<'de> {
+	input: &'de [u8],
+}
+
+impl<'de> BinaryDeserializer<'de> {
+	fn byte(&mut self) -> Result<u8, VdfErr> {
+		let (&b, rest) = self.input.split_first().ok_or(VdfErr::EndOfFile)?;
+		self.input = rest;
+		Ok(b)
+	}
+
+	fn bytes<const N: usize>(&mut self) -> Result<[u8; N], VdfErr> {
+		if self.input.len() < N {
+			return Err(VdfErr::EndOfFile);
+		}
+		let (car, cdr) = self.input.split_at(N);
+		self.input = cdr;
+		let mut out = [0; N];
+		out.copy_from_slice(car);
+		Ok(out)
+	}
+
+	fn cstr(&mut self) -> Result<&'de str, VdfErr> {
+		let nul = self.input.iter().position(|&b| b == 0).ok_or(VdfErr::EndOfFile)?;
+		let s = std::str::from_utf8(&self.input[..nul]).map_err(VdfErr::Utf8)?;
+		self.input = &self.input[nul + 1..];
+		Ok(s)
+	}
+
+	/// Call after deserializing the top-level value, to make sure nothing is left over. `terminated` allows one more `0x08`
+	/// closing off the whole file, which Steam writes after a top-level block.
+	fn end(&mut self, terminated: bool) -> Result<(), VdfErr> {
+		match self.input {
+			[] => Ok(()),
+			[END] if terminated => Ok(()),
+			_ => Err(VdfErr::TrailingCharacters),
+		}
+	}
+
+	/// Reads past the payload of a value of the given type.
+	fn skip(&mut self, ty: u8) -> Result<(), VdfErr> {
+		match ty {
+			SUBKEY => loop {
+				match self.byte()? {
+					END => return Ok(()),
+					ty => {
+						self.cstr()?;
+						self.skip(ty)?;
+					},
+				}
+			},
+			STRING => self.cstr().map(|_| ()),
+			INT32 | FLOAT32 => self.bytes::<4>().map(|_| ()),
+			UINT64 | INT64 => self.bytes::<8>().map(|_| ()),
+			other => Err(VdfErr::BadType(other)),
+		}
+	}
+}
+
+/// Deserializes one value, whose type byte has already been read. A type of `None` means the top level of the file, which is
+/// a block that may or may not have its terminating `0x08`.
+struct ValueDeserializer<'a, 'de> {
+	de: &'a mut BinaryDeserializer<'de>,
+	ty: Option<u8>,
+}
+
+impl<'a, 'de> ValueDeserializer<'a, 'de> {
+	fn int32(&mut self) -> Result<Option<i32>, VdfErr> {
+		if self.ty == Some(INT32) {
+			self.de.bytes::<4>().map(|b| Some(i32::from_le_bytes(b)))
+		} else {
+			Ok(None)
+		}
+	}
+
+	fn block(&mut self) -> Result<BlockAccess<'_, 'de>, VdfErr> {
+		match self.ty {
+			None | Some(SUBKEY) => Ok(BlockAccess { de: &mut *self.de, toplevel: self.ty.is_none(), ty: SUBKEY, done: false }),
+			Some(_) => Err(VdfErr::ExpectedBlock),
+		}
+	}
+}
+
+macro_rules! from_int32 {
+	( $func:ident | $visit:ident | $type:ty ) => {
+		fn $func<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
+		where
+			V: de::Visitor<'de>,
+		{
+			match self.int32()? {
+				//Bit-cast, to undo what the serializer does for unsigned numbers
+				Some(i) => visitor.$visit(i as $type),
+				None => self.deserialize_any(visitor),
+			}
+		}
+	};
+}
+
+impl<'a, 'de> de::Deserializer<'de> for ValueDeserializer<'a, 'de> {
+	type Error = VdfErr;
+
+	fn deserialize_any<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		match self.ty {
+			None | Some(SUBKEY) => visitor.visit_map(self.block()?),
+			Some(STRING) => visitor.visit_borrowed_str(self.de.cstr()?),
+			Some(INT32) => visitor.visit_i32(i32::from_le_bytes(self.de.bytes()?)),
+			Some(FLOAT32) => visitor.visit_f32(f32::from_le_bytes(self.de.bytes()?)),
+			Some(UINT64) => visitor.visit_u64(u64::from_le_bytes(self.de.bytes()?)),
+			Some(INT64) => visitor.visit_i64(i64::from_le_bytes(self.de.bytes()?)),
+			Some(other) => Err(VdfErr::BadType(other)),
+		}
+	}
+
+	fn deserialize_bool<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		match self.int32()? {
+			Some(i) => visitor.visit_bool(i != 0),
+			None if self.ty == Some(STRING) => match self.de.cstr()? {
+				"1" | "true" => visitor.visit_bool(true),
+				"0" | "false" => visitor.visit_bool(false),
+				s => Err(VdfErr::ParseBool(s.to_string())),
+			},
+			None => self.deserialize_any(visitor),
+		}
+	}
+
+	from_int32!(deserialize_u8 | visit_u8 | u8);
+	from_int32!(deserialize_u16 | visit_u16 | u16);
+	from_int32!(deserialize_u32 | visit_u32 | u32);
+
+	fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		//Structures written for the text format might have strings where the binary file has numbers.
+		match self.ty {
+			Some(INT32) => visitor.visit_string(i32::from_le_bytes(self.de.bytes()?).to_string()),
+			Some(FLOAT32) => visitor.visit_string(f32::from_le_bytes(self.de.bytes()?).to_string()),
+			Some(UINT64) => visitor.visit_string(u64::from_le_bytes(self.de.bytes()?).to_string()),
+			Some(INT64) => visitor.visit_string(i64::from_le_bytes(self.de.bytes()?).to_string()),
+			_ => self.deserialize_any(visitor),
+		}
+	}
+
+	fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.deserialize_str(visitor)
+	}
+
+	fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		//`None` is written as an empty string
+		if self.ty == Some(STRING) && self.de.input.first() == Some(&0) {
+			self.de.byte()?;
+			visitor.visit_none()
+		} else {
+			visitor.visit_some(self)
+		}
+	}
+
+	fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.de.skip(self.ty.unwrap_or(SUBKEY))?;
+		visitor.visit_unit()
+	}
+
+	fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.deserialize_unit(visitor)
+	}
+
+	fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		visitor.visit_newtype_struct(self)
+	}
+
+	fn deserialize_seq<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		//Tuples stop reading once they have enough elements, so make sure the block ends there
+		let mut block = self.block()?;
+		let value = visitor.visit_seq(&mut block)?;
+		block.finish()?;
+		Ok(value)
+	}
+
+	fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.deserialize_seq(visitor)
+	}
+
+	fn deserialize_tuple_struct<V>(self, name: &'static str, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.deserialize_seq(visitor)
+	}
+
+	fn deserialize_enum<V>(mut self, name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		match self.ty {
+			Some(STRING) => visitor.visit_enum(de::value::BorrowedStrDeserializer::new(self.de.cstr()?)),
+			_ => visitor.visit_enum(self.block()?),
+		}
+	}
+
+	fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.deserialize_unit(visitor)
+	}
+
+	serde::forward_to_deserialize_any! {
+		i8 i16 i32 i64 i128 u64 u128 f32 f64 char bytes byte_buf map struct identifier
+	}
+}
+
+/// Walks over the entries of a block.
+struct BlockAccess<'a, 'de> {
+	de: &'a mut BinaryDeserializer<'de>,
+	/// The top-level block is allowed to end at the end of the input, without a `0x08`.
+	toplevel: bool,
+	/// The type byte of the entry whose value is read next.
+	ty: u8,
+	/// Whether the end of the block was read.
+	done: bool,
+}
+
+impl<'a, 'de> BlockAccess<'a, 'de> {
+	/// Reads the type byte and key of the next entry, returning `None` at the end of the block.
+	fn next_entry(&mut self) -> Result<Option<&'de str>, VdfErr> {
+		if self.toplevel && self.de.input.is_empty() {
+			self.done = true;
+			return Ok(None);
+		}
+
+		match self.de.byte()? {
+			END => {
+				self.done = true;
+				Ok(None)
+			},
+			ty => {
+				self.ty = ty;
+				self.de.cstr().map(Some)
+			},
+		}
+	}
+
+	fn value(&mut self) -> ValueDeserializer<'_, 'de> {
+		ValueDeserializer { de: &mut *self.de, ty: Some(self.ty) }
+	}
+
+	/// Expects the end of a block that was read until the last element it wanted.
+	fn finish(&mut self) -> Result<(), VdfErr> {
+		if self.done {
+			return Ok(());
+		}
+		match self.next_entry()? {
+			None => Ok(()),
+			Some(key) => Err(VdfErr::ExtraKey(key.to_string())),
+		}
+	}
+
+	/// Expects the end of the block containing an enum variant.
+	fn end(&mut self) -> Result<(), VdfErr> {
+		match self.de.byte()? {
+			END => Ok(()),
+			other => Err(VdfErr::BadType(other)),
+		}
+	}
+}
+
+impl<'a, 'de> de::MapAccess<'de> for BlockAccess<'a, 'de> {
+	type Error = VdfErr;
+
+	fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+	where
+		K: de::DeserializeSeed<'de>,
+	{
+		match self.next_entry()? {
+			Some(key) => seed.deserialize(de::value::BorrowedStrDeserializer::new(key)).map(Some),
+			None => Ok(None),
+		}
+	}
+
+	fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::DeserializeSeed<'de>,
+	{
+		seed.deserialize(self.value())
+	}
+}
+
+impl<'a, 'de> de::SeqAccess<'de> for BlockAccess<'a, 'de> {
+	type Error = VdfErr;
+
+	fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+	where
+		T: de::DeserializeSeed<'de>,
+	{
+		//The keys of a sequence ("0", "1", "2"...) aren't important when reading.
+		match self.next_entry()? {
+			Some(_) => seed.deserialize(self.value()).map(Some),
+			None => Ok(None),
+		}
+	}
+}
+
+impl<'a, 'de> de::EnumAccess<'de> for BlockAccess<'a, 'de> {
+	type Error = VdfErr;
+	type Variant = Self;
+
+	fn variant_seed<V>(mut self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+	where
+		V: de::DeserializeSeed<'de>,
+	{
+		//Data-carrying variants are a block containing one entry, named after the variant.
+		match self.next_entry()? {
+			Some(key) => {
+				let variant = seed.deserialize(de::value::BorrowedStrDeserializer::<VdfErr>::new(key))?;
+				Ok((variant, self))
+			},
+			None => Err(VdfErr::ExpectedBlock),
+		}
+	}
+}
+
+impl<'a, 'de> de::VariantAccess<'de> for BlockAccess<'a, 'de> {
+	type Error = VdfErr;
+
+	fn unit_variant(mut self) -> Result<(), Self::Error> {
+		self.de.skip(self.ty)?;
+		self.end()
+	}
+
+	fn newtype_variant_seed<T>(mut self, seed: T) -> Result<T::Value, Self::Error>
+	where
+		T: de::DeserializeSeed<'de>,
+	{
+		let value = seed.deserialize(self.value())?;
+		self.end()?;
+		Ok(value)
+	}
+
+	fn tuple_variant<V>(mut self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		let value = de::Deserializer::deserialize_seq(self.value(), visitor)?;
+		self.end()?;
+		Ok(value)
+	}
+
+	fn struct_variant<V>(mut self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		let value = de::Deserializer::deserialize_map(self.value(), visitor)?;
+		self.end()?;
+		Ok(value)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::collections::BTreeMap;
+
+	use serde::Deserialize;
+	use serde::Serialize;
+
+	use super::*;
+
+	#[derive(Debug, PartialEq, Serialize, Deserialize)]
+	struct Shortcuts {
+		shortcuts: Vec<Shortcut>,
+	}
+
+	#[derive(Debug, PartialEq, Serialize, Deserialize)]
+	struct Shortcut {
+		appid: u32,
+		#[serde(rename = "AppName")]
+		app_name: String,
+		#[serde(rename = "IsHidden")]
+		is_hidden: bool,
+		#[serde(rename = "LastPlayTime")]
+		last_play_time: i32,
+		tags: BTreeMap<String, String>,
+	}
+
+	fn shortcuts() -> Shortcuts {
+		let mut tags = BTreeMap::new();
+		tags.insert("0".to_string(), "favorite".to_string());
+		Shortcuts { shortcuts: vec![Shortcut { appid: 0xDEAD_BEEF, app_name: "Portal 2".into(), is_hidden: false, last_play_time: 1234, tags }] }
+	}
+
+	#[test]
+	fn shortcuts_layout() {
+		let mut expected = Vec::new();
+		expected.extend_from_slice(b"\x00shortcuts\x00");
+		expected.extend_from_slice(b"\x000\x00");
+		expected.extend_from_slice(b"\x02appid\x00\xEF\xBE\xAD\xDE");
+		expected.extend_from_slice(b"\x01AppName\x00Portal 2\x00");
+		expected.extend_from_slice(b"\x02IsHidden\x00\x00\x00\x00\x00");
+		expected.extend_from_slice(b"\x02LastPlayTime\x00\xD2\x04\x00\x00");
+		expected.extend_from_slice(b"\x00tags\x00\x010\x00favorite\x00\x08");
+		expected.extend_from_slice(b"\x08\x08\x08");
+
+		assert_eq!(to_vec(&shortcuts()).unwrap(), expected);
+		//Steam's files have one more 0x08 for the root, but it's optional
+		assert_eq!(from_slice::<Shortcuts>(&expected).unwrap(), shortcuts());
+		expected.push(END);
+		assert_eq!(from_slice::<Shortcuts>(&expected).unwrap(), shortcuts());
+	}
+
+	#[test]
+	fn round_trip() {
+		#[derive(Debug, PartialEq, Serialize, Deserialize)]
+		enum Shape {
+			Point,
+			Circle(f32),
+			Rect { w: u64, h: i64 },
+		}
+
+		#[derive(Debug, PartialEq, Serialize, Deserialize)]
+		struct Doc<'a> {
+			name: &'a str,
+			small: i8,
+			shapes: Vec<Shape>,
+			missing: Option<String>,
+			count: Option<u32>,
+			pair: (u16, String),
+		}
+
+		let doc = Doc {
+			name: "borrowed",
+			small: -5,
+			shapes: vec![Shape::Point, Shape::Circle(1.5), Shape::Rect { w: u64::MAX, h: -1 }],
+			missing: None,
+			count: None,
+			pair: (7, "seven".into()),
+		};
+
+		let bytes = to_vec_with_toplevel_block(&doc, "Doc").unwrap();
+		let back: Doc = from_slice_with_toplevel_block(&bytes, "Doc").unwrap();
+		assert_eq!(back, doc);
+
+		//Also readable into the generic document tree, with numbers turned into strings
+		let obj: crate::Object = from_slice(&bytes).unwrap();
+		assert_eq!(obj["Doc"]["small"].as_str(), Some("-5"));
+		assert_eq!(obj["Doc"]["shapes"]["2"]["Rect"]["w"].as_str(), Some("18446744073709551615"));
+	}
+
+	#[test]
+	fn errors() {
+		assert!(matches!(to_vec(&5), Err(VdfErr::ExpectedBlock)));
+		assert!(matches!(to_vec(&BTreeMap::from([("a", "b\0c")])), Err(VdfErr::Unrepresentable(_))));
+		assert!(matches!(to_vec(&BTreeMap::from([("a", 0.1f64)])), Err(VdfErr::Unrepresentable(v)) if v == "0.1"));
+		assert!(matches!(to_vec(&BTreeMap::from([("a", 1e300)])), Err(VdfErr::Unrepresentable(_))));
+		assert_eq!(to_vec(&BTreeMap::from([("a", 0.25f64)])).unwrap(), to_vec(&BTreeMap::from([("a", 0.25f32)])).unwrap());
+		assert!(matches!(from_slice::<BTreeMap<String, String>>(b"\x01a\x00b"), Err(VdfErr::EndOfFile)));
+		assert!(matches!(from_slice::<crate::Object>(b"\x05a\x00"), Err(VdfErr::BadType(5))));
+		assert!(matches!(from_slice::<BTreeMap<String, String>>(b"\x01a\x00\xFF\x00"), Err(VdfErr::Utf8(_))));
+		assert!(matches!(from_slice::<BTreeMap<String, String>>(b"\x01a\x00b\x00\x08\x08\x08"), Err(VdfErr::TrailingCharacters)));
+		assert!(matches!(from_slice::<BTreeMap<String, (String,)>>(b"\x00t\x00\x010\x001\x00\x011\x002\x00\x08"), Err(VdfErr::ExtraKey(key)) if key == "1"));
+		assert!(matches!(from_slice_with_toplevel_block::<BTreeMap<String, String>>(b"\x00D\x00\x08\x08\x08", "D"), Err(VdfErr::TrailingCharacters)));
+	}
+}
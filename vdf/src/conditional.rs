@@ -0,0 +1,214 @@
+//! Platform conditionals, like the `[$WIN32]` in `"key" "value" [$WIN32]`.
+//!
+//! Source engine KeyValues files can tag a key-value pair (or a whole block) with a conditional expression in square brackets.
+//! The pair only "exists" if the expression is true. Expressions are made of symbols like `$WINDOWS` or `$X360`, combined with
+//! `!`, `&&`, `||` and parentheses.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::iter::FromIterator;
+
+use crate::error::VdfErr;
+
+/// A conditional expression, stored as the text that appears between the square brackets.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Conditional {
+	text: String,
+}
+
+/// A set of defined symbols to evaluate conditionals against.
+///
+/// Symbols are case-insensitive, and the leading `$` is optional: `"WIN32"`, `"$WIN32"` and `"$win32"` are all the same symbol.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Symbols {
+	defined: HashSet<String>,
+}
+
+impl Conditional {
+	/// Parses a conditional from the text between the square brackets, like `!$X360 && !$PS3`.
+	pub fn new<S>(text: S) -> Result<Self, VdfErr>
+	where
+		S: Into<String>,
+	{
+		let cond = Conditional { text: text.into() };
+		//Evaluating the expression once checks that it's well-formed.
+		cond.evaluate(&Symbols::new())?;
+		Ok(cond)
+	}
+
+	pub fn as_str(&self) -> &str {
+		&self.text
+	}
+
+	/// Returns whether the expression is true when exactly the given symbols are defined.
+	pub fn evaluate(&self, symbols: &Symbols) -> Result<bool, VdfErr> {
+		let tokens = tokenize(&self.text).ok_or_else(|| VdfErr::BadConditional(self.text.clone()))?;
+		let mut parser = Parser { tokens: &tokens, symbols };
+		match parser.or() {
+			Some(result) if parser.tokens.is_empty() => Ok(result),
+			_ => Err(VdfErr::BadConditional(self.text.clone())),
+		}
+	}
+
+	/// Like `evaluate`, for conditionals that are already known to be well-formed.
+	pub(crate) fn is_true(&self, symbols: &Symbols) -> bool {
+		self.evaluate(symbols).unwrap_or(false)
+	}
+}
+
+impl fmt::Display for Conditional {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "[{}]", self.text)
+	}
+}
+
+impl Symbols {
+	pub fn new() -> Self {
+		Symbols { defined: HashSet::new() }
+	}
+
+	pub fn define(&mut self, symbol: &str) {
+		self.defined.insert(normalize(symbol));
+	}
+
+	pub fn undefine(&mut self, symbol: &str) {
+		self.defined.remove(&normalize(symbol));
+	}
+
+	pub fn is_defined(&self, symbol: &str) -> bool {
+		self.defined.contains(&normalize(symbol))
+	}
+}
+
+impl<S> FromIterator<S> for Symbols
+where
+	S: AsRef<str>,
+{
+	fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+		Symbols { defined: iter.into_iter().map(|s| normalize(s.as_ref())).collect() }
+	}
+}
+
+fn normalize(symbol: &str) -> String {
+	symbol.trim_start_matches('$').to_ascii_uppercase()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token<'a> {
+	Symbol(&'a str),
+	Not,
+	And,
+	Or,
+	Open,
+	Close,
+}
+
+fn tokenize(text: &str) -> Option<Vec<Token<'_>>> {
+	let mut tokens = Vec::new();
+	let mut rest = text.trim_start();
+	while !rest.is_empty() {
+		let (token, len) = if rest.starts_with("&&") {
+			(Token::And, 2)
+		} else if rest.starts_with("||") {
+			(Token::Or, 2)
+		} else if rest.starts_with('!') {
+			(Token::Not, 1)
+		} else if rest.starts_with('(') {
+			(Token::Open, 1)
+		} else if rest.starts_with(')') {
+			(Token::Close, 1)
+		} else {
+			let len = rest.find(|c: char| !(c == '$' || c == '_' || c.is_ascii_alphanumeric())).unwrap_or(rest.len());
+			if len == 0 {
+				return None;
+			}
+			(Token::Symbol(&rest[..len]), len)
+		};
+
+		tokens.push(token);
+		rest = rest[len..].trim_start();
+	}
+	Some(tokens)
+}
+
+/// Recursive-descent evaluator. `&&` binds tighter than `||`, and `!` binds tighter than both.
+struct Parser<'t, 'a> {
+	tokens: &'t [Token<'a>],
+	symbols: &'t Symbols,
+}
+
+impl<'t, 'a> Parser<'t, 'a> {
+	fn eat(&mut self, token: Token) -> bool {
+		if self.tokens.first() == Some(&token) {
+			self.tokens = &self.tokens[1..];
+			true
+		} else {
+			false
+		}
+	}
+
+	fn or(&mut self) -> Option<bool> {
+		let mut result = self.and()?;
+		while self.eat(Token::Or) {
+			//No short-circuiting, the rest of the expression still has to be checked
+			result |= self.and()?;
+		}
+		Some(result)
+	}
+
+	fn and(&mut self) -> Option<bool> {
+		let mut result = self.unary()?;
+		while self.eat(Token::And) {
+			result &= self.unary()?;
+		}
+		Some(result)
+	}
+
+	fn unary(&mut self) -> Option<bool> {
+		if self.eat(Token::Not) {
+			return self.unary().map(|b| !b);
+		}
+
+		if self.eat(Token::Open) {
+			let result = self.or()?;
+			return if self.eat(Token::Close) { Some(result) } else { None };
+		}
+
+		match self.tokens.first()? {
+			Token::Symbol(s) => {
+				let result = self.symbols.is_defined(s);
+				self.tokens = &self.tokens[1..];
+				Some(result)
+			},
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn evaluate() {
+		let windows: Symbols = vec!["$WINDOWS", "win32"].into_iter().collect();
+
+		let cond = |s: &str| Conditional::new(s).unwrap().evaluate(&windows).unwrap();
+		assert!(cond("$WIN32"));
+		assert!(cond("$windows"));
+		assert!(!cond("$X360"));
+		assert!(cond("!$X360"));
+		assert!(cond("!$X360 && !$PS3"));
+		assert!(!cond("$OSX || $LINUX"));
+		assert!(cond("$OSX || $LINUX || $WIN32"));
+		assert!(cond("$OSX || $WIN32 && !$X360"));
+		assert!(!cond("!($OSX || $WIN32)"));
+	}
+
+	#[test]
+	fn malformed() {
+		for bad in &["", "$WIN32 &&", "&& $WIN32", "($WIN32", "$WIN32)", "$WIN32 $OSX", "$WIN32 | $OSX"] {
+			assert!(matches!(Conditional::new(*bad), Err(VdfErr::BadConditional(_))), "{:?} should not parse", bad);
+		}
+	}
+}
@@ -0,0 +1,744 @@
+//! A lossless syntax tree, for editing hand-maintained files without wrecking them.
+//!
+//! `Object` only keeps keys, values and conditionals. A `Document` also keeps every comment, blank line and bit of
+//! indentation, and remembers which strings were quoted, so writing it back out produces exactly the text it was parsed from.
+//! Edit it through `Block` and `Pair`, and only the parts you touched come out different.
+//!
+//! ```
+//! use vdf::cst::Document;
+//!
+//! let text = "\"Item\"\n{\n\t// The cube\n\t\"Type\"\t\"ITEM_CUBE\"\n\t\"Skin\"\t\"0\" [$X360]\n}\n";
+//! let mut doc: Document = text.parse().unwrap();
+//!
+//! let item = doc.root_mut().get_mut("Item").unwrap().value_mut().as_block_mut().unwrap();
+//! item.set("Skin", "2");
+//! item.push("Model", "cube.mdl");
+//!
+//! assert_eq!(doc.to_string().unwrap(), "\"Item\"\n{\n\t// The cube\n\t\"Type\"\t\"ITEM_CUBE\"\n\t\"Skin\"\t\"2\" [$X360]\n\t\"Model\"\t\"cube.mdl\"\n}\n");
+//! ```
+
+use std::fmt;
+use std::ops::Index;
+use std::str::FromStr;
+
+use crate::conditional::Conditional;
+use crate::de::unescape;
+use crate::error::Location;
+use crate::error::VdfErr;
+use crate::ser::needs_quotes;
+use crate::ser::write_string;
+use crate::ser::FormatSettings;
+use crate::value::Kv;
+use crate::value::Object;
+use crate::value::Value;
+
+/// A whole VDF file, down to the last space.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Document {
+	/// Whitespace and comments before the opening brace, if the top level of the file has braces around it.
+	before: String,
+	braced: bool,
+	root: Block,
+	/// Whitespace and comments after the closing brace, if the top level of the file has braces around it.
+	after: String,
+	/// If `true`, quoted strings understand escape sequences, like `FormatSettings::with_escapes`.
+	escapes: bool,
+}
+
+/// The key-value pairs in a block (or the top level of the file), plus the whitespace and comments around them.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Block {
+	pairs: Vec<Pair>,
+	/// Whitespace and comments after the last pair, before the closing brace or the end of the file.
+	end: String,
+	/// How deeply nested this block is, for indenting new pairs when there's nothing in the block to copy.
+	depth: usize,
+}
+
+/// One key-value pair, plus the whitespace and comments in front of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pair {
+	/// Whitespace and comments before the key. Comments on the lines above a pair belong to it.
+	before: String,
+	key: Token,
+	/// A conditional in between the key and the value, like `"Block" [$WIN32] { }`.
+	key_condition: Option<Tag>,
+	/// Whitespace and comments in between the key (or its conditional) and the value.
+	between: String,
+	value: Node,
+	/// A conditional after the value, like `"key" "value" [$WIN32]`.
+	condition: Option<Tag>,
+}
+
+/// The value of a pair: a string, or a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+	Str(Token),
+	Block(Block),
+}
+
+/// A key or a string value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+	/// The string, with escape sequences already replaced.
+	text: String,
+	/// Exactly how the string was written in the file, quotes and all. Gone once the string is changed.
+	raw: Option<String>,
+	quoted: bool,
+}
+
+/// A conditional, plus the whitespace and comments in front of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Tag {
+	before: String,
+	condition: Conditional,
+}
+
+impl Document {
+	/// Parse a document, understanding escape sequences in quoted strings.
+	pub fn parse(input: &str) -> Result<Self, VdfErr> {
+		Document::parse_with_settings(input, &FormatSettings::p2c_like())
+	}
+
+	/// Parse a document. The only setting that matters is whether quoted strings have escape sequences in them; Hammer files don't.
+	pub fn parse_with_settings(input: &str, settings: &FormatSettings) -> Result<Self, VdfErr> {
+		let mut parser = Parser { original: input, input, escapes: settings.escapes, path: Vec::new() };
+		parser.document().map_err(|err| parser.locate(err))
+	}
+
+	/// The pairs at the top level of the file.
+	pub fn root(&self) -> &Block {
+		&self.root
+	}
+
+	pub fn root_mut(&mut self) -> &mut Block {
+		&mut self.root
+	}
+
+	/// Write the document back out. Anything that wasn't edited comes out exactly how it was parsed.
+	///
+	/// # Errors
+	///
+	/// If escape sequences are off, and a string that was changed has a double quote in it.
+	pub fn to_string(&self) -> Result<String, VdfErr> {
+		let mut out = String::new();
+		self.write(&mut out)?;
+		Ok(out)
+	}
+
+	/// Like `to_string`, but into any `fmt::Write`.
+	pub fn write<W>(&self, out: &mut W) -> Result<(), VdfErr>
+	where
+		W: fmt::Write + ?Sized,
+	{
+		if self.braced {
+			out.write_str(&self.before)?;
+			out.write_char('{')?;
+			self.root.write(out, self.escapes)?;
+			out.write_char('}')?;
+			out.write_str(&self.after)?;
+			Ok(())
+		} else {
+			self.root.write(out, self.escapes)
+		}
+	}
+
+	/// Throw away the formatting, keeping the keys, values and conditionals.
+	pub fn to_object(&self) -> Object {
+		self.root.to_object()
+	}
+
+	/// Whether there are any `//` comments anywhere in the file, which `to_object` would throw away.
+	pub fn has_comments(&self) -> bool {
+		has_comment(&self.before) || self.root.has_comments() || has_comment(&self.after)
+	}
+}
+
+impl FromStr for Document {
+	type Err = VdfErr;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Document::parse(s)
+	}
+}
+
+impl Block {
+	/// The number of pairs in this block, counting repeated keys separately.
+	pub fn len(&self) -> usize {
+		self.pairs.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.pairs.is_empty()
+	}
+
+	pub fn pairs(&self) -> std::slice::Iter<'_, Pair> {
+		self.pairs.iter()
+	}
+
+	pub fn pairs_mut(&mut self) -> std::slice::IterMut<'_, Pair> {
+		self.pairs.iter_mut()
+	}
+
+	/// Returns the first pair with the given key.
+	pub fn get(&self, key: &str) -> Option<&Pair> {
+		self.pairs.iter().find(|pair| pair.key() == key)
+	}
+
+	/// Returns the first pair with the given key.
+	pub fn get_mut(&mut self, key: &str) -> Option<&mut Pair> {
+		self.pairs.iter_mut().find(|pair| pair.key() == key)
+	}
+
+	/// Returns every pair with the given key, in order.
+	pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a Pair> + 'a {
+		self.pairs.iter().filter(move |pair| pair.key() == key)
+	}
+
+	/// Returns every pair with the given key, in order.
+	pub fn get_all_mut<'a>(&'a mut self, key: &'a str) -> impl Iterator<Item = &'a mut Pair> + 'a {
+		self.pairs.iter_mut().filter(move |pair| pair.key() == key)
+	}
+
+	/// Returns the string value of the first pair with the given key.
+	pub fn get_str(&self, key: &str) -> Option<&str> {
+		self.get(key).and_then(|pair| pair.value().as_str())
+	}
+
+	/// Changes the value of the first pair with the given key, keeping its formatting. If there isn't one, a new pair is added
+	/// to the end of the block.
+	pub fn set<K, V>(&mut self, key: K, value: V) -> &mut Pair
+	where
+		K: Into<String>,
+		V: Into<String>,
+	{
+		let key = key.into();
+		match self.pairs.iter().position(|pair| pair.key() == key) {
+			Some(i) => {
+				let pair = &mut self.pairs[i];
+				pair.set_value(value);
+				pair
+			},
+			None => self.push(key, value),
+		}
+	}
+
+	/// Adds a pair with a string value to the end of the block, formatted like the pair before it.
+	pub fn push<K, V>(&mut self, key: K, value: V) -> &mut Pair
+	where
+		K: Into<String>,
+		V: Into<String>,
+	{
+		let (between, quote_key, quote_value) = match self.pairs.iter().rev().find(|pair| pair.value.is_str()) {
+			Some(sibling) => (sibling.between.clone(), sibling.key.quoted, sibling.value.as_token().map_or(true, |t| t.quoted)),
+			None => ("\t".to_string(), self.pairs.last().map_or(true, |p| p.key.quoted), true),
+		};
+
+		let pair = Pair {
+			before: self.next_indent(),
+			key: Token::new(key, quote_key),
+			key_condition: None,
+			between,
+			value: Node::Str(Token::new(value, quote_value)),
+			condition: None,
+		};
+		self.pairs.push(pair);
+		self.pairs.last_mut().unwrap()
+	}
+
+	/// Adds a pair with an empty block for a value to the end of the block, and returns the new block.
+	pub fn push_block<K>(&mut self, key: K) -> &mut Block
+	where
+		K: Into<String>,
+	{
+		//Hammer quotes the keys of strings but not blocks, so copy a block's key if there is one
+		let sibling = self.pairs.iter().rev().find(|pair| pair.value.is_block()).or(self.pairs.last());
+		let quote_key = sibling.map_or(true, |pair| pair.key.quoted);
+		let before = self.next_indent();
+		//The braces go on their own lines, at the same indentation as the key
+		let line = if before.is_empty() { "\n".to_string() } else { before.clone() };
+
+		let pair = Pair {
+			before,
+			key: Token::new(key, quote_key),
+			key_condition: None,
+			between: line.clone(),
+			value: Node::Block(Block { pairs: Vec::new(), end: line, depth: self.depth + 1 }),
+			condition: None,
+		};
+		self.pairs.push(pair);
+		match &mut self.pairs.last_mut().unwrap().value {
+			Node::Block(block) => block,
+			Node::Str(_) => unreachable!(),
+		}
+	}
+
+	/// Removes the first pair with the given key, along with the comments above it.
+	pub fn remove(&mut self, key: &str) -> Option<Pair> {
+		let i = self.pairs.iter().position(|pair| pair.key() == key)?;
+		Some(self.pairs.remove(i))
+	}
+
+	/// Keeps only the pairs for which the predicate returns `true`.
+	pub fn retain<F>(&mut self, f: F)
+	where
+		F: FnMut(&Pair) -> bool,
+	{
+		self.pairs.retain(f);
+	}
+
+	pub fn to_object(&self) -> Object {
+		self.pairs
+			.iter()
+			.map(|pair| {
+				let value = match &pair.value {
+					Node::Str(token) => Value::Str(token.text.clone()),
+					Node::Block(block) => Value::Obj(block.to_object()),
+				};
+				Kv { key: pair.key.text.clone(), value, condition: pair.condition().cloned() }
+			})
+			.collect::<Vec<_>>()
+			.into()
+	}
+
+	fn has_comments(&self) -> bool {
+		has_comment(&self.end)
+			|| self.pairs.iter().any(|pair| {
+				let mut tags = pair.key_condition.iter().chain(&pair.condition);
+				has_comment(&pair.before)
+					|| has_comment(&pair.between)
+					|| tags.any(|tag| has_comment(&tag.before))
+					|| pair.value.as_block().is_some_and(Block::has_comments)
+			})
+	}
+
+	/// The whitespace to put in front of a new pair, going by the line the pair before it is on. Comments aren't copied.
+	fn next_indent(&self) -> String {
+		if let Some(line) = self.pairs.last().and_then(|sibling| last_line(&sibling.before)) {
+			return line.to_string();
+		}
+		//Nothing to copy (or the pair before is on the same line as the brace), so go by the closing brace and indent one more than it
+		match last_line(&self.end) {
+			Some(line) if self.depth > 0 => format!("{}\t", line),
+			_ if self.depth == 0 && self.pairs.is_empty() => String::new(),
+			_ if self.depth == 0 => "\n".to_string(),
+			_ => format!("\n{}", "\t".repeat(self.depth)),
+		}
+	}
+
+	fn write<W>(&self, out: &mut W, escapes: bool) -> Result<(), VdfErr>
+	where
+		W: fmt::Write + ?Sized,
+	{
+		for pair in &self.pairs {
+			pair.write(out, escapes)?;
+		}
+		out.write_str(&self.end)?;
+		Ok(())
+	}
+}
+
+/// Indexing by key returns the value of the first pair with that key.
+///
+/// # Panics
+///
+/// Panics if there's no pair with that key. Use `get` if you're not sure.
+impl Index<&str> for Block {
+	type Output = Node;
+
+	fn index(&self, key: &str) -> &Node {
+		self.get(key).map(|pair| pair.value()).unwrap_or_else(|| panic!("no key {:?} in block", key))
+	}
+}
+
+impl Pair {
+	pub fn key(&self) -> &str {
+		&self.key.text
+	}
+
+	/// Renames this pair, keeping the key quoted if it was quoted before.
+	pub fn set_key<K>(&mut self, key: K)
+	where
+		K: Into<String>,
+	{
+		self.key.set(key);
+	}
+
+	pub fn value(&self) -> &Node {
+		&self.value
+	}
+
+	pub fn value_mut(&mut self) -> &mut Node {
+		&mut self.value
+	}
+
+	/// Changes the value to a string. If it was already a string, it stays quoted (or unquoted, if that still works).
+	pub fn set_value<V>(&mut self, value: V)
+	where
+		V: Into<String>,
+	{
+		match &mut self.value {
+			Node::Str(token) => token.set(value),
+			Node::Block(_) => {
+				self.between = "\t".to_string();
+				self.value = Node::Str(Token::new(value, true));
+			},
+		}
+	}
+
+	/// The conditional attached to this pair, like `[$WIN32]`, if there is one.
+	pub fn condition(&self) -> Option<&Conditional> {
+		self.condition.as_ref().or(self.key_condition.as_ref()).map(|tag| &tag.condition)
+	}
+
+	/// Changes the conditional attached to this pair, or removes it with `None`.
+	pub fn set_condition(&mut self, condition: Option<Conditional>) {
+		let existing = self.condition.as_mut().or(self.key_condition.as_mut());
+		match (existing, condition) {
+			(Some(tag), Some(condition)) => tag.condition = condition,
+			(None, Some(condition)) => self.condition = Some(Tag { before: " ".to_string(), condition }),
+			(_, None) => {
+				self.key_condition = None;
+				self.condition = None;
+			},
+		}
+	}
+
+	/// The `//` comments on the lines above this pair, without the slashes.
+	pub fn comments(&self) -> Vec<&str> {
+		self.before.lines().filter_map(|line| line.trim_start().strip_prefix("//")).map(|comment| comment.trim_end_matches('\r')).collect()
+	}
+
+	fn write<W>(&self, out: &mut W, escapes: bool) -> Result<(), VdfErr>
+	where
+		W: fmt::Write + ?Sized,
+	{
+		out.write_str(&self.before)?;
+		self.key.write(out, escapes)?;
+		if let Some(tag) = &self.key_condition {
+			write!(out, "{}{}", tag.before, tag.condition)?;
+		}
+		out.write_str(&self.between)?;
+		match &self.value {
+			Node::Str(token) => token.write(out, escapes)?,
+			Node::Block(block) => {
+				out.write_char('{')?;
+				block.write(out, escapes)?;
+				out.write_char('}')?;
+			},
+		}
+		if let Some(tag) = &self.condition {
+			write!(out, "{}{}", tag.before, tag.condition)?;
+		}
+		Ok(())
+	}
+}
+
+impl Node {
+	pub fn as_str(&self) -> Option<&str> {
+		self.as_token().map(|token| token.text.as_str())
+	}
+
+	pub fn as_block(&self) -> Option<&Block> {
+		match self {
+			Node::Str(_) => None,
+			Node::Block(block) => Some(block),
+		}
+	}
+
+	pub fn as_block_mut(&mut self) -> Option<&mut Block> {
+		match self {
+			Node::Str(_) => None,
+			Node::Block(block) => Some(block),
+		}
+	}
+
+	pub fn is_str(&self) -> bool {
+		matches!(self, Node::Str(_))
+	}
+
+	pub fn is_block(&self) -> bool {
+		matches!(self, Node::Block(_))
+	}
+
+	fn as_token(&self) -> Option<&Token> {
+		match self {
+			Node::Str(token) => Some(token),
+			Node::Block(_) => None,
+		}
+	}
+}
+
+impl Token {
+	fn new<S>(text: S, quoted: bool) -> Self
+	where
+		S: Into<String>,
+	{
+		Token { text: text.into(), raw: None, quoted }
+	}
+
+	pub fn as_str(&self) -> &str {
+		&self.text
+	}
+
+	/// `true` if the string was written with double quotes around it.
+	pub fn is_quoted(&self) -> bool {
+		self.quoted
+	}
+
+	fn set<S>(&mut self, text: S)
+	where
+		S: Into<String>,
+	{
+		self.text = text.into();
+		self.raw = None;
+	}
+
+	fn write<W>(&self, out: &mut W, escapes: bool) -> Result<(), VdfErr>
+	where
+		W: fmt::Write + ?Sized,
+	{
+		match &self.raw {
+			Some(raw) => Ok(out.write_str(raw)?),
+			//An unquoted string containing a '[' would turn into a conditional.
+			None => write_string(out, &self.text, self.quoted || needs_quotes(&self.text) || self.text.contains('['), escapes),
+		}
+	}
+}
+
+/// Whitespace and comments are all that's in between the tokens, so any `//` is a comment.
+fn has_comment(trivia: &str) -> bool {
+	trivia.contains("//")
+}
+
+/// The line break at the end of some whitespace, and the indentation after it.
+fn last_line(trivia: &str) -> Option<&str> {
+	let i = trivia.rfind('\n')?;
+	Some(if trivia[..i].ends_with('\r') { &trivia[i - 1..] } else { &trivia[i..] })
+}
+
+struct Parser<'a> {
+	original: &'a str,
+	input: &'a str,
+	escapes: bool,
+	/// The keys of the blocks the parser is inside of, for error messages.
+	path: Vec<String>,
+}
+
+impl<'a> Parser<'a> {
+	fn document(&mut self) -> Result<Document, VdfErr> {
+		let start = self.input;
+		let before = self.trivia();
+
+		if !self.input.starts_with('{') {
+			//No braces around the top level. The whitespace before the first key belongs to that key.
+			self.input = start;
+			let root = self.block(0, false)?;
+			return Ok(Document { before: String::new(), braced: false, root, after: String::new(), escapes: self.escapes });
+		}
+
+		self.input = &self.input[1..];
+		let root = self.block(0, true)?;
+		let after = self.trivia();
+		if !self.input.is_empty() {
+			return Err(VdfErr::TrailingCharacters);
+		}
+		Ok(Document { before, braced: true, root, after, escapes: self.escapes })
+	}
+
+	/// Reads pairs up to the closing brace (which is consumed), or the end of the file if the block isn't `braced`.
+	fn block(&mut self, depth: usize, braced: bool) -> Result<Block, VdfErr> {
+		let mut pairs = Vec::new();
+		loop {
+			let before = self.trivia();
+			match self.input.chars().next() {
+				None if braced => return Err(VdfErr::EndOfFile),
+				None => return Ok(Block { pairs, end: before, depth }),
+				Some('}') if braced => {
+					self.input = &self.input[1..];
+					return Ok(Block { pairs, end: before, depth });
+				},
+				Some('}') => return Err(VdfErr::UnexpectedCloseBrace),
+				Some(_) => pairs.push(self.pair(before, depth)?),
+			}
+		}
+	}
+
+	fn pair(&mut self, before: String, depth: usize) -> Result<Pair, VdfErr> {
+		let key = self.token()?;
+		self.path.push(key.text.clone());
+
+		let key_condition = self.tag()?;
+		let between = self.trivia();
+		let value = match self.input.chars().next() {
+			Some('{') => {
+				self.input = &self.input[1..];
+				Node::Block(self.block(depth + 1, true)?)
+			},
+			_ => Node::Str(self.token()?),
+		};
+		let condition = self.tag()?;
+
+		self.path.pop();
+		Ok(Pair { before, key, key_condition, between, value, condition })
+	}
+
+	/// Reads whitespace and `//` comments.
+	fn trivia(&mut self) -> String {
+		let mut rest = self.input;
+		loop {
+			rest = rest.trim_start();
+			if rest.starts_with("//") {
+				rest = rest.find('\n').map_or("", |i| &rest[i..]);
+			} else {
+				break;
+			}
+		}
+
+		let trivia = &self.input[..self.input.len() - rest.len()];
+		self.input = rest;
+		trivia.to_string()
+	}
+
+	/// Reads a conditional like `[$WIN32]`, if there's one after the whitespace.
+	fn tag(&mut self) -> Result<Option<Tag>, VdfErr> {
+		let rewind = self.input;
+		let before = self.trivia();
+		if !self.input.starts_with('[') {
+			self.input = rewind;
+			return Ok(None);
+		}
+
+		let end = self.input.find(']').ok_or(VdfErr::EndOfFile)?;
+		let condition = Conditional::new(&self.input[1..end])?;
+		self.input = &self.input[end + 1..];
+		Ok(Some(Tag { before, condition }))
+	}
+
+	/// Reads a key or string value, quoted or not.
+	fn token(&mut self) -> Result<Token, VdfErr> {
+		let mut chars = self.input.char_indices();
+		let len = match chars.next() {
+			None => return Err(VdfErr::EndOfFile),
+			Some((_, '{')) | Some((_, '}')) | Some((_, '[')) => return Err(VdfErr::ExpectedString),
+			Some((_, '"')) => loop {
+				match chars.next() {
+					None => return Err(VdfErr::EndOfFile),
+					Some((i, '"')) => break i + 1,
+					Some((_, '\\')) if self.escapes => {
+						chars.next();
+					},
+					Some(_) => {},
+				}
+			},
+			Some(_) => self.input.find(|c: char| c.is_whitespace() || c == '"' || c == '{' || c == '}' || c == '[').unwrap_or(self.input.len()),
+		};
+
+		let raw = &self.input[..len];
+		self.input = &self.input[len..];
+
+		let quoted = raw.starts_with('"');
+		let text = match raw.strip_prefix('"').and_then(|raw| raw.strip_suffix('"')) {
+			Some(inner) if self.escapes => unescape(inner),
+			Some(inner) => inner.to_string(),
+			None => raw.to_string(),
+		};
+		Ok(Token { text, raw: Some(raw.to_string()), quoted })
+	}
+
+	fn locate(&self, err: VdfErr) -> VdfErr {
+		let consumed = &self.original[..self.original.len() - self.input.len()];
+		let line_start = consumed.rfind('\n').map_or(0, |i| i + 1);
+		let location = Location { line: consumed.matches('\n').count() + 1, column: consumed[line_start..].chars().count() + 1, path: self.path.join(".") };
+		VdfErr::Located { location, source: Box::new(err) }
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	const EDITORITEMS: &str = "// Hand-maintained, please keep tidy\r\n\"ItemData\"\r\n{\r\n\t\"Item\"\r\n\t{\r\n\t\t\"Type\"\t\t\"ITEM_BUTTON_FLOOR\"\r\n\r\n\t\t// Skins go 0-3\r\n\t\tSkin 2 [!$X360]\r\n\t\t\"Editor\" [$WIN32]\r\n\t\t{\r\n\t\t\t\"SubType\"\t\"a \\\"quoted\\\" name\" // trailing\r\n\t\t}\r\n\t}\r\n\t\"Item\" { \"Type\" \"ITEM_CUBE\" }\r\n}\r\n// the end";
+
+	#[test]
+	fn lossless_round_trip() {
+		let doc = Document::parse(EDITORITEMS).unwrap();
+		assert_eq!(doc.to_string().unwrap(), EDITORITEMS);
+
+		let braced = "\n{\n\t\"key\"\t\"value\"\n}\n";
+		assert_eq!(Document::parse(braced).unwrap().to_string().unwrap(), braced);
+		assert_eq!(Document::parse("").unwrap().to_string().unwrap(), "");
+
+		let item = doc.root()["ItemData"].as_block().unwrap().get("Item").unwrap().value().as_block().unwrap();
+		assert_eq!(item.get_str("Skin"), Some("2"));
+		assert_eq!(item.get("Skin").unwrap().condition().unwrap().as_str(), "!$X360");
+		assert_eq!(item.get("Skin").unwrap().comments(), vec![" Skins go 0-3"]);
+		assert_eq!(item["Editor"].as_block().unwrap().get_str("SubType"), Some("a \"quoted\" name"));
+
+		let object = doc.to_object();
+		assert_eq!(object["ItemData"].as_obj().unwrap().get_all("Item").count(), 2);
+		assert_eq!(object, EDITORITEMS.parse::<Object>().unwrap());
+
+		assert!(doc.has_comments());
+		assert!(Document::parse("a { b c } // trailing").unwrap().has_comments());
+		assert!(Document::parse("a { b c // end of the block\n}").unwrap().has_comments());
+		assert!(!Document::parse("a { b \"//c\" }").unwrap().has_comments());
+	}
+
+	#[test]
+	fn edits() {
+		let mut doc = Document::parse(EDITORITEMS).unwrap();
+		let data = doc.root_mut().get_mut("ItemData").unwrap().value_mut().as_block_mut().unwrap();
+		let item = data.get_mut("Item").unwrap().value_mut().as_block_mut().unwrap();
+		item.set("Skin", "3");
+		item.set("Type", "ITEM_BUTTON_PEDESTAL");
+		item.push("Offset", "64 64 64");
+		let properties = item.push_block("Properties");
+		properties.push("StartEnabled", "1");
+		data.retain(|pair| pair.value().as_block().and_then(|item| item.get_str("Type")) != Some("ITEM_CUBE"));
+
+		//New pairs copy the style of the ones before them, and everything else is untouched
+		let expected = EDITORITEMS
+			.replace("ITEM_BUTTON_FLOOR", "ITEM_BUTTON_PEDESTAL")
+			.replace("Skin 2", "Skin 3")
+			.replace("\r\n\t\"Item\" { \"Type\" \"ITEM_CUBE\" }", "")
+			.replace("\t\t}\r\n\t}", "\t\t}\r\n\t\tOffset \"64 64 64\"\r\n\t\t\"Properties\"\r\n\t\t{\r\n\t\t\t\"StartEnabled\"\t\"1\"\r\n\t\t}\r\n\t}");
+		assert_eq!(doc.to_string().unwrap(), expected);
+
+		//A block on one line has no indentation to copy
+		let mut doc = Document::parse("ItemData\n{\n\tItem { Type X }\n}").unwrap();
+		let data = doc.root_mut().get_mut("ItemData").unwrap().value_mut().as_block_mut().unwrap();
+		data.get_mut("Item").unwrap().value_mut().as_block_mut().unwrap().push("Skin", "1");
+		assert_eq!(doc.to_string().unwrap(), "ItemData\n{\n\tItem { Type X\n\t\tSkin 1 }\n}");
+
+		let mut doc = Document::parse("").unwrap();
+		doc.root_mut().push_block("Top").push("key", "value");
+		assert_eq!(doc.to_string().unwrap(), "\"Top\"\n{\n\t\"key\"\t\"value\"\n}");
+
+		//Values that need quotes get them, even if the old one didn't
+		let mut doc = Document::parse("key value").unwrap();
+		doc.root_mut().set("key", "two words");
+		doc.root_mut().get_mut("key").unwrap().set_condition(Some(Conditional::new("$OSX").unwrap()));
+		assert_eq!(doc.to_string().unwrap(), "key \"two words\" [$OSX]");
+
+		let mut doc = Document::parse_with_settings("key \"C:\\path\"", &FormatSettings::vmf_like()).unwrap();
+		assert_eq!(doc.root().get_str("key"), Some("C:\\path"));
+		doc.root_mut().set("key", "\"");
+		assert!(matches!(doc.to_string(), Err(VdfErr::Unescapable(_))));
+	}
+
+	#[test]
+	fn errors() {
+		let err = Document::parse("a { b { c").unwrap_err();
+		assert!(matches!(err.kind(), VdfErr::EndOfFile));
+		assert_eq!(err.location().unwrap().path, "a.b.c");
+
+		let err = Document::parse("a b\n}").unwrap_err();
+		assert!(matches!(err.kind(), VdfErr::UnexpectedCloseBrace));
+		assert_eq!(err.location().unwrap().line, 2);
+
+		assert!(matches!(Document::parse("{ a b } c").unwrap_err().kind(), VdfErr::TrailingCharacters));
+		assert!(matches!(Document::parse("a b [$X").unwrap_err().kind(), VdfErr::EndOfFile));
+		assert!(matches!(Document::parse("a b [&&]").unwrap_err().kind(), VdfErr::BadConditional(_)));
+	}
+}
@@ -1,26 +1,424 @@
+//! VDF deserialization.
+
 #![allow(unused_variables)] //shush
 
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::io;
+
 use serde::de;
+use serde::de::DeserializeOwned;
+use serde::de::IntoDeserializer;
 use serde::serde_if_integer128;
 use serde::Deserialize;
 
+use crate::conditional::Conditional;
+use crate::conditional::Symbols;
+use crate::error::Location;
 use crate::error::VdfErr;
+use crate::inline;
+use crate::inline::Inline;
+use crate::repeated;
+use crate::ser::FormatSettings;
+use crate::ser::NewtypeFormat;
+use crate::ser::SequenceFormat;
+use crate::value::Kv;
+use crate::value::Object;
+use crate::value::Value;
 
-struct VdfDeserializer<'de> {
+/// Thing what do the deserialize!!!
+pub struct VdfDeserializer<'de> {
 	input: &'de str,
+	/// How many blocks deep the deserializer currently is. Zero means "at the top level of the file".
+	depth: usize,
+	settings: FormatSettings,
+	/// If `Some`, key-value pairs with conditionals that evaluate to false are skipped over, as if they weren't in the file.
+	symbols: Option<Symbols>,
+	/// The whole document, for working out line and column numbers.
+	original: &'de str,
+	/// The rest of the input, starting from the token that is being read. Errors are reported at this position.
+	mark: &'de str,
+	/// The keys leading to the value that is being read.
+	path: Vec<Segment<'de>>,
+	/// Pairs that `vdf::repeated` already read ahead of time, as byte offsets of where they start and end. The block they're
+	/// in skips over them when it gets there.
+	already_read: HashMap<usize, usize>,
+	/// If `Some`, struct fields and enum variants are matched without caring about case.
+	case_insensitive: Option<Ambiguity>,
+	warnings: Vec<VdfErr>,
+}
+
+/// What to do about keys that are ambiguous when matching case-insensitively: a key that matches more than one struct field or
+/// enum variant, or two keys in the same block that are the same field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ambiguity {
+	/// Go with the first field or variant, or the first of the keys (the later ones are skipped), and add a warning to
+	/// `VdfDeserializer::warnings`.
+	Warn,
+	/// Fail with `VdfErr::AmbiguousKey` or `VdfErr::DuplicateKey`.
+	Error,
 }
 
+/// One step of the path to the value being read, for error messages.
+enum Segment<'de> {
+	/// A key, and how many times it has already appeared in the same block.
+	Key(Cow<'de, str>, usize),
+	/// An element of a sequence.
+	Index(usize),
+}
+
+/// Deserialize an instance of type `T` from a string of VDF text.
+///
+/// Valve's files usually don't wrap the whole document in curly braces, so when a map or struct is requested at the very top
+/// of the file and there's no opening `{`, the entire file is read as the contents of one big block.
+///
+/// # Errors
+///
+/// Deserialization can fail if the input is not valid VDF, or if it doesn't match the structure expected by `T`.
 pub fn from_str<'a, T>(input: &'a str) -> Result<T, VdfErr>
 where
 	T: Deserialize<'a>,
 {
-	let mut de = VdfDeserializer::from_str(input);
-	T::deserialize(&mut de)
+	from_str_with_settings(input, FormatSettings::p2c_like())
+}
+
+/// Deserialize an instance of type `T` from a string of VDF text, using the given settings.
+///
+/// Only the parts of `FormatSettings` that affect the meaning of the file are used (such as whether escape sequences are
+/// recognized). How the file is indented and quoted doesn't matter when reading.
+pub fn from_str_with_settings<'a, T>(input: &'a str, settings: FormatSettings) -> Result<T, VdfErr>
+where
+	T: Deserialize<'a>,
+{
+	let mut de = VdfDeserializer::with_settings(input, settings);
+	let result = T::deserialize(&mut de).and_then(|value| de.end().map(|_| value));
+	result.map_err(|err| de.locate(err))
+}
+
+/// Deserialize an instance of type `T` from a string of VDF text, where the file consists of one top-level block with the given name.
+///
+/// This is the inverse of `vdf::ser::to_string_with_toplevel_block`. For example, calling
+/// `from_str_with_toplevel_block::<T>(input, "Block")` with the following input:
+/// ```text
+/// "Block"
+/// {
+///     "key" "value"
+/// }
+/// ```
+/// will deserialize `T` from the `{ "key" "value" }` part.
+pub fn from_str_with_toplevel_block<'a, T>(input: &'a str, toplevel_block_name: &str) -> Result<T, VdfErr>
+where
+	T: Deserialize<'a>,
+{
+	let mut de = VdfDeserializer::new(input);
+	let result = de.expect_key(toplevel_block_name).and_then(|_| {
+		de.path.push(Segment::Key(Cow::Owned(toplevel_block_name.to_string()), 0));
+		let value = T::deserialize(&mut de)?;
+		de.end()?;
+		Ok(value)
+	});
+	result.map_err(|err| de.locate(err))
+}
+
+/// Deserialize an instance of type `T` from the bytes of a VDF file.
+///
+/// Valve's tools aren't consistent about text encodings, so the encoding is sniffed from the byte order mark: UTF-8 with or
+/// without a BOM, and UTF-16 (little- or big-endian) with a BOM are all understood. See `decode`.
+///
+/// # Errors
+///
+/// Deserialization can fail if the bytes aren't valid text, if the text is not valid VDF, or if it doesn't match the structure
+/// expected by `T`.
+pub fn from_slice<T>(input: &[u8]) -> Result<T, VdfErr>
+where
+	T: DeserializeOwned,
+{
+	from_str(&decode(input)?)
+}
+
+/// Deserialize an instance of type `T` from an IO stream of VDF, like a file.
+///
+/// The whole stream is read into memory first. The encoding is detected the same way as `from_slice`.
+pub fn from_reader<R, T>(mut reader: R) -> Result<T, VdfErr>
+where
+	R: io::Read,
+	T: DeserializeOwned,
+{
+	let mut bytes = Vec::new();
+	reader.read_to_end(&mut bytes)?;
+	from_slice(&bytes)
+}
+
+/// Turns the bytes of a VDF file into text, going by the byte order mark at the start. The BOM itself is removed.
+///
+/// * `EF BB BF`: UTF-8.
+/// * `FF FE`: UTF-16, little-endian. Localization files like `portal2_english.txt` look like this.
+/// * `FE FF`: UTF-16, big-endian.
+/// * No BOM: UTF-8.
+///
+/// UTF-8 input is borrowed, UTF-16 input is transcoded to a new `String`.
+pub fn decode(bytes: &[u8]) -> Result<Cow<'_, str>, VdfErr> {
+	if let Some(rest) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+		std::str::from_utf8(rest).map(Cow::Borrowed).map_err(VdfErr::Utf8)
+	} else if let Some(rest) = bytes.strip_prefix(b"\xFF\xFE") {
+		decode_utf16(rest, u16::from_le_bytes).map(Cow::Owned)
+	} else if let Some(rest) = bytes.strip_prefix(b"\xFE\xFF") {
+		decode_utf16(rest, u16::from_be_bytes).map(Cow::Owned)
+	} else {
+		std::str::from_utf8(bytes).map(Cow::Borrowed).map_err(VdfErr::Utf8)
+	}
+}
+
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> Result<String, VdfErr> {
+	//Offsets in errors count the BOM, so they line up with the file
+	if bytes.len() % 2 != 0 {
+		return Err(VdfErr::Utf16(bytes.len() + 1));
+	}
+
+	let units = bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]));
+	let mut out = String::with_capacity(bytes.len() / 2);
+	let mut offset = 2;
+	for c in char::decode_utf16(units) {
+		let c = c.map_err(|_| VdfErr::Utf16(offset))?;
+		out.push(c);
+		offset += c.len_utf16() * 2;
+	}
+	Ok(out)
 }
 
 impl<'de> VdfDeserializer<'de> {
-	pub fn from_str(input: &'de str) -> Self {
-		VdfDeserializer { input }
+	/// Create a deserializer that reads from the given string.
+	///
+	/// Note that `VdfDeserializer::new` uses the default `editoritems.txt`-like settings, which recognize escape sequences.
+	pub fn new(input: &'de str) -> Self {
+		VdfDeserializer::with_settings(input, FormatSettings::p2c_like())
+	}
+
+	/// Create a deserializer that reads from the given string with the given settings.
+	pub fn with_settings(input: &'de str, settings: FormatSettings) -> Self {
+		//A BOM can sneak through if the file was read with `read_to_string`
+		let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+		VdfDeserializer {
+			input,
+			depth: 0,
+			settings,
+			symbols: None,
+			original: input,
+			mark: input,
+			path: Vec::new(),
+			already_read: HashMap::new(),
+			case_insensitive: None,
+			warnings: Vec::new(),
+		}
+	}
+
+	/// Evaluate conditionals like `[$WIN32]` while reading, using the given set of defined symbols.
+	///
+	/// Key-value pairs (and blocks) tagged with a conditional that evaluates to false are skipped. Without calling this,
+	/// conditionals are still parsed, but every pair is read regardless of them.
+	pub fn evaluate_conditionals(mut self, symbols: Symbols) -> Self {
+		self.symbols = Some(symbols);
+		self
+	}
+
+	/// Match keys to struct fields and enum variants without caring about (ASCII) case, like Valve's own KeyValues lookups do.
+	///
+	/// A key that exactly matches a field is always used for that field, even if it'd also match another one when ignoring case.
+	/// Map keys are left alone.
+	///
+	/// ```
+	/// # use serde::Deserialize;
+	/// # use vdf::de::*;
+	/// #[derive(Deserialize, Debug, PartialEq)]
+	/// #[serde(rename_all = "PascalCase")]
+	/// struct Editor {
+	///     movement_handle: String,
+	/// }
+	///
+	/// let mut de = VdfDeserializer::new("MOVEMENTHANDLE QUAD movementhandle NONE").case_insensitive(Ambiguity::Warn);
+	/// let editor = Editor::deserialize(&mut de).unwrap();
+	/// assert_eq!(editor.movement_handle, "QUAD");
+	/// assert_eq!(de.warnings().len(), 1);
+	/// ```
+	pub fn case_insensitive(mut self, on_ambiguity: Ambiguity) -> Self {
+		self.case_insensitive = Some(on_ambiguity);
+		self
+	}
+
+	/// Problems that were worked around while reading, with where they happened. Only case-insensitive matching makes any of
+	/// these, with `Ambiguity::Warn`.
+	pub fn warnings(&self) -> &[VdfErr] {
+		&self.warnings
+	}
+
+	/// Reads the whole document into an `Object`, keeping the conditionals attached to each key-value pair.
+	///
+	/// If `evaluate_conditionals` was called, pairs with false conditionals are left out.
+	pub fn parse_object(&mut self) -> Result<Object, VdfErr> {
+		let result = self.begin_block().and_then(|implicit| {
+			let object = self.parse_block_contents(implicit)?;
+			self.end()?;
+			Ok(object)
+		});
+		result.map_err(|err| self.locate(err))
+	}
+
+	fn parse_block_contents(&mut self, implicit: bool) -> Result<Object, VdfErr> {
+		let mut items = Vec::new();
+		let mut seen = HashMap::new();
+		while !self.end_of_block(implicit)? {
+			let key = self.yoink()?;
+			self.push_key(&mut seen, key.clone());
+			let mut condition = self.take_conditional()?;
+			let value = if self.peek() == Some('{') {
+				let implicit = self.begin_block()?;
+				Value::Obj(self.parse_block_contents(implicit)?)
+			} else {
+				let value = Value::Str(self.yoink()?.into_owned());
+				if condition.is_none() {
+					condition = self.take_conditional()?;
+				}
+				value
+			};
+
+			self.path.pop();
+			if self.passes(&condition) {
+				items.push(Kv { key: key.into_owned(), value, condition });
+			}
+		}
+		Ok(items.into())
+	}
+
+	/// How far into the document the deserializer is, in bytes.
+	fn offset(&self) -> usize {
+		self.original.len() - self.input.len()
+	}
+
+	/// Jumps over any pairs that were already read by `vdf::repeated`.
+	fn skip_already_read(&mut self) {
+		while !self.already_read.is_empty() {
+			self.skip_whitespace_and_comments();
+			match self.already_read.get(&self.offset()) {
+				Some(&end) => self.input = &self.original[end..],
+				None => break,
+			}
+		}
+	}
+
+	/// Where the deserializer currently is in the file.
+	pub fn location(&self) -> Location {
+		let consumed = &self.original[..self.original.len() - self.mark.len()];
+		let line_start = consumed.rfind('\n').map_or(0, |i| i + 1);
+		Location { line: consumed.matches('\n').count() + 1, column: consumed[line_start..].chars().count() + 1, path: self.render_path() }
+	}
+
+	/// Attaches the current location to an error, unless it already has one.
+	fn locate(&self, err: VdfErr) -> VdfErr {
+		if err.location().is_some() {
+			err
+		} else {
+			VdfErr::Located { location: self.location(), source: Box::new(err) }
+		}
+	}
+
+	fn render_path(&self) -> String {
+		let mut out = String::new();
+		for segment in &self.path {
+			match segment {
+				Segment::Key(key, n) => {
+					if !out.is_empty() {
+						out.push('.');
+					}
+					out.push_str(key);
+					if *n > 0 {
+						let _ = write!(out, "[{}]", n);
+					}
+				},
+				Segment::Index(i) => {
+					let _ = write!(out, "[{}]", i);
+				},
+			}
+		}
+		out
+	}
+
+	/// Adds a key to the path, counting how many times it's been seen in this block so repeated keys can be told apart.
+	fn push_key(&mut self, seen: &mut HashMap<Cow<'de, str>, usize>, key: Cow<'de, str>) {
+		let count = seen.entry(key.clone()).or_insert(0);
+		self.path.push(Segment::Key(key, *count));
+		*count += 1;
+	}
+
+	/// Records the problem as a warning, or returns it as an error, depending on the `Ambiguity` setting.
+	fn ambiguity(&mut self, problem: VdfErr) -> Result<(), VdfErr> {
+		match self.case_insensitive {
+			Some(Ambiguity::Warn) => {
+				let warning = self.locate(problem);
+				self.warnings.push(warning);
+				Ok(())
+			},
+			_ => Err(problem),
+		}
+	}
+
+	/// Which of the struct fields or enum variants the key is, when matching case-insensitively. `None` if it's none of them.
+	fn match_name(&mut self, key: &str, names: &'static [&'static str]) -> Result<Option<&'static str>, VdfErr> {
+		if let Some(name) = names.iter().find(|name| **name == key) {
+			return Ok(Some(name));
+		}
+
+		let matches: Vec<&'static str> = names.iter().copied().filter(|name| name.eq_ignore_ascii_case(key)).collect();
+		if matches.len() > 1 {
+			self.ambiguity(VdfErr::AmbiguousKey { key: key.to_string(), candidates: matches.iter().map(|name| name.to_string()).collect() })?;
+		}
+		Ok(matches.first().copied())
+	}
+
+	/// With case-insensitive matching on, swaps the key for the struct field or enum variant it matches.
+	fn canonical_name(&mut self, key: Cow<'de, str>, names: &'static [&'static str]) -> Result<Cow<'de, str>, VdfErr> {
+		if self.case_insensitive.is_none() {
+			return Ok(key);
+		}
+		Ok(self.match_name(&key, names)?.map_or(key, Cow::Borrowed))
+	}
+
+	/// Whether two keys are the same, going by the case sensitivity setting.
+	fn same_key(&self, a: &str, b: &str) -> bool {
+		match self.case_insensitive {
+			Some(_) => a.eq_ignore_ascii_case(b),
+			None => a == b,
+		}
+	}
+
+	/// Returns `false` if conditionals are being evaluated, and this one is false.
+	fn passes(&self, condition: &Option<Conditional>) -> bool {
+		match (&self.symbols, condition) {
+			(Some(symbols), Some(condition)) => condition.is_true(symbols),
+			_ => true,
+		}
+	}
+
+	/// Reads a conditional like `[$WIN32]`, if there is one.
+	fn take_conditional(&mut self) -> Result<Option<Conditional>, VdfErr> {
+		if self.peek() != Some('[') {
+			return Ok(None);
+		}
+
+		let end = self.input.find(']').ok_or(VdfErr::EndOfFile)?;
+		let text = &self.input[1..end];
+		self.input = &self.input[end + 1..];
+		Conditional::new(text).map(Some)
+	}
+
+	/// Call after deserializing the top-level value, to make sure nothing is left over.
+	pub fn end(&mut self) -> Result<(), VdfErr> {
+		self.skip_whitespace_and_comments();
+		if self.input.is_empty() {
+			Ok(())
+		} else {
+			Err(VdfErr::TrailingCharacters)
+		}
 	}
 
 	fn skip_whitespace_and_comments(&mut self) {
@@ -43,52 +441,275 @@ impl<'de> VdfDeserializer<'de> {
 
 			break;
 		}
+
+		self.mark = self.input;
+	}
+
+	/// Skips whitespace and comments, then returns the next character without consuming it.
+	fn peek(&mut self) -> Option<char> {
+		self.skip_whitespace_and_comments();
+		self.input.chars().next()
 	}
 
-	fn yoink(&mut self) -> Result<&'de str, VdfErr> {
+	/// Consumes the next character. Only call this after `peek` returned `Some`.
+	fn bump(&mut self) {
+		let mut chars = self.input.chars();
+		chars.next();
+		self.input = chars.as_str();
+	}
+
+	fn yoink(&mut self) -> Result<Cow<'de, str>, VdfErr> {
 		self.skip_whitespace_and_comments();
 
 		let mut chars = self.input.chars();
 
 		match chars.next().ok_or(VdfErr::EndOfFile)? {
+			'{' | '}' | '[' => Err(VdfErr::ExpectedString),
 			'"' => {
 				//Starts with a double quote, so we are reading a double quoted string.
-				//TODO this method fucking sucks LMAO
 
 				//Find the end of the double-quoted string.
 				//first munch this " character
 				self.input = &self.input[1..];
-				//... so that find() finds the ending character instead
-				let ending_quote_pos = self.input.find('"').ok_or(VdfErr::EndOfFile)?; //todo nicer error messages more clear about the reason would be nice
+				//... so that the search finds the ending character instead
+				let mut escaped = false;
+				let mut ending_quote_pos = None;
+				let mut chars = self.input.char_indices();
+				while let Some((i, c)) = chars.next() {
+					match c {
+						'"' => {
+							ending_quote_pos = Some(i);
+							break;
+						},
+						'\\' if self.settings.escapes => {
+							//Whatever the next character is, it can't end the string
+							escaped = true;
+							chars.next();
+						},
+						_ => {},
+					}
+				}
+
+				let ending_quote_pos = ending_quote_pos.ok_or(VdfErr::EndOfFile)?; //todo nicer error messages more clear about the reason would be nice
+				let s = &self.input[0..ending_quote_pos];
 				self.input = &self.input[ending_quote_pos + 1..];
-				Ok(&self.input[0..ending_quote_pos])
+				Ok(if escaped { Cow::Owned(unescape(s)) } else { Cow::Borrowed(s) })
 			},
 			_ => {
 				//Does not start with a double quote.
-				//Find the end of the unquoted string (first non-whitespace character).
+				//Find the end of the unquoted string (first whitespace character, or something that can't be part of an unquoted string).
 				//TODO: Allow values (not keys) to run into the end of the line
-				if let Some((car, cdr)) = self.input.split_once(|c: char| c.is_whitespace()) {
+				if let Some(end) = self.input.find(|c: char| c.is_whitespace() || c == '"' || c == '{' || c == '}' || c == '[') {
+					let (car, cdr) = self.input.split_at(end);
 					self.input = cdr;
-					Ok(car)
+					Ok(Cow::Borrowed(car))
 				} else {
 					//The value runs all the way up to the end of the line.
 					//Common in toy examples like parsing "123" as an entire vdf file, I guess
 					//Just return all the input
-					Ok(self.input)
+					let s = self.input;
+					self.input = "";
+					Ok(Cow::Borrowed(s))
 				}
 			},
 		}
 	}
+
+	/// Reads a key and makes sure it's the expected one.
+	fn expect_key(&mut self, expected: &str) -> Result<(), VdfErr> {
+		let found = self.yoink()?;
+		if found == expected {
+			Ok(())
+		} else {
+			Err(VdfErr::UnexpectedKey { expected: expected.to_string(), found: found.into_owned() })
+		}
+	}
+
+	/// Prepares to read the contents of a block.
+	///
+	/// Consumes the opening `{` and returns `false` if there is one. At the top level of the file, a missing `{` is allowed,
+	/// and the whole rest of the file is treated as the contents of the block; `true` is returned in that case.
+	fn begin_block(&mut self) -> Result<bool, VdfErr> {
+		//A block's conditional goes in between its key and the opening brace. By the time a value is being read, it's too late to skip it.
+		self.take_conditional()?;
+
+		let implicit = match self.peek() {
+			Some('{') => {
+				self.bump();
+				false
+			},
+			Some(_) | None if self.depth == 0 => true,
+			Some(_) => return Err(VdfErr::ExpectedBlock),
+			None => return Err(VdfErr::EndOfFile),
+		};
+
+		self.depth += 1;
+		Ok(implicit)
+	}
+
+	/// Returns `true` and consumes the closing brace if the block that is currently being read has ended.
+	fn end_of_block(&mut self, implicit: bool) -> Result<bool, VdfErr> {
+		match self.peek() {
+			Some('}') if implicit => Err(VdfErr::UnexpectedCloseBrace),
+			Some('}') => {
+				self.bump();
+				self.depth -= 1;
+				Ok(true)
+			},
+			None if implicit => {
+				self.depth -= 1;
+				Ok(true)
+			},
+			None => Err(VdfErr::EndOfFile),
+			Some(_) => Ok(false),
+		}
+	}
+
+	/// Returns `true` if the next value is a block.
+	///
+	/// At the top level of the file, a block can appear without braces around it. The file is assumed to be one of those when
+	/// there's more than one string in it; a file with a single string in it is assumed to contain just that single value.
+	fn at_block(&mut self) -> Result<bool, VdfErr> {
+		self.take_conditional()?;
+
+		match self.peek() {
+			Some('{') => Ok(true),
+			Some(_) if self.depth == 0 => {
+				let rewind = self.input;
+				self.yoink()?;
+				let more = self.peek().is_some();
+				self.input = rewind;
+				Ok(more)
+			},
+			Some(_) => Ok(false),
+			None => Err(VdfErr::EndOfFile),
+		}
+	}
+
+	/// Reads the contents of a block as a map, making sure the whole block gets consumed even if the visitor stops early.
+	///
+	/// When reading a struct, `fields` are its field names, for case-insensitive matching.
+	fn visit_block<V>(&mut self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, VdfErr>
+	where
+		V: de::Visitor<'de>,
+	{
+		let implicit = self.begin_block()?;
+		let mut access = BlockAccess { de: self, implicit, finished: false, index: 0, seen: HashMap::new(), fields, used: Vec::new() };
+		let value = visitor.visit_map(&mut access)?;
+		access.finish()?;
+		Ok(value)
+	}
+
+	/// Reads a sequence, either inline like `"1 2 3"` or as a block with the keys `"0"`, `"1"`, `"2"`...
+	///
+	/// Inline sequences are accepted no matter the settings, there's no mistaking them for a block.
+	fn visit_sequence<V>(&mut self, visitor: V) -> Result<V::Value, VdfErr>
+	where
+		V: de::Visitor<'de>,
+	{
+		if !self.at_block()? {
+			let parts = inline::parse(self.yoink()?)?;
+			let mut access = InlineAccess { de: self, parts: parts.into_iter(), index: 0 };
+			let value = visitor.visit_seq(&mut access)?;
+			return match access.parts.len() {
+				0 => Ok(value),
+				extra => Err(de::Error::invalid_length(access.index + extra, &"fewer elements")),
+			};
+		}
+
+		let implicit = self.begin_block()?;
+		let mut access = BlockAccess { de: self, implicit, finished: false, index: 0, seen: HashMap::new(), fields: &[], used: Vec::new() };
+		let value = visitor.visit_seq(&mut access)?;
+		access.finish()?;
+		Ok(value)
+	}
+
+	/// Consumes the end of a block that's only supposed to have the one entry in it, which has already been read.
+	fn end_single_entry_block(&mut self, implicit: bool) -> Result<(), VdfErr> {
+		self.skip_false_entries(implicit)?;
+		if self.end_of_block(implicit)? {
+			Ok(())
+		} else {
+			Err(VdfErr::ExtraKey(self.yoink()?.into_owned()))
+		}
+	}
+
+	/// Reads and discards one value, which may be a string or an entire block.
+	fn skip_value(&mut self) -> Result<(), VdfErr> {
+		self.take_conditional()?;
+
+		if self.peek() == Some('{') {
+			self.bump();
+			loop {
+				match self.peek() {
+					Some('}') => {
+						self.bump();
+						return Ok(());
+					},
+					None => return Err(VdfErr::EndOfFile),
+					Some(_) => {
+						self.skip_entry()?;
+					},
+				}
+			}
+		} else {
+			self.yoink().map(|_| ())
+		}
+	}
+
+	/// Reads and discards a key and its value, returning the conditional attached to the pair if there was one.
+	fn skip_entry(&mut self) -> Result<Option<Conditional>, VdfErr> {
+		self.yoink()?;
+		let before = self.take_conditional()?;
+		self.skip_value()?;
+		let after = self.take_conditional()?;
+		Ok(before.or(after))
+	}
+
+	/// When evaluating conditionals, skips over any pairs whose conditional is false, stopping at the first pair that should be read.
+	fn skip_false_entries(&mut self, implicit: bool) -> Result<(), VdfErr> {
+		if self.symbols.is_none() {
+			return Ok(());
+		}
+
+		loop {
+			if self.end_of_block_ahead(implicit) {
+				return Ok(());
+			}
+
+			let rewind = self.input;
+			let condition = self.skip_entry()?;
+			if self.passes(&condition) {
+				self.input = rewind;
+				return Ok(());
+			}
+		}
+	}
+
+	/// Like `end_of_block`, but doesn't consume anything.
+	fn end_of_block_ahead(&mut self, implicit: bool) -> bool {
+		match self.peek() {
+			Some('}') => !implicit,
+			None => true,
+			Some(_) => false,
+		}
+	}
 }
 
-impl<'a, 'de> de::Deserializer<'de> for &'a mut VdfDeserializer<'de> {
+impl<'de> de::Deserializer<'de> for &mut VdfDeserializer<'de> {
 	type Error = VdfErr;
 
 	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		todo!("deserialize_any")
+		//VDF doesn't have any types besides strings and blocks, so that's all this can produce.
+		//Numbers and bools come out as strings, so targets like `#[serde(flatten)]` structs need string fields (or `deserialize_with`).
+		if self.at_block()? {
+			self.visit_block(&[], visitor)
+		} else {
+			ScalarDeserializer(self.yoink()?).deserialize_any(visitor)
+		}
 	}
 
 	fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -96,10 +717,10 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut VdfDeserializer<'de> {
 		V: de::Visitor<'de>,
 	{
 		let s = self.yoink()?;
-		match s {
+		match &*s {
 			"1" | "true" => visitor.visit_bool(true),
 			"0" | "false" => visitor.visit_bool(false),
-			_ => Err(VdfErr::ParseBool(s.to_string())),
+			_ => Err(VdfErr::ParseBool(s.into_owned())),
 		}
 	}
 
@@ -248,132 +869,1245 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut VdfDeserializer<'de> {
 		}
 	}
 
+	//Strings without escape sequences in them are borrowed straight out of the input, so `&'de str` and `Cow<'de, str>` fields
+	//(with `#[serde(borrow)]`) don't allocate. Strings that had to be unescaped are handed over as an owned `String` instead.
 	fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		let s = self.yoink()?;
-		visitor.visit_str(s)
+		ScalarDeserializer(self.yoink()?).deserialize_any(visitor)
 	}
 
 	fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		let s = self.yoink()?;
-		visitor.visit_string(s.to_string())
+		ScalarDeserializer(self.yoink()?).deserialize_any(visitor)
 	}
 
 	fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		todo!("deserialize_bytes")
+		match self.yoink()? {
+			Cow::Borrowed(s) => visitor.visit_borrowed_bytes(s.as_bytes()),
+			Cow::Owned(s) => visitor.visit_byte_buf(s.into_bytes()),
+		}
 	}
 
 	fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		todo!("deserialize_byte_buf")
+		self.deserialize_bytes(visitor)
 	}
 
 	fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		todo!("deserialize_option")
+		//`None` is serialized as the empty string.
+		if self.peek() == Some('"') && self.input.starts_with("\"\"") {
+			self.input = &self.input[2..];
+			visitor.visit_none()
+		} else {
+			visitor.visit_some(self)
+		}
 	}
 
 	fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		todo!("deserialize_unit")
+		let s = self.yoink()?;
+		if s.is_empty() {
+			visitor.visit_unit()
+		} else {
+			Err(VdfErr::ExpectedUnit(s.into_owned()))
+		}
 	}
 
 	fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		todo!("deserialize_unit_struct")
+		self.deserialize_unit(visitor)
 	}
 
 	fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		todo!("deserialize_newtype_struct")
+		if name == repeated::NAME {
+			//The key of the pair being read is the one to look for.
+			return match self.path.last() {
+				Some(Segment::Key(key, _)) => {
+					let key = key.clone();
+					visitor.visit_seq(RepeatedAccess { de: self, key, resume: None })
+				},
+				_ => visitor.visit_newtype_struct(self),
+			};
+		}
+
+		if inline::brackets_for(name).is_some() {
+			//Take the brackets off the outside, if they're there.
+			let mut parts = inline::parse(self.yoink()?)?;
+			let whole = match parts.as_slice() {
+				[Inline::Group(_)] => parts.remove(0),
+				_ => Inline::Group(parts),
+			};
+			return visitor.visit_newtype_struct(whole.into_deserializer());
+		}
+
+		if self.settings.newtype_format == NewtypeFormat::Transparent {
+			return visitor.visit_newtype_struct(self);
+		}
+
+		//The name of the newtype struct comes before its contents. As the value of a pair, they're wrapped in a block.
+		self.take_conditional()?;
+		let block = self.peek() == Some('{');
+		if block {
+			self.begin_block()?;
+		}
+
+		self.expect_key(name)?;
+		self.path.push(Segment::Key(Cow::Borrowed(name), 0));
+		let value = visitor.visit_newtype_struct(&mut *self)?;
+		self.take_conditional()?;
+		self.path.pop();
+
+		if block {
+			self.end_single_entry_block(false)?;
+		}
+		Ok(value)
 	}
 
 	fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		todo!("deserialize_seq")
+		if self.settings.sequence_format == SequenceFormat::Repeated {
+			if let Some(Segment::Key(key, _)) = self.path.last() {
+				let key = key.clone();
+				return visitor.visit_seq(RepeatedAccess { de: self, key, resume: None });
+			}
+		}
+
+		self.visit_sequence(visitor)
 	}
 
 	fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		todo!("deserialize_tuple")
+		self.visit_sequence(visitor)
 	}
 
 	fn deserialize_tuple_struct<V>(self, name: &'static str, len: usize, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		todo!("deserialize_tuple_struct")
+		self.visit_sequence(visitor)
 	}
 
 	fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		todo!("deserialize_map")
+		self.visit_block(&[], visitor)
 	}
 
 	fn deserialize_struct<V>(self, name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		todo!("deserialize_struct")
+		self.visit_block(fields, visitor)
 	}
 
 	fn deserialize_enum<V>(self, name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		todo!("deserialize_enum")
+		//Unit variants are serialized by name.
+		if !self.at_block()? {
+			let variant = self.yoink()?;
+			let variant = self.canonical_name(variant, variants)?;
+			return visitor.visit_enum(variant.into_deserializer());
+		}
+
+		//Everything else is a block containing one entry, named after the variant.
+		let implicit = self.begin_block()?;
+		self.skip_false_entries(implicit)?;
+		let value = visitor.visit_enum(VariantAccess { de: &mut *self, variants })?;
+		self.take_conditional()?;
+		self.path.pop();
+		self.end_single_entry_block(implicit)?;
+		Ok(value)
 	}
 
 	fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		todo!("deserialize_identifier")
+		self.deserialize_str(visitor)
 	}
 
 	fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		todo!("deserialize_ignored_any")
+		self.skip_value()?;
+		visitor.visit_unit()
 	}
 }
 
-#[allow(unused_imports)] //r-a bug? These imports are used in the tests below
-mod test {
-	use super::*;
+/// Replaces escape sequences in a quoted string with the characters they stand for.
+///
+/// Like Valve's parser, a backslash followed by something that isn't an escape sequence is left alone.
+pub(crate) fn unescape(s: &str) -> String {
+	let mut out = String::with_capacity(s.len());
+	let mut chars = s.chars();
+	while let Some(c) = chars.next() {
+		if c != '\\' {
+			out.push(c);
+			continue;
+		}
 
-	#[test]
-	fn de_u64() {
-		//TODO: make the tests pass! D:
+		match chars.next() {
+			Some('n') => out.push('\n'),
+			Some('t') => out.push('\t'),
+			Some('\\') => out.push('\\'),
+			Some('"') => out.push('"'),
+			Some(other) => {
+				out.push('\\');
+				out.push(other);
+			},
+			None => out.push('\\'),
+		}
+	}
+	out
+}
 
-		assert_eq!(from_str::<u64>("123").expect("failed to parse 1"), 123);
-		//assert_eq!(from_str::<u64>("\"123\"").expect("failed to parse 2"), 123); //panics
-		//assert_eq!(from_str::<u64>("\"123\"asdf").expect("failed to parse 3"), 123); //int parse failure, but it shouldn't
-		assert_eq!(from_str::<u64>("   123  ").expect("failed to parse 4"), 123);
+/// Walks over the entries of a `{ ... }` block.
+struct BlockAccess<'a, 'de> {
+	de: &'a mut VdfDeserializer<'de>,
+	/// If `true`, this is the top-level block of the file, which isn't surrounded by curly braces and ends at the end of the file.
+	implicit: bool,
+	/// Set once the end of the block has been consumed.
+	finished: bool,
+	/// How many sequence elements have been read, for error messages.
+	index: usize,
+	/// How many times each key has been read, for error messages.
+	seen: HashMap<Cow<'de, str>, usize>,
+	/// The field names, if this block is a struct.
+	fields: &'static [&'static str],
+	/// When matching case-insensitively, the fields that have been read so far, and the key each one was read from.
+	used: Vec<(&'static str, Cow<'de, str>)>,
+}
+
+impl<'a, 'de> BlockAccess<'a, 'de> {
+	fn at_end(&mut self) -> Result<bool, VdfErr> {
+		if !self.finished {
+			loop {
+				self.de.skip_already_read();
+				let before = self.de.input.len();
+				self.de.skip_false_entries(self.implicit)?;
+				if self.de.input.len() == before {
+					break;
+				}
+			}
+			self.finished = self.de.end_of_block(self.implicit)?;
+		}
+		Ok(self.finished)
+	}
+
+	/// Skips any entries the visitor didn't read, so the deserializer ends up after the closing brace.
+	fn finish(&mut self) -> Result<(), VdfErr> {
+		while !self.at_end()? {
+			self.de.skip_entry()?;
+		}
+		Ok(())
+	}
+}
+
+impl<'a, 'de> de::MapAccess<'de> for BlockAccess<'a, 'de> {
+	type Error = VdfErr;
+
+	fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+	where
+		K: de::DeserializeSeed<'de>,
+	{
+		loop {
+			if self.at_end()? {
+				return Ok(None);
+			}
+
+			//Peek at the key for the path in error messages, then let the seed read it for real
+			let rewind = self.de.input;
+			let key = self.de.yoink()?;
+			let field = match self.de.case_insensitive {
+				Some(_) => self.de.match_name(&key, self.fields)?,
+				None => None,
+			};
+
+			let field = match field {
+				Some(field) => field,
+				None => {
+					self.de.input = rewind;
+					self.de.push_key(&mut self.seen, key);
+					return seed.deserialize(&mut *self.de).map(Some);
+				},
+			};
+
+			//Two spellings of the same field. (The exact same key twice is left for the visitor to complain about.)
+			if let Some((_, earlier)) = self.used.iter().find(|(used, earlier)| *used == field && *earlier != key) {
+				let problem = VdfErr::DuplicateKey { key: key.to_string(), earlier: earlier.to_string() };
+				self.de.push_key(&mut self.seen, key);
+				self.de.ambiguity(problem)?;
+				self.de.skip_value()?;
+				self.de.take_conditional()?;
+				self.de.path.pop();
+				continue;
+			}
+
+			self.used.push((field, key.clone()));
+			self.de.push_key(&mut self.seen, key);
+			return seed.deserialize(ScalarDeserializer(Cow::Borrowed(field))).map(Some);
+		}
+	}
+
+	fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::DeserializeSeed<'de>,
+	{
+		let value = seed.deserialize(&mut *self.de)?;
+		//A conditional on a string value comes after it.
+		self.de.take_conditional()?;
+		self.de.path.pop();
+		Ok(value)
+	}
+}
+
+impl<'a, 'de> de::SeqAccess<'de> for BlockAccess<'a, 'de> {
+	type Error = VdfErr;
+
+	fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+	where
+		T: de::DeserializeSeed<'de>,
+	{
+		if self.at_end()? {
+			Ok(None)
+		} else {
+			//Sequences are serialized as blocks with the keys "0", "1", "2"... The keys aren't important when reading.
+			self.de.yoink()?;
+			self.de.path.push(Segment::Index(self.index));
+			self.index += 1;
+			let value = seed.deserialize(&mut *self.de)?;
+			self.de.take_conditional()?;
+			self.de.path.pop();
+			Ok(Some(value))
+		}
+	}
+}
+
+/// Reads the one entry inside the block of a data-carrying enum variant.
+struct VariantAccess<'a, 'de> {
+	de: &'a mut VdfDeserializer<'de>,
+	variants: &'static [&'static str],
+}
+
+impl<'a, 'de> de::EnumAccess<'de> for VariantAccess<'a, 'de> {
+	type Error = VdfErr;
+	type Variant = Self;
+
+	fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+	where
+		V: de::DeserializeSeed<'de>,
+	{
+		if self.de.peek() == Some('}') {
+			return Err(de::Error::custom("expected an enum variant, but the block is empty"));
+		}
+
+		let variant = self.de.yoink()?;
+		self.de.path.push(Segment::Key(variant.clone(), 0));
+		let variant = self.de.canonical_name(variant, self.variants)?;
+		let variant = seed.deserialize(ScalarDeserializer(variant))?;
+		Ok((variant, self))
+	}
+}
+
+impl<'a, 'de> de::VariantAccess<'de> for VariantAccess<'a, 'de> {
+	type Error = VdfErr;
+
+	fn unit_variant(self) -> Result<(), Self::Error> {
+		de::Deserialize::deserialize(&mut *self.de)
+	}
+
+	fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+	where
+		T: de::DeserializeSeed<'de>,
+	{
+		seed.deserialize(&mut *self.de)
+	}
+
+	fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.de.visit_sequence(visitor)
+	}
+
+	fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.de.visit_block(fields, visitor)
+	}
+}
+
+/// Reads every pair in the block with the same key as the one that's currently being read, for `vdf::repeated`.
+///
+/// The first element is the value right here. The rest are found by scanning ahead through the rest of the block; the pairs
+/// found that way are remembered in `already_read`, and the deserializer goes back to where it was afterwards.
+struct RepeatedAccess<'a, 'de> {
+	de: &'a mut VdfDeserializer<'de>,
+	key: Cow<'de, str>,
+	/// Where to go back to once the scan is over. `None` until the first element is read.
+	resume: Option<&'de str>,
+}
+
+impl<'a, 'de> de::SeqAccess<'de> for RepeatedAccess<'a, 'de> {
+	type Error = VdfErr;
+
+	fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+	where
+		T: de::DeserializeSeed<'de>,
+	{
+		if self.resume.is_none() {
+			let value = seed.deserialize(&mut *self.de)?;
+			self.de.take_conditional()?;
+			self.resume = Some(self.de.input);
+			return Ok(Some(value));
+		}
+
+		loop {
+			self.de.skip_already_read();
+			if let None | Some('}') = self.de.peek() {
+				//Unwrap safety: checked above
+				self.de.input = self.resume.unwrap();
+				return Ok(None);
+			}
+
+			let entry = self.de.input;
+			let key = self.de.yoink()?;
+			let matches = self.de.same_key(&key, &self.key);
+			self.de.input = entry;
+			let condition = self.de.skip_entry()?;
+			if !matches {
+				continue;
+			}
+
+			let after = self.de.input;
+			self.de.already_read.insert(self.de.original.len() - entry.len(), self.de.offset());
+			if !self.de.passes(&condition) {
+				continue;
+			}
+
+			if let Some(Segment::Key(_, n)) = self.de.path.last_mut() {
+				*n += 1;
+			}
+
+			self.de.input = entry;
+			self.de.yoink()?;
+			let value = seed.deserialize(&mut *self.de)?;
+			self.de.input = after;
+			return Ok(Some(value));
+		}
+	}
+}
+
+/// Reads the space-separated elements of an inline sequence, like `"origin" "1 2 3"`.
+struct InlineAccess<'a, 'de> {
+	de: &'a mut VdfDeserializer<'de>,
+	parts: std::vec::IntoIter<Inline<'de>>,
+	index: usize,
+}
+
+impl<'a, 'de> de::SeqAccess<'de> for InlineAccess<'a, 'de> {
+	type Error = VdfErr;
+
+	fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+	where
+		T: de::DeserializeSeed<'de>,
+	{
+		let part = match self.parts.next() {
+			Some(part) => part,
+			None => return Ok(None),
+		};
+
+		self.de.path.push(Segment::Index(self.index));
+		self.index += 1;
+		let value = seed.deserialize(part.into_deserializer())?;
+		self.de.path.pop();
+		Ok(Some(value))
+	}
+
+	fn size_hint(&self) -> Option<usize> {
+		Some(self.parts.len())
+	}
+}
+
+macro_rules! parse_scalar {
+	( $func:ident | $visit:ident | $type:ty | $err:ident ) => {
+		fn $func<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+		where
+			V: de::Visitor<'de>,
+		{
+			match self.0.parse::<$type>() {
+				Ok(v) => visitor.$visit(v),
+				Err(e) => Err(VdfErr::$err(e)),
+			}
+		}
+	};
+}
+
+/// Deserializes one string that has already been read, parsing it the same way `VdfDeserializer` would.
+pub(crate) struct ScalarDeserializer<'de>(pub(crate) Cow<'de, str>);
+
+impl<'de> de::Deserializer<'de> for ScalarDeserializer<'de> {
+	type Error = VdfErr;
+
+	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		match self.0 {
+			Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+			Cow::Owned(s) => visitor.visit_string(s),
+		}
+	}
+
+	fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		match &*self.0 {
+			"1" | "true" => visitor.visit_bool(true),
+			"0" | "false" => visitor.visit_bool(false),
+			_ => Err(VdfErr::ParseBool(self.0.into_owned())),
+		}
+	}
+
+	parse_scalar!(deserialize_i8 | visit_i8 | i8 | ParseInt);
+	parse_scalar!(deserialize_i16 | visit_i16 | i16 | ParseInt);
+	parse_scalar!(deserialize_i32 | visit_i32 | i32 | ParseInt);
+	parse_scalar!(deserialize_i64 | visit_i64 | i64 | ParseInt);
+	parse_scalar!(deserialize_u8 | visit_u8 | u8 | ParseInt);
+	parse_scalar!(deserialize_u16 | visit_u16 | u16 | ParseInt);
+	parse_scalar!(deserialize_u32 | visit_u32 | u32 | ParseInt);
+	parse_scalar!(deserialize_u64 | visit_u64 | u64 | ParseInt);
+	parse_scalar!(deserialize_f32 | visit_f32 | f32 | ParseFloat);
+	parse_scalar!(deserialize_f64 | visit_f64 | f64 | ParseFloat);
+	parse_scalar!(deserialize_char | visit_char | char | ParseChar);
+
+	serde_if_integer128! {
+		parse_scalar!(deserialize_i128 | visit_i128 | i128 | ParseInt);
+		parse_scalar!(deserialize_u128 | visit_u128 | u128 | ParseInt);
+	}
+
+	fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		if self.0.is_empty() {
+			visitor.visit_none()
+		} else {
+			visitor.visit_some(self)
+		}
+	}
+
+	fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		if self.0.is_empty() {
+			visitor.visit_unit()
+		} else {
+			Err(VdfErr::ExpectedUnit(self.0.into_owned()))
+		}
+	}
+
+	fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.deserialize_unit(visitor)
+	}
+
+	fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		visitor.visit_newtype_struct(self)
+	}
+
+	fn deserialize_enum<V>(self, name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		visitor.visit_enum(self.0.into_deserializer())
+	}
+
+	serde::forward_to_deserialize_any! {
+		str string bytes byte_buf seq tuple tuple_struct map struct identifier ignored_any
+	}
+}
+
+#[allow(unused_imports)] //r-a bug? These imports are used in the tests below
+mod test {
+	use serde::Serialize;
+
+	use super::*;
+	use crate::ser::FormatSettings;
+	use crate::ser::VdfSerializer;
+
+	#[test]
+	fn de_u64() {
+		//TODO: make the tests pass! D:
+
+		assert_eq!(from_str::<u64>("123").expect("failed to parse 1"), 123);
+		//assert_eq!(from_str::<u64>("\"123\"").expect("failed to parse 2"), 123); //panics
+		//assert_eq!(from_str::<u64>("\"123\"asdf").expect("failed to parse 3"), 123); //int parse failure, but it shouldn't
+		assert_eq!(from_str::<u64>("   123  ").expect("failed to parse 4"), 123);
+	}
+
+	#[derive(Deserialize, Debug, PartialEq)]
+	struct Hello {
+		hello: String,
+		small_number: u8,
+		yea: Yea,
+	}
+
+	#[derive(Deserialize, Debug, PartialEq)]
+	struct Yea {
+		yeah_woo: String,
+		wooo_yeah: bool,
+	}
+
+	#[test]
+	fn de_nested_structs() {
+		let input = r#"
+			{
+				"hello" "world"
+				"small_number" "69"
+				"yea"
+				{
+					"yeah_woo" "yeah woo yea woooo yeah woo"
+					"wooo_yeah" "1"
+				}
+			}
+		"#;
+
+		let expected = Hello { hello: "world".into(), small_number: 69, yea: Yea { yeah_woo: "yeah woo yea woooo yeah woo".into(), wooo_yeah: true } };
+		assert_eq!(from_str::<Hello>(input).unwrap(), expected);
+
+		//The braces around the whole file are optional
+		let input = "hello world small_number 69 yea { yeah_woo \"yeah woo yea woooo yeah woo\" wooo_yeah 1 }";
+		assert_eq!(from_str::<Hello>(input).unwrap(), expected);
+	}
+
+	#[test]
+	fn de_toplevel_block() {
+		let input = "\"Yea\"\n{\n\t\"yeah_woo\"\t\"woo\"\n\t\"wooo_yeah\"\t\"0\"\n}\n";
+		assert_eq!(from_str_with_toplevel_block::<Yea>(input, "Yea").unwrap(), Yea { yeah_woo: "woo".into(), wooo_yeah: false });
+		assert!(matches!(from_str_with_toplevel_block::<Yea>(input, "Nope").unwrap_err().kind(), VdfErr::UnexpectedKey { .. }));
+	}
+
+	#[test]
+	fn de_comments_and_unknown_keys() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Small {
+			a: u32,
+		}
+
+		let input = r#"
+			// a comment
+			"a" "5" // another comment
+			"ignored" { "deeply" { "nested" "stuff" } }
+			"also_ignored" "yes"
+		"#;
+		assert_eq!(from_str::<Small>(input).unwrap(), Small { a: 5 });
+	}
+
+	#[test]
+	fn de_maps_seqs_and_options() {
+		use std::collections::BTreeMap;
+
+		let map: BTreeMap<String, u32> = from_str(r#""one" "1" "two" "2""#).unwrap();
+		assert_eq!(map.get("one"), Some(&1));
+		assert_eq!(map.get("two"), Some(&2));
+
+		let seq: Vec<String> = from_str(r#"{ "0" "asd" "1" "ghj" "2" "ahdjahds" }"#).unwrap();
+		assert_eq!(seq, vec!["asd", "ghj", "ahdjahds"]);
+
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Opts {
+			present: Option<u32>,
+			empty: Option<u32>,
+			missing: Option<u32>,
+		}
+
+		let opts: Opts = from_str(r#""present" "5" "empty" """#).unwrap();
+		assert_eq!(opts, Opts { present: Some(5), empty: None, missing: None });
+	}
+
+	#[test]
+	fn de_errors() {
+		assert!(matches!(from_str::<Yea>(r#""yeah_woo" { }"#).unwrap_err().kind(), VdfErr::ExpectedString));
+		assert!(matches!(from_str::<Hello>(r#""hello" "world" "small_number" "1" "yea" "oops""#).unwrap_err().kind(), VdfErr::ExpectedBlock));
+		assert!(matches!(from_str::<Yea>(r#"{ "yeah_woo" "woo" "wooo_yeah" "1""#).unwrap_err().kind(), VdfErr::EndOfFile));
+		assert!(matches!(from_str::<Yea>(r#"{ "yeah_woo" "woo" "wooo_yeah" "1" } }"#).unwrap_err().kind(), VdfErr::TrailingCharacters));
+
+		#[derive(Deserialize, Debug)]
+		#[serde(rename_all = "PascalCase")]
+		struct Item {
+			editor: Editor,
+		}
+
+		#[derive(Deserialize, Debug)]
+		#[serde(rename_all = "PascalCase")]
+		struct Editor {
+			sub_type: u32,
+		}
+
+		let input = "\"ItemData\"\n{\n\t\"Item\" { \"Editor\" { \"SubType\" \"0\" } }\n\t\"Item\" { \"Editor\" { \"SubType\" \"oops\" } }\n}\n";
+		let err = from_str_with_toplevel_block::<std::collections::BTreeMap<String, Item>>(input, "ItemData").unwrap_err();
+		assert!(matches!(err.kind(), VdfErr::ParseInt(_)));
+		assert_eq!(err.location(), Some(&Location { line: 4, column: 32, path: "ItemData.Item[1].Editor.SubType".into() }));
+		assert_eq!(err.to_string(), "Could not parse integer: invalid digit found in string (line 4, column 32, at ItemData.Item[1].Editor.SubType)");
+
+		let err = from_str::<Vec<Yea>>("{ \"0\" { \"yeah_woo\" \"a\" \"wooo_yeah\" \"1\" }\n\"1\" { \"yeah_woo\" \"b\" } }").unwrap_err();
+		assert_eq!(err.location(), Some(&Location { line: 2, column: 22, path: "[1]".into() }));
+		assert_eq!(err.to_string(), "missing field `wooo_yeah` (line 2, column 22, at [1])");
+	}
+
+	#[test]
+	fn de_any() {
+		use std::collections::BTreeMap;
+
+		#[derive(Deserialize, Debug, PartialEq)]
+		#[serde(untagged)]
+		enum Untyped {
+			Str(String),
+			Block(BTreeMap<String, Untyped>),
+		}
+
+		let input = r#"
+			"GameInfo"
+			{
+				game "Portal 2"
+				FileSystem
+				{
+					SteamAppId 620
+				}
+			}
+		"#;
+
+		let mut filesystem = BTreeMap::new();
+		filesystem.insert("SteamAppId".to_string(), Untyped::Str("620".into()));
+		let mut gameinfo = BTreeMap::new();
+		gameinfo.insert("game".to_string(), Untyped::Str("Portal 2".into()));
+		gameinfo.insert("FileSystem".to_string(), Untyped::Block(filesystem));
+		let mut root = BTreeMap::new();
+		root.insert("GameInfo".to_string(), Untyped::Block(gameinfo));
+
+		assert_eq!(from_str::<Untyped>(input).unwrap(), Untyped::Block(root));
+		assert_eq!(from_str::<Untyped>(r#""just a string""#).unwrap(), Untyped::Str("just a string".into()));
+	}
+
+	#[test]
+	fn de_borrowed() {
+		use std::collections::BTreeMap;
+
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Side<'a> {
+			material: &'a str,
+			#[serde(borrow)]
+			uaxis: Cow<'a, str>,
+			#[serde(borrow)]
+			vaxis: Cow<'a, str>,
+			#[serde(borrow)]
+			extra: BTreeMap<&'a str, &'a str>,
+		}
+
+		let input = r#"material "TILE/WHITE" uaxis "[1 0 0 0] 0.25" vaxis "[0 \"1\" 0 0] 0.25" extra { rotation 0 "smoothing groups" 0 }"#;
+		let side: Side = from_str(input).unwrap();
+		assert_eq!(side.material, "TILE/WHITE");
+		assert!(matches!(side.uaxis, Cow::Borrowed("[1 0 0 0] 0.25")));
+		assert!(matches!(&side.vaxis, Cow::Owned(s) if s == "[0 \"1\" 0 0] 0.25"));
+		assert_eq!(side.extra.get("smoothing groups"), Some(&"0"));
+
+		//A borrowed &str can't hold a string that had to be unescaped
+		let err = from_str::<Side>(r#"material "a\\b" uaxis "" vaxis "" extra { }"#).unwrap_err();
+		assert_eq!(err.location().unwrap().path, "material");
+
+		let bytes: &[u8] = from_str("\"abc\"").unwrap();
+		assert_eq!(bytes, b"abc");
+	}
+
+	#[test]
+	fn de_flatten() {
+		use std::collections::BTreeMap;
+
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Outer {
+			name: String,
+			#[serde(flatten)]
+			inner: Inner,
+			#[serde(flatten)]
+			rest: BTreeMap<String, String>,
+		}
+
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Inner {
+			model: String,
+		}
+
+		let outer: Outer = from_str(r#""name" "thing" "model" "switch.3ds" "extra" "stuff""#).unwrap();
+		assert_eq!(outer.name, "thing");
+		assert_eq!(outer.inner, Inner { model: "switch.3ds".into() });
+		assert_eq!(outer.rest.get("extra").map(String::as_str), Some("stuff"));
+	}
+
+	#[test]
+	fn de_escapes() {
+		let s: String = from_str(r#""say \"hi\"\n\tC:\\maps\\ \q""#).unwrap();
+		assert_eq!(s, "say \"hi\"\n\tC:\\maps\\ \\q");
+
+		//Hammer doesn't use escapes, and it's common to find a trailing backslash in a path
+		let s: String = from_str_with_settings(r#""materials\""#, FormatSettings::vmf_like()).unwrap();
+		assert_eq!(s, "materials\\");
+	}
+
+	#[test]
+	fn escapes_round_trip() {
+		let map: std::collections::BTreeMap<String, String> =
+			vec![("quote \"key\"".to_string(), "multi\nline\tvalue with a \\ and a \"".to_string())].into_iter().collect();
+
+		let written = crate::ser::to_string(&map).unwrap();
+		assert_eq!(written, "\n{\n\t\"quote \\\"key\\\"\"\t\"multi\\nline\\tvalue with a \\\\ and a \\\"\"\n}\n");
+		assert_eq!(from_str::<std::collections::BTreeMap<String, String>>(&written).unwrap(), map);
+
+		let mut ser = VdfSerializer::with_settings(FormatSettings::vmf_like());
+		assert!(matches!(map.serialize(&mut ser), Err(VdfErr::Unescapable(_))));
+	}
+
+	#[test]
+	fn de_conditionals() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Font {
+			name: String,
+			tall: u32,
+		}
+
+		let input = r#"
+			"name" "Verdana" [$WIN32]
+			"name" "Helvetica" [$OSX]
+			"tall" "12" [!$X360]
+			"tall" "24" [$X360]
+			"extra" [$OSX] { "ignored" "yes" [$WIN32] }
+		"#;
+
+		let windows: Symbols = vec!["$WIN32"].into_iter().collect();
+		let mut de = VdfDeserializer::new(input).evaluate_conditionals(windows);
+		assert_eq!(Font::deserialize(&mut de).unwrap(), Font { name: "Verdana".into(), tall: 12 });
+
+		let x360: Symbols = vec!["$X360"].into_iter().collect();
+		let mut de = VdfDeserializer::new(input).evaluate_conditionals(x360);
+		assert!(Font::deserialize(&mut de).is_err()); //no name
+
+		//Without evaluating them, conditionals are skipped over and every pair is read
+		let map: std::collections::BTreeMap<String, Value> = from_str(r#""a" "1" [$WIN32] "b" [$OSX] { "c" "2" }"#).unwrap();
+		assert_eq!(map.len(), 2);
+
+		assert!(matches!(from_str::<Value>(r#""a" "1" [$WIN32 &&]"#).unwrap_err().kind(), VdfErr::BadConditional(_)));
+	}
+
+	#[test]
+	fn round_trip() {
+		#[derive(serde::Serialize, Deserialize, Debug, PartialEq)]
+		enum Shape {
+			Square,
+			Circle,
+		}
+
+		#[derive(serde::Serialize, Deserialize, Debug, PartialEq)]
+		struct Thing {
+			name: String,
+			shape: Shape,
+			nothing: (),
+			numbers: Vec<i64>,
+			nested: Vec<Yea2>,
+		}
+
+		#[derive(serde::Serialize, Deserialize, Debug, PartialEq)]
+		struct Yea2 {
+			yeah_woo: String,
+			wooo_yeah: f32,
+		}
+
+		let thing = Thing {
+			name: "my thing".into(),
+			shape: Shape::Circle,
+			nothing: (),
+			numbers: vec![1, -2, 3],
+			nested: vec![Yea2 { yeah_woo: "a".into(), wooo_yeah: 1.5 }, Yea2 { yeah_woo: "b".into(), wooo_yeah: -0.25 }],
+		};
+
+		for settings in &[FormatSettings::p2c_like(), FormatSettings::vmf_like(), FormatSettings::beemod_like(), FormatSettings::gameinfo_like()] {
+			let mut ser = VdfSerializer::with_settings(settings.clone());
+			thing.serialize(&mut ser).unwrap();
+			assert_eq!(from_str_with_settings::<Thing>(&ser.into_inner(), settings.clone()).unwrap(), thing);
+		}
+
+		let s = crate::ser::to_string_with_toplevel_block(&thing, "Thing").unwrap();
+		assert_eq!(from_str_with_toplevel_block::<Thing>(&s, "Thing").unwrap(), thing);
+	}
+
+	#[test]
+	fn de_encodings() {
+		let text = "\"Lang\" { \"Tokens\" { \"Hello\" \"Hallå 👋\" } }";
+		let expected: Object = text.parse().unwrap();
+
+		let utf8_bom: Vec<u8> = b"\xEF\xBB\xBF".iter().copied().chain(text.bytes()).collect();
+		let utf16le: Vec<u8> = b"\xFF\xFE".iter().copied().chain(text.encode_utf16().flat_map(u16::to_le_bytes)).collect();
+		let utf16be: Vec<u8> = b"\xFE\xFF".iter().copied().chain(text.encode_utf16().flat_map(u16::to_be_bytes)).collect();
+
+		assert_eq!(from_slice::<Object>(text.as_bytes()).unwrap(), expected);
+		assert_eq!(from_slice::<Object>(&utf8_bom).unwrap(), expected);
+		assert_eq!(from_str::<Object>(std::str::from_utf8(&utf8_bom).unwrap()).unwrap(), expected);
+		assert_eq!(from_slice::<Object>(&utf16le).unwrap(), expected);
+		assert_eq!(from_reader::<_, Object>(&utf16be[..]).unwrap(), expected);
+
+		assert!(matches!(from_slice::<Object>(b"\"a\" \"\xFF\""), Err(VdfErr::Utf8(_))));
+		assert!(matches!(from_slice::<Object>(&utf16le[..utf16le.len() - 1]), Err(VdfErr::Utf16(_))));
+		//An unpaired surrogate right after the BOM
+		assert!(matches!(from_slice::<Object>(b"\xFF\xFE\x00\xD8a\x00"), Err(VdfErr::Utf16(2))));
+	}
+
+	#[test]
+	fn de_repeated() {
+		#[derive(Deserialize, Serialize, Debug, PartialEq)]
+		struct Editor {
+			#[serde(rename = "SubType", with = "crate::repeated")]
+			sub_types: Vec<String>,
+			#[serde(rename = "Tag", with = "crate::repeated", default)]
+			tags: Vec<u32>,
+			#[serde(rename = "MovementHandle")]
+			movement_handle: String,
+		}
+
+		//Other keys in between the repeated ones, and one of them skipped by a conditional
+		let input = r#"
+			"SubType" "a"
+			"Tag" "1"
+			"MovementHandle" "HANDLE_NONE"
+			"SubType" "b" [$X360]
+			"SubType" "c"
+			"Tag" "2"
+			"Unknown" { "SubType" "nested, so not this one" }
+		"#;
+		let expected = Editor { sub_types: vec!["a".into(), "b".into(), "c".into()], tags: vec![1, 2], movement_handle: "HANDLE_NONE".into() };
+		assert_eq!(from_str::<Editor>(input).unwrap(), expected);
+
+		let mut de = VdfDeserializer::new(input).evaluate_conditionals(Symbols::new());
+		let editor = Editor::deserialize(&mut de).unwrap();
+		assert_eq!(editor.sub_types, vec!["a", "c"]);
+
+		let written = crate::ser::to_string(&expected).unwrap();
+		assert_eq!(written.matches("SubType").count(), 3);
+		assert_eq!(from_str::<Editor>(&written).unwrap(), expected);
+
+		let none = Editor { sub_types: vec!["a".into()], tags: vec![], movement_handle: "x".into() };
+		assert_eq!(from_str::<Editor>(&crate::ser::to_string(&none).unwrap()).unwrap(), none);
+
+		//Errors point at the right one
+		let err = from_str::<Editor>(r#""SubType" "a" "MovementHandle" "x" "Tag" "1" "Tag" "nope""#).unwrap_err();
+		assert_eq!(err.location().unwrap().path, "Tag[1]");
+	}
+
+	#[test]
+	fn de_sequence_formats() {
+		#[derive(Deserialize, Serialize, Debug, PartialEq)]
+		struct Entity {
+			origin: Vec<f32>,
+			#[serde(default)]
+			solid: Vec<Solid>,
+			targetname: String,
+		}
+
+		#[derive(Deserialize, Serialize, Debug, PartialEq)]
+		struct Solid {
+			id: u32,
+		}
+
+		let entity = Entity { origin: vec![1.0, -2.5, 3.0], solid: vec![Solid { id: 1 }, Solid { id: 2 }], targetname: "door".into() };
+
+		let inline = FormatSettings::vmf_like().with_sequence_format(SequenceFormat::Inline);
+		let mut ser = VdfSerializer::with_settings(inline.clone());
+		assert!(matches!(entity.serialize(&mut ser), Err(VdfErr::ExpectedString))); //solids can't go inline
+
+		let mut ser = VdfSerializer::with_settings(inline);
+		vec!["a b"].serialize(&mut ser).unwrap(); //no key to go with it at the top level, so this is a block
+		let mut map = std::collections::BTreeMap::new();
+		map.insert("names", vec!["a b"]);
+		assert!(matches!(map.serialize(&mut ser), Err(VdfErr::Unrepresentable(_))));
+
+		let repeated = FormatSettings::vmf_like().with_sequence_format(SequenceFormat::Repeated);
+		let mut ser = VdfSerializer::with_settings(repeated.clone());
+		entity.serialize(&mut ser).unwrap();
+		let text = ser.into_inner();
+		assert_eq!(text.matches("origin").count(), 3);
+		assert_eq!(text.matches("solid").count(), 2);
+		assert_eq!(from_str_with_settings::<Entity>(&text, repeated).unwrap(), entity);
+
+		//Inline sequences can always be read, whatever the settings
+		let text = "origin \"1 -2.5 3\" solid { 0 { id 1 } 1 { id 2 } } targetname door";
+		assert_eq!(from_str::<Entity>(text).unwrap(), entity);
+
+		let err = from_str::<Entity>("origin \"1 x 3\" targetname door").unwrap_err();
+		assert_eq!(err.location().unwrap().path, "origin[1]");
+	}
+
+	#[test]
+	fn de_tuples() {
+		use crate::inline::Brackets;
+		use crate::inline::Parens;
+
+		#[derive(Deserialize, Serialize, Debug, PartialEq)]
+		struct Color(u8, u8, u8);
+
+		type Point = (f32, f32, f32);
+
+		#[derive(Deserialize, Serialize, Debug, PartialEq)]
+		struct Side {
+			plane: (Point, Point, Point),
+			uaxis: (Brackets<[f32; 4]>, f32),
+			rendercolor: Color,
+			origin: [i32; 3],
+			angles: Parens<(i32, i32, i32)>,
+		}
+
+		let side = Side {
+			plane: ((-64.0, 64.0, 64.0), (64.0, 64.0, 64.0), (64.0, -64.0, 64.0)),
+			uaxis: (Brackets([1.0, 0.0, 0.0, 0.0]), 0.25),
+			rendercolor: Color(255, 128, 0),
+			origin: [0, -16, 8],
+			angles: Parens((0, 90, 0)),
+		};
+
+		let text = crate::ser::to_string(&side).unwrap();
+		assert!(text.contains("\"(-64 64 64) (64 64 64) (64 -64 64)\""));
+		assert!(text.contains("\"[1 0 0 0] 0.25\""));
+		assert!(text.contains("\"255 128 0\""));
+		assert!(text.contains("\"0 -16 8\""));
+		assert!(text.contains("\"(0 90 0)\""));
+		assert_eq!(from_str::<Side>(&text).unwrap(), side);
+
+		//Parens and Brackets don't insist on their brackets, but nested groups need them to tell where they end
+		let text = "plane \"(0 0 0) (1 1 1) (2 2 2)\" uaxis \"[1 0 0 0] 0.25\" rendercolor \"1 2 3\" origin \"0 0 0\" angles \"0 90 0\"";
+		let read = from_str::<Side>(text).unwrap();
+		assert_eq!(read.uaxis, (Brackets([1.0, 0.0, 0.0, 0.0]), 0.25));
+		assert_eq!(read.angles, Parens((0, 90, 0)));
+
+		//Tuples at the top level have nowhere to go inline, so they're an indexed block like a Vec
+		let text = crate::ser::to_string(&(1, "two", 3.5)).unwrap();
+		assert_eq!(from_str::<(i32, String, f32)>(&text).unwrap(), (1, "two".into(), 3.5));
+
+		let err = from_str::<Side>("plane \"(0 0 0) (1 1 1\"").unwrap_err();
+		assert!(matches!(err.kind(), VdfErr::UnbalancedBrackets(_)));
+		let err = from_str::<Side>("plane \"(0 0 0) (1 1 1) (2 2 2) (3 3 3)\"").unwrap_err();
+		assert_eq!(err.location().unwrap().path, "plane");
+		assert!(from_str::<Color>("\"1 2\"").is_err());
+		assert!(crate::ser::to_string(&Side { rendercolor: Color(1, 2, 3), ..side }).is_ok());
+	}
+
+	#[test]
+	fn de_newtypes() {
+		#[derive(Deserialize, Serialize, Debug, PartialEq)]
+		struct Yea {
+			yeah_woo: String,
+		}
+
+		#[derive(Deserialize, Serialize, Debug, PartialEq)]
+		struct Wrapper(Yea);
+
+		#[derive(Deserialize, Serialize, Debug, PartialEq)]
+		struct Id(u32);
+
+		#[derive(Deserialize, Serialize, Debug, PartialEq)]
+		struct Outer {
+			wrapped: Wrapper,
+			id: Id,
+		}
+
+		let outer = Outer { wrapped: Wrapper(Yea { yeah_woo: "yeah woo".into() }), id: Id(5) };
+
+		let text = crate::ser::to_string(&outer).unwrap();
+		assert!(text.contains("\"wrapped\"\n\t{\n\t\t\"Wrapper\"\n"));
+		assert!(text.contains("\"Id\"\t\"5\""));
+		assert_eq!(from_str::<Outer>(&text).unwrap(), outer);
+
+		let text = crate::ser::to_string(&outer.wrapped).unwrap();
+		assert!(text.starts_with("\"Wrapper\"\n{"));
+		assert_eq!(from_str::<Wrapper>(&text).unwrap(), outer.wrapped);
+
+		let text = crate::ser::to_string_with_toplevel_block(&outer.wrapped, "Block").unwrap();
+		assert_eq!(from_str_with_toplevel_block::<Wrapper>(&text, "Block").unwrap(), outer.wrapped);
+
+		let transparent = FormatSettings::p2c_like().with_newtype_format(NewtypeFormat::Transparent);
+		let mut ser = VdfSerializer::with_settings(transparent.clone());
+		outer.serialize(&mut ser).unwrap();
+		let text = ser.into_inner();
+		assert!(!text.contains("Wrapper") && !text.contains("Id"));
+		assert!(text.contains("\"id\"\t\"5\""));
+		assert_eq!(from_str_with_settings::<Outer>(&text, transparent).unwrap(), outer);
+
+		let err = from_str::<Outer>("wrapped { Wrapper { yeah_woo a } Extra {} } id { Id 5 }").unwrap_err();
+		assert!(matches!(err.kind(), VdfErr::ExtraKey(found) if found == "Extra"));
+		let err = from_str::<Outer>("wrapped { Wrapper { yeah_woo a } } id { Nope 5 }").unwrap_err();
+		assert!(matches!(err.kind(), VdfErr::UnexpectedKey { .. }));
+	}
+
+	#[test]
+	fn de_enums() {
+		#[derive(Deserialize, Serialize, Debug, PartialEq)]
+		enum SubType {
+			Plain,
+			Model(String),
+			Offset(i32, i32, i32),
+			Button { skin: u8, sound: String },
+		}
+
+		#[derive(Deserialize, Serialize, Debug, PartialEq)]
+		struct Item {
+			#[serde(rename = "SubType", with = "crate::repeated")]
+			subtypes: Vec<SubType>,
+		}
+
+		let item = Item {
+			subtypes: vec![SubType::Plain, SubType::Model("cube.mdl".into()), SubType::Offset(0, -8, 64), SubType::Button { skin: 2, sound: "click".into() }],
+		};
+
+		let text = crate::ser::to_string(&item).unwrap();
+		assert!(text.contains("\"SubType\"\t\"Plain\""));
+		assert!(text.contains("\"Offset\"\t\"0 -8 64\""));
+		assert_eq!(from_str::<Item>(&text).unwrap(), item);
+
+		//At the top level, the variant's block is the file
+		let text = "Button { skin 1 sound beep }";
+		assert_eq!(from_str::<SubType>(text).unwrap(), SubType::Button { skin: 1, sound: "beep".into() });
+		let button = SubType::Button { skin: 1, sound: "beep".into() };
+		let text = crate::ser::to_string(&button).unwrap();
+		assert_eq!(text, "\"Button\"\n{\n\t\"skin\"\t\"1\"\n\t\"sound\"\t\"beep\"\n}\n");
+		assert_eq!(from_str::<SubType>(&text).unwrap(), button);
+		for subtype in [SubType::Model("cube.mdl".into()), SubType::Offset(0, -8, 64)] {
+			let text = crate::ser::to_string(&subtype).unwrap();
+			assert!(!text.starts_with('\n'), "{:?}", text);
+			assert_eq!(from_str::<SubType>(&text).unwrap(), subtype);
+		}
+
+		let err = from_str::<Item>("SubType { Model cube.mdl Offset \"1 2 3\" }").unwrap_err();
+		assert!(matches!(err.kind(), VdfErr::ExtraKey(found) if found == "Offset"));
+		let err = from_str::<Item>("SubType { Button { skin x sound beep } }").unwrap_err();
+		assert_eq!(err.location().unwrap().path, "SubType.Button.skin");
+
+		//Internally and adjacently tagged enums go through deserialize_any, so their fields have to be strings
+		#[derive(Deserialize, Serialize, Debug, PartialEq)]
+		#[serde(tag = "classname")]
+		enum Entity {
+			#[serde(rename = "light")]
+			Light { targetname: String, _light: String },
+			#[serde(rename = "info_player_start")]
+			PlayerStart { origin: String },
+		}
+
+		let entities = vec![Entity::PlayerStart { origin: "0 0 64".into() }, Entity::Light { targetname: "lamp".into(), _light: "255 255 255 200".into() }];
+		let text = crate::ser::to_string(&entities).unwrap();
+		assert!(text.contains("\"classname\"\t\"light\""));
+		assert_eq!(from_str::<Vec<Entity>>(&text).unwrap(), entities);
+
+		#[derive(Deserialize, Serialize, Debug, PartialEq)]
+		#[serde(tag = "type", content = "data")]
+		enum Adjacent {
+			Plain,
+			Model(String),
+			Pair(String, String),
+			Named { name: String },
+		}
+
+		for adjacent in [Adjacent::Plain, Adjacent::Model("cube.mdl".into()), Adjacent::Pair("a".into(), "b".into()), Adjacent::Named { name: "x".into() }] {
+			let text = crate::ser::to_string(&adjacent).unwrap();
+			assert_eq!(from_str::<Adjacent>(&text).unwrap(), adjacent, "{}", text);
+		}
+	}
+
+	#[test]
+	fn de_case_insensitive() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		enum Handle {
+			Quad,
+			Corner(String),
+		}
+
+		#[derive(Deserialize, Debug, PartialEq)]
+		#[serde(rename_all = "PascalCase")]
+		struct Editor {
+			movement_handle: Handle,
+			#[serde(rename = "SubType", with = "crate::repeated")]
+			subtypes: Vec<String>,
+		}
+
+		let read = |text: &str, on_ambiguity: Ambiguity| {
+			let mut de = VdfDeserializer::new(text).case_insensitive(on_ambiguity);
+			let editor = Editor::deserialize(&mut de).and_then(|editor| de.end().map(|_| editor)).map_err(|err| de.locate(err));
+			(editor, de.warnings().iter().map(|warning| warning.to_string()).collect::<Vec<_>>())
+		};
+
+		let text = "movementhandle QUAD SUBTYPE a subType b";
+		assert!(from_str::<Editor>(text).is_err());
+		let (editor, warnings) = read(text, Ambiguity::Error);
+		assert_eq!(editor.unwrap(), Editor { movement_handle: Handle::Quad, subtypes: vec!["a".into(), "b".into()] });
+		assert!(warnings.is_empty());
+
+		let (editor, _) = read("MovementHandle { corner x } SubType a", Ambiguity::Error);
+		assert_eq!(editor.unwrap().movement_handle, Handle::Corner("x".into()));
+
+		//Two spellings of the same field
+		let text = "MovementHandle Quad\nmovementHANDLE { Corner y } SubType a";
+		let (editor, warnings) = read(text, Ambiguity::Warn);
+		assert_eq!(editor.unwrap().movement_handle, Handle::Quad);
+		assert_eq!(
+			warnings,
+			vec!["The key \"movementHANDLE\" is the same as the earlier \"MovementHandle\" when ignoring case (line 2, column 1, at movementHANDLE)"]
+		);
+		let (editor, _) = read(text, Ambiguity::Error);
+		assert!(matches!(editor.unwrap_err().kind(), VdfErr::DuplicateKey { .. }));
+
+		//A key that could be either of two fields
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Confusing {
+			name: Option<String>,
+			#[serde(rename = "Name")]
+			other_name: Option<String>,
+		}
+
+		let mut de = VdfDeserializer::new("NAME x").case_insensitive(Ambiguity::Warn);
+		assert_eq!(Confusing::deserialize(&mut de).unwrap(), Confusing { name: Some("x".into()), other_name: None });
+		assert!(matches!(de.warnings()[0].kind(), VdfErr::AmbiguousKey { candidates, .. } if candidates.len() == 2));
+		let mut de = VdfDeserializer::new("Name x name y").case_insensitive(Ambiguity::Error);
+		assert_eq!(Confusing::deserialize(&mut de).unwrap(), Confusing { name: Some("y".into()), other_name: Some("x".into()) });
+		let mut de = VdfDeserializer::new("NAME x").case_insensitive(Ambiguity::Error);
+		assert!(matches!(Confusing::deserialize(&mut de).unwrap_err(), VdfErr::AmbiguousKey { .. }));
 	}
 }
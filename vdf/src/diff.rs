@@ -0,0 +1,456 @@
+//! Comparing and merging VDF documents by their structure instead of line-by-line.
+//!
+//! Pairs are lined up by key path, not by where they are in the file, so moving things around isn't a change. The tricky part is
+//! repeated keys. Normally the first `"Item"` in one document is compared with the first `"Item"` in the other, the second with
+//! the second, and so on. That falls apart as soon as someone adds an item in the middle, so `DiffOptions::with_id_key` can name
+//! a child key that identifies each block instead: with `"Type"`, the `"Item"` whose `"Type"` is `"ITEM_CUBE"` is compared with
+//! the other `"Item"` whose `"Type"` is `"ITEM_CUBE"`, wherever it is. Id keys are only used for a group of repeated keys when
+//! every one of them is a block with a unique string value for it; otherwise it's back to going by position.
+//!
+//! Pairs with different conditionals are different keys, so `"Skin" "2" [$X360]` never lines up with `"Skin" "2" [$PS3]`.
+//!
+//! ```
+//! # use vdf::diff::*;
+//! # use vdf::value::Object;
+//! let old: Object = r#"Item { Type ITEM_BUTTON } Item { Type ITEM_CUBE Offset "0 0 0" }"#.parse().unwrap();
+//! let new: Object = r#"Item { Type ITEM_LASER } Item { Type ITEM_BUTTON } Item { Type ITEM_CUBE Offset "0 0 8" }"#.parse().unwrap();
+//!
+//! let changes = diff(&old, &new, &DiffOptions::new().with_id_key("Type"));
+//! assert_eq!(changes.len(), 2);
+//! assert_eq!(changes[0].to_string(), r#"~ Item[Type=ITEM_CUBE]/Offset "0 0 0" -> "0 0 8""#);
+//! assert_eq!(changes[1].to_string(), "+ Item[Type=ITEM_LASER] {...}");
+//! ```
+
+use std::fmt;
+
+use crate::conditional::Conditional;
+use crate::value::Kv;
+use crate::value::Object;
+use crate::value::Value;
+
+/// How to line up repeated keys.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DiffOptions {
+	id_keys: Vec<String>,
+}
+
+/// Where a pair is in a document: the steps from the root block down to it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct KeyPath {
+	steps: Vec<Step>,
+}
+
+/// One step in a `KeyPath`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Step {
+	pub key: String,
+	pub condition: Option<Conditional>,
+	/// Which one of the pairs with this key it is.
+	pub locator: Locator,
+}
+
+/// Which one of a group of repeated keys a `Step` means.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Locator {
+	/// The key isn't repeated.
+	Only,
+	/// The n-th pair with the key and conditional, counting from 0.
+	Nth(usize),
+	/// The block with the key whose child `key` is `value`.
+	Id { key: String, value: String },
+}
+
+/// One difference between two documents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+	pub path: KeyPath,
+	pub kind: ChangeKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeKind {
+	/// The pair is only in the new document.
+	Added(Value),
+	/// The pair is only in the old document.
+	Removed(Value),
+	/// The pair is in both, with a different value. (If both values are blocks, the changes inside them are listed instead.)
+	Changed { old: Value, new: Value },
+}
+
+/// The result of a three-way merge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Merge {
+	/// The merged document. Where there's a conflict, it has our side of it, unless we removed a pair they changed: then it has
+	/// theirs, so nothing is lost without someone looking at it.
+	pub merged: Object,
+	pub conflicts: Vec<Conflict>,
+}
+
+/// A pair that both sides of a merge changed in different ways. `None` means the pair isn't there on that side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+	pub path: KeyPath,
+	pub base: Option<Value>,
+	pub ours: Option<Value>,
+	pub theirs: Option<Value>,
+}
+
+impl DiffOptions {
+	/// Lines up repeated keys by position.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Lines up repeated blocks by the value of this child key, if they all have a different one. Can be called more than once,
+	/// and the first id key that works for a group of repeated keys is the one that gets used.
+	pub fn with_id_key(mut self, key: impl Into<String>) -> Self {
+		self.id_keys.push(key.into());
+		self
+	}
+}
+
+impl KeyPath {
+	pub fn steps(&self) -> &[Step] {
+		&self.steps
+	}
+}
+
+impl Merge {
+	/// Whether the merge went through without any conflicts.
+	pub fn is_clean(&self) -> bool {
+		self.conflicts.is_empty()
+	}
+}
+
+/// Lists the differences between two documents, in the order they come up in `old`, followed by everything that was added.
+pub fn diff(old: &Object, new: &Object, options: &DiffOptions) -> Vec<Change> {
+	let mut changes = Vec::new();
+	diff_into(&mut Vec::new(), old, new, options, &mut changes);
+	changes
+}
+
+fn diff_into(path: &mut Vec<Step>, old: &Object, new: &Object, options: &DiffOptions, changes: &mut Vec<Change>) {
+	let matching = match_up(&[old, new], options);
+	let mut order = matching.order[0].clone();
+	order.extend(matching.order[1].iter().filter(|n| matching.entries[**n].1[0].is_none()));
+
+	for n in order {
+		let (step, kvs) = &matching.entries[n];
+		path.push(step.clone());
+		let kind = match (kvs[0], kvs[1]) {
+			(Some(old), Some(new)) => match (&old.value, &new.value) {
+				(Value::Obj(old), Value::Obj(new)) => {
+					diff_into(path, old, new, options, changes);
+					None
+				},
+				(old, new) if old != new => Some(ChangeKind::Changed { old: old.clone(), new: new.clone() }),
+				_ => None,
+			},
+			(Some(old), None) => Some(ChangeKind::Removed(old.value.clone())),
+			(None, Some(new)) => Some(ChangeKind::Added(new.value.clone())),
+			(None, None) => None,
+		};
+		if let Some(kind) = kind {
+			changes.push(Change { path: KeyPath { steps: path.clone() }, kind });
+		}
+		path.pop();
+	}
+}
+
+/// Three-way merges the changes from `base` to `ours` and from `base` to `theirs`.
+///
+/// A pair that only one side changed gets that side's change. When both sides changed the same block, the merge carries on inside
+/// it, so two people editing different keys of the same item is fine. Anything else both sides changed differently is a conflict.
+///
+/// The merged document keeps our order, with pairs that only they added slotted in after whatever came before them on their side.
+pub fn merge(base: &Object, ours: &Object, theirs: &Object, options: &DiffOptions) -> Merge {
+	let mut conflicts = Vec::new();
+	let merged = merge_into(&mut Vec::new(), base, ours, theirs, options, &mut conflicts);
+	Merge { merged, conflicts }
+}
+
+fn merge_into(path: &mut Vec<Step>, base: &Object, ours: &Object, theirs: &Object, options: &DiffOptions, conflicts: &mut Vec<Conflict>) -> Object {
+	let matching = match_up(&[ours, theirs, base], options);
+	let mut resolved: Vec<Option<Kv>> = matching
+		.entries
+		.iter()
+		.map(|(step, kvs)| {
+			path.push(step.clone());
+			let kv = resolve(path, kvs[2], kvs[0], kvs[1], options, conflicts);
+			path.pop();
+			kv
+		})
+		.collect();
+
+	let mut layout = matching.order[0].clone();
+	let mut previous = None;
+	for &n in &matching.order[1] {
+		if !layout.contains(&n) {
+			let at = match previous {
+				Some(previous) => layout.iter().position(|m| *m == previous).map_or(layout.len(), |i| i + 1),
+				None => 0,
+			};
+			layout.insert(at, n);
+		}
+		previous = Some(n);
+	}
+
+	Object::from(layout.into_iter().filter_map(|n| resolved[n].take()).collect::<Vec<_>>())
+}
+
+fn resolve(
+	path: &mut Vec<Step>,
+	base: Option<&Kv>,
+	ours: Option<&Kv>,
+	theirs: Option<&Kv>,
+	options: &DiffOptions,
+	conflicts: &mut Vec<Conflict>,
+) -> Option<Kv> {
+	if ours == theirs || base == theirs {
+		return ours.cloned();
+	}
+	if base == ours {
+		return theirs.cloned();
+	}
+
+	if let (Some(ours), Some(theirs)) = (ours, theirs) {
+		if let (Value::Obj(ours_obj), Value::Obj(theirs_obj)) = (&ours.value, &theirs.value) {
+			let empty = Object::new();
+			let base_obj = base.and_then(|b| b.value.as_obj()).unwrap_or(&empty);
+			let merged = merge_into(path, base_obj, ours_obj, theirs_obj, options, conflicts);
+			return Some(Kv { key: ours.key.clone(), value: Value::Obj(merged), condition: ours.condition.clone() });
+		}
+	}
+
+	let value = |kv: Option<&Kv>| kv.map(|kv| kv.value.clone());
+	conflicts.push(Conflict { path: KeyPath { steps: path.clone() }, base: value(base), ours: value(ours), theirs: value(theirs) });
+	//Our side wins, but a removal loses to a change so the change can still be looked at
+	ours.or(theirs).cloned()
+}
+
+/// The pairs of several blocks, lined up with each other.
+struct Matching<'a> {
+	/// Each step, and the pair it leads to in each block (if it's there).
+	entries: Vec<(Step, Vec<Option<&'a Kv>>)>,
+	/// For each block, the index into `entries` of each of its pairs, in order.
+	order: Vec<Vec<usize>>,
+}
+
+/// A key and conditional, and every pair that has them (with its position) in each block.
+type Group<'a> = (&'a str, Option<&'a Conditional>, Vec<Vec<(usize, &'a Kv)>>);
+
+fn match_up<'a>(sides: &[&'a Object], options: &DiffOptions) -> Matching<'a> {
+	//Group the pairs by key and conditional, remembering where each one was
+	let mut groups: Vec<Group<'a>> = Vec::new();
+	for (side, object) in sides.iter().enumerate() {
+		for (i, kv) in object.iter().enumerate() {
+			let condition = kv.condition.as_ref();
+			let group = match groups.iter().position(|(key, cond, _)| *key == kv.key && *cond == condition) {
+				Some(group) => group,
+				None => {
+					groups.push((&kv.key, condition, vec![Vec::new(); sides.len()]));
+					groups.len() - 1
+				},
+			};
+			groups[group].2[side].push((i, kv));
+		}
+	}
+
+	let conditioned: Vec<&str> = groups.iter().filter(|(_, condition, _)| condition.is_some()).map(|(key, _, _)| *key).collect();
+	let mut entries: Vec<(Step, Vec<Option<&'a Kv>>)> = Vec::new();
+	let mut order: Vec<Vec<usize>> = sides.iter().map(|object| vec![0; object.len()]).collect();
+	for (key, condition, members) in groups {
+		let first = entries.len();
+		let mixed = condition.is_none() && conditioned.contains(&key);
+		for (side, (kvs, locators)) in members.iter().zip(locators(&members, options, mixed)).enumerate() {
+			for (&(i, kv), locator) in kvs.iter().zip(locators) {
+				let n = match entries[first..].iter().position(|(step, _)| step.locator == locator) {
+					Some(n) => first + n,
+					None => {
+						entries.push((Step { key: key.to_string(), condition: condition.cloned(), locator }, vec![None; sides.len()]));
+						entries.len() - 1
+					},
+				};
+				entries[n].1[side] = Some(kv);
+				order[side][i] = n;
+			}
+		}
+	}
+
+	Matching { entries, order }
+}
+
+/// Works out how to tell apart the pairs of one group of repeated keys, across all the blocks being compared.
+///
+/// `mixed` is for pairs without a conditional when the same key also shows up with one. Those always get an index, since that's
+/// how a query only picks the ones without a conditional.
+fn locators(members: &[Vec<(usize, &Kv)>], options: &DiffOptions, mixed: bool) -> Vec<Vec<Locator>> {
+	if mixed {
+		return members.iter().map(|kvs| (0..kvs.len()).map(Locator::Nth).collect()).collect();
+	}
+	if members.iter().all(|kvs| kvs.len() <= 1) {
+		return members.iter().map(|kvs| kvs.iter().map(|_| Locator::Only).collect()).collect();
+	}
+
+	for id_key in &options.id_keys {
+		let ids: Option<Vec<Vec<&str>>> = members.iter().map(|kvs| kvs.iter().map(|(_, kv)| kv.value.get(id_key).and_then(Value::as_str)).collect()).collect();
+		if let Some(ids) = ids {
+			if ids.iter().all(|ids| ids.iter().enumerate().all(|(i, id)| !ids[..i].contains(id))) {
+				return ids.into_iter().map(|ids| ids.into_iter().map(|id| Locator::Id { key: id_key.clone(), value: id.to_string() }).collect()).collect();
+			}
+		}
+	}
+
+	members.iter().map(|kvs| (0..kvs.len()).map(Locator::Nth).collect()).collect()
+}
+
+/// Writes a key as-is, or in quotes if it has anything in it that would make the path ambiguous. Quoted keys only escape `\"` and
+/// `\\`, which is all `vdf::query` understands.
+fn write_key(f: &mut fmt::Formatter<'_>, key: &str) -> fmt::Result {
+	if key.is_empty() || key.chars().any(|c| c.is_whitespace() || "/[]=!\"*".contains(c)) {
+		write!(f, "\"{}\"", key.replace('\\', "\\\\").replace('"', "\\\""))
+	} else {
+		f.write_str(key)
+	}
+}
+
+/// Strings in quotes, blocks as `{...}`.
+struct Short<'a>(Option<&'a Value>);
+
+impl fmt::Display for Short<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.0 {
+			Some(Value::Str(s)) => write!(f, "{:?}", s),
+			Some(Value::Obj(_)) => f.write_str("{...}"),
+			None => f.write_str("(nothing)"),
+		}
+	}
+}
+
+/// Steps are separated with `/`, like `Item[Type=ITEM_CUBE]/Exporting/Offset`.
+impl fmt::Display for KeyPath {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for (i, step) in self.steps.iter().enumerate() {
+			if i != 0 {
+				f.write_str("/")?;
+			}
+			write!(f, "{}", step)?;
+		}
+		Ok(())
+	}
+}
+
+/// Like `Item`, `Item[2]` or `Item[Type=ITEM_CUBE]`, followed by the conditional if there is one, like `Skin [$X360]`.
+impl fmt::Display for Step {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write_key(f, &self.key)?;
+		match &self.locator {
+			Locator::Only => {},
+			Locator::Nth(n) => write!(f, "[{}]", n)?,
+			Locator::Id { key, value } => {
+				f.write_str("[")?;
+				write_key(f, key)?;
+				f.write_str("=")?;
+				write_key(f, value)?;
+				f.write_str("]")?;
+			},
+		}
+		if let Some(condition) = &self.condition {
+			write!(f, " {}", condition)?;
+		}
+		Ok(())
+	}
+}
+
+/// Like `+ path "value"`, `- path "value"` or `~ path "old" -> "new"`.
+impl fmt::Display for Change {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match &self.kind {
+			ChangeKind::Added(value) => write!(f, "+ {} {}", self.path, Short(Some(value))),
+			ChangeKind::Removed(value) => write!(f, "- {} {}", self.path, Short(Some(value))),
+			ChangeKind::Changed { old, new } => write!(f, "~ {} {} -> {}", self.path, Short(Some(old)), Short(Some(new))),
+		}
+	}
+}
+
+impl fmt::Display for Conflict {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}: was {}, ours is {}, theirs is {}", self.path, Short(self.base.as_ref()), Short(self.ours.as_ref()), Short(self.theirs.as_ref()))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn obj(text: &str) -> Object {
+		text.parse().unwrap()
+	}
+
+	fn lines<T: ToString>(things: &[T]) -> Vec<String> {
+		things.iter().map(T::to_string).collect()
+	}
+
+	#[test]
+	fn diffs() {
+		let old = obj(r#"a 1 b { c 2 d 3 } e x e y "f g" 4 h 5 [$X360]"#);
+		let new = obj(r#"b { d 3 c 20 } a 1 e x e z e w "f g" { } h 6 [$X360] h 7 [$PS3]"#);
+		assert_eq!(
+			lines(&diff(&old, &new, &DiffOptions::new())),
+			vec![r#"~ b/c "2" -> "20""#, r#"~ e[1] "y" -> "z""#, r#"~ "f g" "4" -> {...}"#, r#"~ h [$X360] "5" -> "6""#, r#"+ e[2] "w""#, r#"+ h [$PS3] "7""#,]
+		);
+		assert!(diff(&old, &old, &DiffOptions::new()).is_empty());
+	}
+
+	#[test]
+	fn id_keys() {
+		let old = obj("Item { Type A Pos 1 } Item { Type B Pos 2 } Item { Type C Pos 3 }");
+		let new = obj("Item { Type A Pos 1 } Item { Type C Pos 30 }");
+
+		//By position, B turned into C and C disappeared
+		assert_eq!(lines(&diff(&old, &new, &DiffOptions::new())), vec![r#"~ Item[1]/Type "B" -> "C""#, r#"~ Item[1]/Pos "2" -> "30""#, "- Item[2] {...}"]);
+
+		let by_type = DiffOptions::new().with_id_key("ID").with_id_key("Type");
+		assert_eq!(lines(&diff(&old, &new, &by_type)), vec!["- Item[Type=B] {...}", r#"~ Item[Type=C]/Pos "3" -> "30""#]);
+
+		//Not usable if there's a duplicate, or one is missing
+		let dupes = obj("Item { Type A Pos 1 } Item { Type A Pos 2 }");
+		assert_eq!(lines(&diff(&dupes, &obj("Item { Type A Pos 1 }"), &by_type)), vec!["- Item[1] {...}"]);
+		let missing = obj("Item { Type A } Item { Pos 2 }");
+		assert_eq!(lines(&diff(&missing, &obj("Item { Type A }"), &by_type)), vec!["- Item[1] {...}"]);
+	}
+
+	#[test]
+	fn clean_merges() {
+		let base = obj("Item { Type A Pos 1 Color red } Item { Type B Pos 2 } Item { Type C Pos 3 }");
+		let ours = obj("Item { Type A Pos 10 Color red } Item { Type B Pos 2 } Item { Type D Pos 4 } Item { Type C Pos 3 }");
+		let theirs = obj("Item { Type A Pos 1 Color blue } Item { Type E Pos 5 } Item { Type C Pos 3 }");
+		let merged = merge(&base, &ours, &theirs, &DiffOptions::new().with_id_key("Type"));
+		assert!(merged.is_clean(), "{:?}", merged.conflicts);
+		assert_eq!(merged.merged, obj("Item { Type A Pos 10 Color blue } Item { Type E Pos 5 } Item { Type D Pos 4 } Item { Type C Pos 3 }"));
+
+		//Both sides doing the same thing is fine too
+		let both = merge(&base, &ours, &ours, &DiffOptions::new().with_id_key("Type"));
+		assert!(both.is_clean());
+		assert_eq!(both.merged, ours);
+	}
+
+	#[test]
+	fn conflicts() {
+		let options = DiffOptions::new().with_id_key("Type");
+		let base = obj("Item { Type A Pos 1 Color red } Item { Type B Pos 2 }");
+		let ours = obj("Item { Type A Pos 10 Color green } Item { Type B Pos 20 }");
+		let theirs = obj("Item { Type A Pos 1 Color blue } New { }");
+		let merge = merge(&base, &ours, &theirs, &options);
+		assert_eq!(
+			lines(&merge.conflicts),
+			vec![r#"Item[Type=A]/Color: was "red", ours is "green", theirs is "blue""#, r#"Item[Type=B]: was {...}, ours is {...}, theirs is (nothing)"#,]
+		);
+		assert_eq!(merge.merged, obj("Item { Type A Pos 10 Color green } New { } Item { Type B Pos 20 }"));
+
+		//When we deleted something they changed, their version is kept
+		let merge = super::merge(&base, &obj("Item { Type A Pos 1 Color red }"), &obj("Item { Type A Pos 1 Color red } Item { Type B Pos 3 }"), &options);
+		assert_eq!(merge.conflicts.len(), 1);
+		assert_eq!(merge.merged, obj("Item { Type A Pos 1 Color red } Item { Type B Pos 3 }"));
+	}
+}
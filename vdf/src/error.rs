@@ -1,7 +1,9 @@
 use std::char::ParseCharError;
 use std::fmt;
+use std::io;
 use std::num::ParseFloatError;
 use std::num::ParseIntError;
+use std::str::Utf8Error;
 
 use serde::de;
 use serde::ser;
@@ -24,6 +26,124 @@ pub enum VdfErr {
 	ParseFloat(ParseFloatError),
 	#[error("Could not parse char: {0}")]
 	ParseChar(ParseCharError),
+	/// When deserializing, a string was expected, but a `{`-delimited block was found instead.
+	#[error("Expected a string, but found a block")]
+	ExpectedString,
+	/// When deserializing, a `{`-delimited block was expected, but a string was found instead.
+	#[error("Expected a block, but found a string")]
+	ExpectedBlock,
+	/// When deserializing, a unit value (the empty string) was expected, but something else was found.
+	#[error("Expected an empty string, but found {0:?}")]
+	ExpectedUnit(String),
+	/// When deserializing, a specific key was expected (such as the name of a top-level block), but a different one was found.
+	#[error("Expected the key {expected:?}, but found {found:?}")]
+	UnexpectedKey { expected: String, found: String },
+	/// When deserializing, a `}` was found that doesn't close any block.
+	#[error("Unexpected closing brace")]
+	UnexpectedCloseBrace,
+	/// A conditional like `[$WIN32]` couldn't be understood.
+	#[error("Malformed conditional [{0}]")]
+	BadConditional(String),
+	/// The loader callback couldn't load a file named by an `#include` or `#base` directive.
+	#[error("Could not load {path:?}: {source}")]
+	Include { path: String, source: io::Error },
+	/// A file (indirectly) includes itself with `#include` or `#base`. The chain of files is listed, starting and ending with the same one.
+	#[error("Include cycle: {}", .0.join(" -> "))]
+	IncludeCycle(Vec<String>),
+	/// When serializing without escape sequences, a string contained a double quote, which can't be written.
+	#[error("Can't write {0:?} without escape sequences")]
+	Unescapable(String),
+	/// When reading binary VDF, a type byte that isn't part of the format was found.
+	#[error("Unknown binary VDF type {0:#04x}")]
+	BadType(u8),
+	/// Text that was supposed to be UTF-8 wasn't.
+	#[error("Invalid UTF-8: {0}")]
+	Utf8(Utf8Error),
+	/// Text that was supposed to be UTF-16 wasn't, starting around the given byte offset.
+	#[error("Invalid UTF-16 at byte {0}")]
+	Utf16(usize),
+	/// A key or value can't be represented in the output format, like a string containing a NUL byte in binary VDF.
+	#[error("Can't write {0:?}")]
+	Unrepresentable(String),
+	/// When serializing, the writer failed.
+	#[error("Couldn't write output: {0}")]
+	Fmt(#[from] fmt::Error),
+	/// When serializing into an IO stream, the stream failed.
+	#[error("IO error: {0}")]
+	Io(#[from] io::Error),
+	/// When converting to or from JSON, serde_json had a problem.
+	#[cfg(feature = "json")]
+	#[error("JSON error: {0}")]
+	Json(#[from] serde_json::Error),
+	/// An inline value like `"(0 0 0) (1 0 0)"` has a bracket that doesn't match up with another one.
+	#[error("Unbalanced brackets in {0:?}")]
+	UnbalancedBrackets(String),
+	/// When deserializing, a block that should only hold one entry (an enum variant, or a named newtype struct) had another key in it.
+	/// Or a binary tuple had more elements than it should.
+	#[error("Expected the end of the block, but found another key, {0:?}")]
+	ExtraKey(String),
+	/// When deserializing case-insensitively, a key matched more than one struct field or enum variant.
+	#[error("The key {key:?} could be any of {candidates:?} when ignoring case")]
+	AmbiguousKey { key: String, candidates: Vec<String> },
+	/// When deserializing case-insensitively, a block had two keys for the same struct field that only differ by case.
+	#[error("The key {key:?} is the same as the earlier {earlier:?} when ignoring case")]
+	DuplicateKey { key: String, earlier: String },
+	/// When reading a typed model like `vdf::vmf`, a key that has to be there wasn't.
+	#[error("Missing the key {0:?}")]
+	MissingKey(String),
+	/// When reading a typed model like `vdf::vmf`, a value couldn't be understood.
+	#[error("Expected {expected} for {key:?}, but found {value:?}")]
+	InvalidValue { key: String, value: String, expected: &'static str },
+	/// A `vdf::query` path couldn't be understood.
+	#[error("Bad query {query:?}: {problem}")]
+	BadQuery { query: String, problem: String },
+	/// When deserializing, there was more data in the file after the value was completely read.
+	#[error("Trailing characters after the end of the document")]
+	TrailingCharacters,
+	/// A deserialization error, along with where in the text it happened.
+	#[error("{source} ({location})")]
+	Located { location: Location, source: Box<VdfErr> },
+}
+
+impl VdfErr {
+	/// Where in the file the error happened, if known. Errors from reading VDF text always know.
+	pub fn location(&self) -> Option<&Location> {
+		match self {
+			VdfErr::Located { location, .. } => Some(location),
+			_ => None,
+		}
+	}
+
+	/// The error itself, without the location. Handy for `match`ing on.
+	pub fn kind(&self) -> &VdfErr {
+		match self {
+			VdfErr::Located { source, .. } => source.kind(),
+			other => other,
+		}
+	}
+}
+
+/// A position in a VDF file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+	/// Line number, starting from 1.
+	pub line: usize,
+	/// Column number in characters, starting from 1.
+	pub column: usize,
+	/// The keys leading to the value that was being read, like `ItemData.Item[12].Editor.SubType`. Sequence elements get an
+	/// index in square brackets, and so does a key that already appeared earlier in the same block (the 13th `Item` is
+	/// `Item[12]`). Empty at the top level of the file.
+	pub path: String,
+}
+
+impl fmt::Display for Location {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "line {}, column {}", self.line, self.column)?;
+		if !self.path.is_empty() {
+			write!(f, ", at {}", self.path)?;
+		}
+		Ok(())
+	}
 }
 
 /// Alias for `Result<T, VdfErr>`. I don't really like Result aliases, but ok, here you go.
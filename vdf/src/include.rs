@@ -0,0 +1,225 @@
+//! `#base` and `#include` directives.
+//!
+//! Valve resource files (HUD `.res` files, `scripts/*.txt`, and so on) can start with lines like `#base "other.res"`, which pull
+//! the contents of another file into this one. Paths are relative to the directory of the file containing the directive.
+//!
+//! * `#include "file"` appends the top-level keys of the other file after the keys of this one.
+//! * `#base "file"` merges the other file into this one. Keys in this file take precedence over keys in the base file; when both
+//!   files have a block with the same name, the blocks are merged recursively. Keys are compared case-insensitively, like Valve does.
+//!
+//! The directives are ordinary key-value pairs as far as the parser is concerned, so `Object`s keep them until they're resolved.
+
+use std::io;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::de::VdfDeserializer;
+use crate::error::VdfErr;
+use crate::ser::FormatSettings;
+use crate::value::Kv;
+use crate::value::Object;
+use crate::value::Value;
+
+/// One `#base` or `#include` line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Directive {
+	Base(String),
+	Include(String),
+}
+
+impl Directive {
+	/// Returns the directive this key-value pair represents, if it is one.
+	pub fn from_kv(kv: &Kv) -> Option<Directive> {
+		let path = kv.value.as_str()?.to_string();
+		if kv.key.eq_ignore_ascii_case("#base") {
+			Some(Directive::Base(path))
+		} else if kv.key.eq_ignore_ascii_case("#include") {
+			Some(Directive::Include(path))
+		} else {
+			None
+		}
+	}
+
+	pub fn path(&self) -> &str {
+		match self {
+			Directive::Base(path) | Directive::Include(path) => path,
+		}
+	}
+}
+
+/// Removes the directives from the top level of the object and returns them, in order.
+pub fn take_directives(object: &mut Object) -> Vec<Directive> {
+	let directives = object.iter().filter_map(Directive::from_kv).collect();
+	object.retain(|kv| Directive::from_kv(kv).is_none());
+	directives
+}
+
+/// Loads the file at `path` and resolves all of its directives, recursively.
+///
+/// `loader` is called with the path of every file that needs to be read, and returns its contents. It could read from the
+/// filesystem, or from an in-memory map of files, or from inside a VPK, or whatever you like.
+pub fn resolve<F>(path: &str, loader: F) -> Result<Object, VdfErr>
+where
+	F: FnMut(&Path) -> io::Result<String>,
+{
+	Resolver::new(loader).resolve(path)
+}
+
+/// Resolves `#base` and `#include` directives, with a user-supplied way of loading files.
+pub struct Resolver<F> {
+	loader: F,
+	settings: FormatSettings,
+	/// The files currently being resolved, outermost first. Used to detect cycles.
+	stack: Vec<PathBuf>,
+}
+
+impl<F> Resolver<F>
+where
+	F: FnMut(&Path) -> io::Result<String>,
+{
+	pub fn new(loader: F) -> Self {
+		Resolver::with_settings(loader, FormatSettings::p2c_like())
+	}
+
+	/// Create a resolver that parses files with the given settings.
+	pub fn with_settings(loader: F, settings: FormatSettings) -> Self {
+		Resolver { loader, settings, stack: Vec::new() }
+	}
+
+	/// Loads the file at `path` and resolves all of its directives, recursively.
+	pub fn resolve(&mut self, path: &str) -> Result<Object, VdfErr> {
+		//Might be left over from a previous call that failed partway through
+		self.stack.clear();
+		self.resolve_path(normalize(Path::new(path)))
+	}
+
+	fn resolve_path(&mut self, path: PathBuf) -> Result<Object, VdfErr> {
+		if self.stack.contains(&path) {
+			let mut cycle: Vec<String> = self.stack.iter().skip_while(|p| **p != path).map(|p| p.display().to_string()).collect();
+			cycle.push(path.display().to_string());
+			return Err(VdfErr::IncludeCycle(cycle));
+		}
+
+		let text = (self.loader)(&path).map_err(|source| VdfErr::Include { path: path.display().to_string(), source })?;
+		let mut object = VdfDeserializer::with_settings(&text, self.settings.clone()).parse_object()?;
+		let directives = take_directives(&mut object);
+
+		self.stack.push(path.clone());
+		let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+		let mut bases = Vec::new();
+		for directive in directives {
+			let other = self.resolve_path(normalize(&dir.join(directive.path())))?;
+			match directive {
+				Directive::Include(_) => object.extend(other),
+				Directive::Base(_) => bases.push(other),
+			}
+		}
+
+		for base in bases {
+			merge_base(&mut object, base);
+		}
+
+		self.stack.pop();
+		Ok(object)
+	}
+}
+
+/// Merges `base` into `object` the way `#base` does: keys already in `object` win, blocks present in both are merged recursively,
+/// and keys only in `base` are appended.
+pub fn merge_base(object: &mut Object, base: Object) {
+	for base_kv in base {
+		let existing = object.iter_mut().find(|kv| kv.key.eq_ignore_ascii_case(&base_kv.key));
+		match (existing, base_kv.value) {
+			(None, value) => object.extend(Some(Kv { value, ..base_kv })),
+			(Some(Kv { value: Value::Obj(ours), .. }), Value::Obj(theirs)) => merge_base(ours, theirs),
+			(Some(_), _) => {}, //ours wins
+		}
+	}
+}
+
+/// Lexically cleans up `.` and `..` components, so the same file is always named the same way.
+fn normalize(path: &Path) -> PathBuf {
+	let mut out = PathBuf::new();
+	for component in path.components() {
+		match component {
+			Component::CurDir => {},
+			Component::ParentDir if out.file_name().is_some() => {
+				out.pop();
+			},
+			other => out.push(other),
+		}
+	}
+	out
+}
+
+#[cfg(test)]
+mod test {
+	use std::collections::HashMap;
+
+	use super::*;
+
+	fn loader(files: &[(&str, &str)]) -> impl FnMut(&Path) -> io::Result<String> {
+		let files: HashMap<PathBuf, String> = files.iter().map(|(k, v)| (PathBuf::from(k), v.to_string())).collect();
+		move |path| files.get(path).cloned().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such file"))
+	}
+
+	#[test]
+	fn base_and_include() {
+		let files = [
+			(
+				"resource/ui/hud.res",
+				r#"
+					#base "../base.res"
+					#include "extra.res"
+					"Hud"
+					{
+						"xpos" "10"
+						"Font" { "name" "Mine" }
+					}
+				"#,
+			),
+			(
+				"resource/base.res",
+				r#"
+					"hud"
+					{
+						"xpos" "0"
+						"ypos" "0"
+						"font" { "name" "Base" "tall" "12" }
+					}
+					"Other" { "a" "b" }
+				"#,
+			),
+			("resource/ui/extra.res", r#""Extra" { "c" "d" }"#),
+		];
+
+		let doc = resolve("resource/ui/./hud.res", loader(&files)).unwrap();
+		let expected: Object = r#"
+			"Hud"
+			{
+				"xpos" "10"
+				"Font" { "name" "Mine" "tall" "12" }
+				"ypos" "0"
+			}
+			"Extra" { "c" "d" }
+			"Other" { "a" "b" }
+		"#
+		.parse()
+		.unwrap();
+		assert_eq!(doc, expected);
+	}
+
+	#[test]
+	fn errors() {
+		let files = [("a.res", "#base \"b.res\" \"A\" \"1\""), ("b.res", "#include \"sub/../a.res\""), ("c.res", "#include \"missing.res\"")];
+
+		match resolve("a.res", loader(&files)) {
+			Err(VdfErr::IncludeCycle(cycle)) => assert_eq!(cycle, vec!["a.res", "b.res", "a.res"]),
+			other => panic!("expected a cycle, got {:?}", other),
+		}
+
+		assert!(matches!(resolve("c.res", loader(&files)), Err(VdfErr::Include { path, .. }) if path == "missing.res"));
+	}
+}
@@ -0,0 +1,528 @@
+//! Several values packed into one string, separated by spaces.
+//!
+//! Hammer and the Puzzle Maker store vectors, colors and angles like `"origin" "64 64 64"`. Tuples, fixed-size arrays and tuple
+//! structs are written this way, and so are sequences when using `SequenceFormat::Inline`.
+//!
+//! VMFs go one step further and nest them: plane points are `"(0 0 0) (1 0 0) (1 1 0)"`, and texture axes are
+//! `"[1 0 0 0] 0.25"`. Tuples inside an inline value get parentheses around them; wrap one in `Brackets` to get square
+//! brackets instead. Wrapping the outermost tuple in `Parens` or `Brackets` puts brackets around the whole value. When reading,
+//! either kind of bracket is accepted anywhere.
+//!
+//! ```
+//! use vdf::inline::Brackets;
+//!
+//! #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+//! struct Side {
+//!     plane: ((f32, f32, f32), (f32, f32, f32), (f32, f32, f32)),
+//!     uaxis: (Brackets<[f32; 4]>, f32),
+//! }
+//!
+//! let side = Side { plane: ((0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (1.0, 1.0, 0.0)), uaxis: (Brackets([1.0, 0.0, 0.0, 0.0]), 0.25) };
+//! let text = vdf::ser::to_string(&side).unwrap();
+//! assert!(text.contains("\"(0 0 0) (1 0 0) (1 1 0)\""));
+//! assert!(text.contains("\"[1 0 0 0] 0.25\""));
+//! assert_eq!(vdf::de::from_str::<Side>(&text).unwrap(), side);
+//! ```
+
+use std::borrow::Cow;
+use std::ops::Deref;
+use std::ops::DerefMut;
+
+use serde::de;
+use serde::de::IntoDeserializer;
+use serde::ser;
+use serde::ser::Impossible;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+use crate::de::ScalarDeserializer;
+use crate::error::VdfErr;
+use crate::ser::ScalarSerializer;
+
+/// The magic newtype struct names that `Parens` and `Brackets` serialize as.
+pub(crate) const PARENS: &str = "$vdf::parens";
+pub(crate) const BRACKETS: &str = "$vdf::brackets";
+
+/// Writes the tuple inside with parentheses around it, like `(1 2 3)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Parens<T>(pub T);
+
+/// Writes the tuple inside with square brackets around it, like `[1 2 3]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Brackets<T>(pub T);
+
+macro_rules! bracket_wrapper {
+	( $type:ident $name:ident ) => {
+		impl<T> Deref for $type<T> {
+			type Target = T;
+
+			fn deref(&self) -> &T {
+				&self.0
+			}
+		}
+
+		impl<T> DerefMut for $type<T> {
+			fn deref_mut(&mut self) -> &mut T {
+				&mut self.0
+			}
+		}
+
+		impl<T> Serialize for $type<T>
+		where
+			T: Serialize,
+		{
+			fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+			where
+				S: Serializer,
+			{
+				serializer.serialize_newtype_struct($name, &self.0)
+			}
+		}
+
+		impl<'de, T> Deserialize<'de> for $type<T>
+		where
+			T: Deserialize<'de>,
+		{
+			fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+			where
+				D: Deserializer<'de>,
+			{
+				struct Visitor<T>(std::marker::PhantomData<T>);
+
+				impl<'de, T> de::Visitor<'de> for Visitor<T>
+				where
+					T: Deserialize<'de>,
+				{
+					type Value = $type<T>;
+
+					fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+						formatter.write_str("a bracketed value")
+					}
+
+					fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+					where
+						D: Deserializer<'de>,
+					{
+						T::deserialize(deserializer).map($type)
+					}
+				}
+
+				deserializer.deserialize_newtype_struct($name, Visitor(std::marker::PhantomData))
+			}
+		}
+	};
+}
+
+bracket_wrapper!(Parens PARENS);
+bracket_wrapper!(Brackets BRACKETS);
+
+/// Returns the brackets that a magic newtype struct name asks for, if it's one of them.
+pub(crate) fn brackets_for(name: &str) -> Option<(char, char)> {
+	match name {
+		PARENS => Some(('(', ')')),
+		BRACKETS => Some(('[', ']')),
+		_ => None,
+	}
+}
+
+// Serialization
+
+/// Writes one element of an inline value into a string.
+///
+/// Scalars are written as-is. Tuples and sequences are written space-separated, with `brackets` around them if it's `Some`.
+pub(crate) struct InlineSerializer<'a> {
+	pub(crate) out: &'a mut String,
+	pub(crate) brackets: Option<(char, char)>,
+}
+
+impl<'a> InlineSerializer<'a> {
+	/// The serializer for an element inside a tuple. Nested tuples get parentheses unless they ask for something else.
+	pub(crate) fn element(out: &'a mut String) -> Self {
+		InlineSerializer { out, brackets: Some(('(', ')')) }
+	}
+
+	fn scalar(self, s: String) -> Result<(), VdfErr> {
+		if s.is_empty() || s.contains(|c: char| c.is_whitespace() || "()[]".contains(c)) {
+			//It wouldn't come back out as one element
+			return Err(VdfErr::Unrepresentable(s));
+		}
+		self.out.push_str(&s);
+		Ok(())
+	}
+
+	fn group(self) -> InlineGroupSerializer<'a> {
+		let close = self.brackets.map(|(open, close)| {
+			self.out.push(open);
+			close
+		});
+		InlineGroupSerializer { out: self.out, first: true, close }
+	}
+}
+
+macro_rules! inline_scalar {
+	( $func:ident | $type:ty ) => {
+		fn $func(self, v: $type) -> Result<Self::Ok, Self::Error> {
+			let s = ScalarSerializer.$func(v)?;
+			self.scalar(s)
+		}
+	};
+}
+
+impl<'a> ser::Serializer for InlineSerializer<'a> {
+	type Ok = ();
+	type Error = VdfErr;
+
+	type SerializeSeq = InlineGroupSerializer<'a>;
+	type SerializeTuple = InlineGroupSerializer<'a>;
+	type SerializeTupleStruct = InlineGroupSerializer<'a>;
+	type SerializeTupleVariant = Impossible<(), VdfErr>;
+	type SerializeMap = Impossible<(), VdfErr>;
+	type SerializeStruct = Impossible<(), VdfErr>;
+	type SerializeStructVariant = Impossible<(), VdfErr>;
+
+	inline_scalar!(serialize_bool | bool);
+	inline_scalar!(serialize_i8 | i8);
+	inline_scalar!(serialize_i16 | i16);
+	inline_scalar!(serialize_i32 | i32);
+	inline_scalar!(serialize_i64 | i64);
+	inline_scalar!(serialize_u8 | u8);
+	inline_scalar!(serialize_u16 | u16);
+	inline_scalar!(serialize_u32 | u32);
+	inline_scalar!(serialize_u64 | u64);
+	inline_scalar!(serialize_f32 | f32);
+	inline_scalar!(serialize_f64 | f64);
+	inline_scalar!(serialize_char | char);
+	inline_scalar!(serialize_str | &str);
+	inline_scalar!(serialize_bytes | &[u8]);
+
+	fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+		self.scalar(String::new())
+	}
+
+	fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+	where
+		T: ?Sized + Serialize,
+	{
+		value.serialize(self)
+	}
+
+	fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+		self.scalar(String::new())
+	}
+
+	fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+		self.scalar(String::new())
+	}
+
+	fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str) -> Result<Self::Ok, Self::Error> {
+		self.scalar(variant.to_string())
+	}
+
+	fn serialize_newtype_struct<T>(mut self, name: &'static str, value: &T) -> Result<Self::Ok, Self::Error>
+	where
+		T: ?Sized + Serialize,
+	{
+		if let Some(brackets) = brackets_for(name) {
+			self.brackets = Some(brackets);
+		}
+		value.serialize(self)
+	}
+
+	fn serialize_newtype_variant<T>(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _value: &T) -> Result<Self::Ok, Self::Error>
+	where
+		T: ?Sized + Serialize,
+	{
+		Err(VdfErr::ExpectedString)
+	}
+
+	fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+		Ok(self.group())
+	}
+
+	fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+		Ok(self.group())
+	}
+
+	fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, Self::Error> {
+		Ok(self.group())
+	}
+
+	fn serialize_tuple_variant(
+		self,
+		_name: &'static str,
+		_variant_index: u32,
+		_variant: &'static str,
+		_len: usize,
+	) -> Result<Self::SerializeTupleVariant, Self::Error> {
+		Err(VdfErr::ExpectedString)
+	}
+
+	fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+		Err(VdfErr::ExpectedString)
+	}
+
+	fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, Self::Error> {
+		Err(VdfErr::ExpectedString)
+	}
+
+	fn serialize_struct_variant(
+		self,
+		_name: &'static str,
+		_variant_index: u32,
+		_variant: &'static str,
+		_len: usize,
+	) -> Result<Self::SerializeStructVariant, Self::Error> {
+		Err(VdfErr::ExpectedString)
+	}
+}
+
+/// Writes the elements of a tuple or sequence, separated by spaces.
+pub(crate) struct InlineGroupSerializer<'a> {
+	out: &'a mut String,
+	first: bool,
+	close: Option<char>,
+}
+
+impl<'a> InlineGroupSerializer<'a> {
+	fn element<T>(&mut self, value: &T) -> Result<(), VdfErr>
+	where
+		T: ?Sized + Serialize,
+	{
+		if !std::mem::take(&mut self.first) {
+			self.out.push(' ');
+		}
+		value.serialize(InlineSerializer::element(self.out))
+	}
+
+	fn finish(self) -> Result<(), VdfErr> {
+		if let Some(close) = self.close {
+			self.out.push(close);
+		}
+		Ok(())
+	}
+}
+
+impl<'a> ser::SerializeSeq for InlineGroupSerializer<'a> {
+	type Ok = ();
+	type Error = VdfErr;
+
+	fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+	where
+		T: ?Sized + Serialize,
+	{
+		self.element(value)
+	}
+
+	fn end(self) -> Result<Self::Ok, Self::Error> {
+		self.finish()
+	}
+}
+
+impl<'a> ser::SerializeTuple for InlineGroupSerializer<'a> {
+	type Ok = ();
+	type Error = VdfErr;
+
+	fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+	where
+		T: ?Sized + Serialize,
+	{
+		self.element(value)
+	}
+
+	fn end(self) -> Result<Self::Ok, Self::Error> {
+		self.finish()
+	}
+}
+
+impl<'a> ser::SerializeTupleStruct for InlineGroupSerializer<'a> {
+	type Ok = ();
+	type Error = VdfErr;
+
+	fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+	where
+		T: ?Sized + Serialize,
+	{
+		self.element(value)
+	}
+
+	fn end(self) -> Result<Self::Ok, Self::Error> {
+		self.finish()
+	}
+}
+
+// Deserialization
+
+/// One element of an inline value: a word, or a bracketed group of elements.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Inline<'de> {
+	Word(Cow<'de, str>),
+	Group(Vec<Inline<'de>>),
+}
+
+/// Splits an inline value into its elements.
+pub(crate) fn parse(s: Cow<'_, str>) -> Result<Vec<Inline<'_>>, VdfErr> {
+	match s {
+		Cow::Borrowed(s) => parse_str(s),
+		Cow::Owned(s) => parse_str(&s).map(|parts| parts.into_iter().map(Inline::into_owned).collect()),
+	}
+}
+
+fn parse_str(s: &str) -> Result<Vec<Inline<'_>>, VdfErr> {
+	let unbalanced = || VdfErr::UnbalancedBrackets(s.to_string());
+
+	//The groups that are currently open, and the character that closes each one. The bottom one is the whole value.
+	let mut stack: Vec<(Vec<Inline>, char)> = vec![(Vec::new(), '\0')];
+	let mut word_start = None;
+
+	for (i, c) in s.char_indices().chain(Some((s.len(), ' '))) {
+		let is_word = !(c.is_whitespace() || "()[]".contains(c));
+		match (word_start, is_word) {
+			(None, true) => word_start = Some(i),
+			(Some(start), false) => {
+				//Unwrap safety: the stack is never empty, the bottom group can't be closed
+				stack.last_mut().unwrap().0.push(Inline::Word(Cow::Borrowed(&s[start..i])));
+				word_start = None;
+			},
+			_ => {},
+		}
+
+		match c {
+			'(' => stack.push((Vec::new(), ')')),
+			'[' => stack.push((Vec::new(), ']')),
+			')' | ']' => match stack.pop() {
+				Some((group, close)) if close == c && !stack.is_empty() => stack.last_mut().unwrap().0.push(Inline::Group(group)),
+				_ => return Err(unbalanced()),
+			},
+			_ => {},
+		}
+	}
+
+	match (stack.pop(), stack.is_empty()) {
+		(Some((parts, _)), true) => Ok(parts),
+		_ => Err(unbalanced()),
+	}
+}
+
+impl<'de> Inline<'de> {
+	fn into_owned(self) -> Inline<'static> {
+		match self {
+			Inline::Word(w) => Inline::Word(Cow::Owned(w.into_owned())),
+			Inline::Group(g) => Inline::Group(g.into_iter().map(Inline::into_owned).collect()),
+		}
+	}
+}
+
+impl<'de> IntoDeserializer<'de, VdfErr> for Inline<'de> {
+	type Deserializer = InlineDeserializer<'de>;
+
+	fn into_deserializer(self) -> Self::Deserializer {
+		InlineDeserializer(self)
+	}
+}
+
+/// Deserializes one element of an inline value. Words are parsed like any other string, groups are sequences.
+pub(crate) struct InlineDeserializer<'de>(pub(crate) Inline<'de>);
+
+macro_rules! forward_inline {
+	( $( $func:ident )* ) => {
+		$(
+			fn $func<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+			where
+				V: de::Visitor<'de>,
+			{
+				match self.0 {
+					Inline::Word(w) => ScalarDeserializer(w).$func(visitor),
+					group => group_deserializer(group).deserialize_any(visitor),
+				}
+			}
+		)*
+	};
+}
+
+impl<'de> de::Deserializer<'de> for InlineDeserializer<'de> {
+	type Error = VdfErr;
+
+	forward_inline! {
+		deserialize_any deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+		deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128 deserialize_f32 deserialize_f64
+		deserialize_char deserialize_str deserialize_string deserialize_bytes deserialize_byte_buf deserialize_option
+		deserialize_unit deserialize_seq deserialize_map deserialize_identifier deserialize_ignored_any
+	}
+
+	fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.deserialize_unit(visitor)
+	}
+
+	fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		visitor.visit_newtype_struct(self)
+	}
+
+	fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.deserialize_seq(visitor)
+	}
+
+	fn deserialize_tuple_struct<V>(self, _name: &'static str, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.deserialize_seq(visitor)
+	}
+
+	fn deserialize_struct<V>(self, _name: &'static str, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.deserialize_map(visitor)
+	}
+
+	fn deserialize_enum<V>(self, name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		match self.0 {
+			Inline::Word(w) => ScalarDeserializer(w).deserialize_enum(name, variants, visitor),
+			group => group_deserializer(group).deserialize_any(visitor),
+		}
+	}
+}
+
+/// A group, as a sequence. Complains if the visitor doesn't read every element.
+fn group_deserializer(group: Inline<'_>) -> de::value::SeqDeserializer<std::vec::IntoIter<Inline<'_>>, VdfErr> {
+	match group {
+		Inline::Group(g) => de::value::SeqDeserializer::new(g.into_iter()),
+		Inline::Word(w) => de::value::SeqDeserializer::new(vec![Inline::Word(w)].into_iter()),
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn word(s: &str) -> Inline<'_> {
+		Inline::Word(Cow::Borrowed(s))
+	}
+
+	#[test]
+	fn parse_groups() {
+		assert_eq!(parse_str("  1 2\t3 ").unwrap(), vec![word("1"), word("2"), word("3")]);
+		assert_eq!(parse_str("[1 0 0 0] 0.25").unwrap(), vec![Inline::Group(vec![word("1"), word("0"), word("0"), word("0")]), word("0.25")]);
+		assert_eq!(parse_str("(0 0 0)(1 0 0)").unwrap(), vec![Inline::Group(vec![word("0"); 3]), Inline::Group(vec![word("1"), word("0"), word("0")])]);
+		assert_eq!(parse_str("((a))").unwrap(), vec![Inline::Group(vec![Inline::Group(vec![word("a")])])]);
+		assert_eq!(parse_str("").unwrap(), vec![]);
+
+		for bad in &["(1 2", "1 2)", "(1 2]", "]", "[(1])"] {
+			assert!(matches!(parse_str(bad), Err(VdfErr::UnbalancedBrackets(_))), "{:?} should not parse", bad);
+		}
+	}
+}
@@ -0,0 +1,306 @@
+//! Converting VDF documents to and from JSON, so they can be diffed, grepped and `jq`'d.
+//!
+//! JSON doesn't have repeated keys or conditionals, so they're encoded like this:
+//!
+//! * A block is a JSON object, with the keys in the same order as the file.
+//! * A key that appears more than once in a block becomes one JSON key, holding an array of every value in order. The
+//!   array goes where the first one was. Arrays can't mean anything else, since VDF doesn't have them.
+//! * A pair with a conditional has it tacked onto the end of its key after a space, like `"Skin [$X360]"`. Pairs with
+//!   different conditionals count as different keys.
+//!
+//! ```text
+//! "Item" { "Type" "A" }            {
+//! "Item" { "Type" "B" }              "Item": [ { "Type": "A" }, { "Type": "B" } ],
+//! "Skin" "2" [$X360]                 "Skin [$X360]": "2"
+//!                                  }
+//! ```
+//!
+//! The only thing that doesn't survive the trip is the order of repeated keys relative to other keys: `a b a` comes back as
+//! `a a b`. Going the other way, JSON numbers and bools become strings the way the VDF serializer writes them (`true` is `"1"`),
+//! and `null` is the empty string.
+
+use std::fmt;
+use std::io;
+
+use serde::de;
+use serde::de::DeserializeSeed;
+use serde::de::Visitor;
+use serde::ser::SerializeMap;
+use serde::ser::SerializeSeq;
+use serde::Serialize;
+use serde::Serializer;
+
+use crate::conditional::Conditional;
+use crate::error::VdfErr;
+use crate::value::Kv;
+use crate::value::Object;
+use crate::value::Value;
+use crate::value::ValueVisitor;
+
+/// Convert a document to compact JSON.
+pub fn to_string(object: &Object) -> Result<String, VdfErr> {
+	Ok(serde_json::to_string(&Json(object))?)
+}
+
+/// Convert a document to indented JSON.
+pub fn to_string_pretty(object: &Object) -> Result<String, VdfErr> {
+	Ok(serde_json::to_string_pretty(&Json(object))?)
+}
+
+/// Convert a document to compact JSON, written into the IO stream.
+pub fn to_writer<W>(writer: W, object: &Object) -> Result<(), VdfErr>
+where
+	W: io::Write,
+{
+	Ok(serde_json::to_writer(writer, &Json(object))?)
+}
+
+/// Convert a document to indented JSON, written into the IO stream.
+pub fn to_writer_pretty<W>(writer: W, object: &Object) -> Result<(), VdfErr>
+where
+	W: io::Write,
+{
+	Ok(serde_json::to_writer_pretty(writer, &Json(object))?)
+}
+
+/// Read a document back out of JSON. The JSON has to be an object.
+pub fn from_str(json: &str) -> Result<Object, VdfErr> {
+	let mut de = serde_json::Deserializer::from_str(json);
+	let object = JsonObjectVisitor.deserialize(&mut de)?;
+	de.end()?;
+	Ok(object)
+}
+
+/// Read a document back out of JSON from the IO stream. The JSON has to be an object.
+pub fn from_reader<R>(reader: R) -> Result<Object, VdfErr>
+where
+	R: io::Read,
+{
+	let mut de = serde_json::Deserializer::from_reader(reader);
+	let object = JsonObjectVisitor.deserialize(&mut de)?;
+	de.end()?;
+	Ok(object)
+}
+
+/// Serializes an `Object` in the JSON-friendly shape described in the module docs, with any serde format.
+///
+/// (`Object`'s own `Serialize` impl writes repeated keys as repeated map entries, which is right for VDF but not much else.)
+pub struct Json<'a>(pub &'a Object);
+
+impl<'a> Serialize for Json<'a> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		//Group the values by key (and conditional), in order of first appearance
+		let mut groups: Vec<(String, Vec<&Value>)> = Vec::new();
+		for kv in self.0 {
+			let key = match &kv.condition {
+				Some(condition) => format!("{} {}", kv.key, condition),
+				None => kv.key.clone(),
+			};
+			match groups.iter_mut().find(|(k, _)| *k == key) {
+				Some((_, values)) => values.push(&kv.value),
+				None => groups.push((key, vec![&kv.value])),
+			}
+		}
+
+		let mut map = serializer.serialize_map(Some(groups.len()))?;
+		for (key, values) in groups {
+			match values.as_slice() {
+				[value] => map.serialize_entry(&key, &JsonValue(value))?,
+				_ => map.serialize_entry(&key, &JsonValues(&values))?,
+			}
+		}
+		map.end()
+	}
+}
+
+struct JsonValue<'a>(&'a Value);
+
+impl<'a> Serialize for JsonValue<'a> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		match self.0 {
+			Value::Str(s) => serializer.serialize_str(s),
+			Value::Obj(o) => Json(o).serialize(serializer),
+		}
+	}
+}
+
+struct JsonValues<'a>(&'a [&'a Value]);
+
+impl<'a> Serialize for JsonValues<'a> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+		for value in self.0 {
+			seq.serialize_element(&JsonValue(value))?;
+		}
+		seq.end()
+	}
+}
+
+/// Splits the conditional off the end of a JSON key, if there's a well-formed one there.
+fn split_key(key: String) -> (String, Option<Conditional>) {
+	let split = key.strip_suffix(']').and_then(|rest| rest.rfind(" [").map(|i| (i, &rest[i + 2..])));
+	match split.map(|(i, text)| (i, Conditional::new(text))) {
+		Some((i, Ok(condition))) => (key[..i].to_string(), Some(condition)),
+		_ => (key, None),
+	}
+}
+
+struct JsonObjectVisitor;
+
+impl<'de> DeserializeSeed<'de> for JsonObjectVisitor {
+	type Value = Object;
+
+	fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+	where
+		D: de::Deserializer<'de>,
+	{
+		deserializer.deserialize_map(self)
+	}
+}
+
+impl<'de> Visitor<'de> for JsonObjectVisitor {
+	type Value = Object;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("a JSON object")
+	}
+
+	fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+	where
+		A: de::MapAccess<'de>,
+	{
+		let mut object = Object::new();
+		while let Some(key) = map.next_key::<String>()? {
+			let (key, condition) = split_key(key);
+			let values = map.next_value_seed(JsonEntryVisitor { in_array: false })?;
+			object.extend(values.into_iter().map(|value| Kv { key: key.clone(), value, condition: condition.clone() }));
+		}
+		Ok(object)
+	}
+}
+
+/// Reads the value of a JSON key, which is one value, or an array of them for a repeated key.
+struct JsonEntryVisitor {
+	/// Arrays inside arrays don't mean anything.
+	in_array: bool,
+}
+
+impl<'de> DeserializeSeed<'de> for JsonEntryVisitor {
+	type Value = Vec<Value>;
+
+	fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+	where
+		D: de::Deserializer<'de>,
+	{
+		deserializer.deserialize_any(self)
+	}
+}
+
+macro_rules! forward_to_value_visitor {
+	( $( $func:ident | $type:ty ),* ) => {
+		$(
+			fn $func<E>(self, v: $type) -> Result<Self::Value, E>
+			where
+				E: de::Error,
+			{
+				ValueVisitor.$func(v).map(|value| vec![value])
+			}
+		)*
+	};
+}
+
+impl<'de> Visitor<'de> for JsonEntryVisitor {
+	type Value = Vec<Value>;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		if self.in_array {
+			formatter.write_str("a string, number, bool, null or object")
+		} else {
+			formatter.write_str("a string, number, bool, null, object or array")
+		}
+	}
+
+	forward_to_value_visitor!(visit_bool | bool, visit_i64 | i64, visit_u64 | u64, visit_f64 | f64, visit_str | &str, visit_string | String);
+
+	fn visit_unit<E>(self) -> Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		ValueVisitor.visit_unit().map(|value| vec![value])
+	}
+
+	fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+	where
+		A: de::MapAccess<'de>,
+	{
+		JsonObjectVisitor.visit_map(map).map(|object| vec![Value::Obj(object)])
+	}
+
+	fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+	where
+		A: de::SeqAccess<'de>,
+	{
+		if self.in_array {
+			return Err(de::Error::invalid_type(de::Unexpected::Seq, &self));
+		}
+
+		let mut values = Vec::new();
+		while let Some(mut value) = seq.next_element_seed(JsonEntryVisitor { in_array: true })? {
+			values.append(&mut value);
+		}
+		Ok(values)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn round_trip() {
+		let vdf = r#"
+			"Item" { "Type" "ITEM_BUTTON" "Offset" "0 0 64" }
+			"Skin" "2" [$X360]
+			"Skin" "3" [!$X360]
+			"Item" { "Type" "ITEM_CUBE" "Note" "with \"quotes\"" }
+			"Empty" { }
+		"#;
+		let object: Object = vdf.parse().unwrap();
+
+		let json = to_string(&object).unwrap();
+		assert_eq!(
+			json,
+			r#"{"Item":[{"Type":"ITEM_BUTTON","Offset":"0 0 64"},{"Type":"ITEM_CUBE","Note":"with \"quotes\""}],"Skin [$X360]":"2","Skin [!$X360]":"3","Empty":{}}"#
+		);
+
+		//Everything comes back except that the second Item moved up next to the first
+		let back = from_str(&to_string_pretty(&object).unwrap()).unwrap();
+		assert_eq!(back.get_all("Item").collect::<Vec<_>>(), object.get_all("Item").collect::<Vec<_>>());
+		assert_eq!(back.iter().map(|kv| kv.key.as_str()).collect::<Vec<_>>(), vec!["Item", "Item", "Skin", "Skin", "Empty"]);
+		assert_eq!(back.iter().find(|kv| kv.key == "Skin").unwrap().condition.as_ref().unwrap().as_str(), "$X360");
+	}
+
+	#[test]
+	fn from_json() {
+		let object = from_str(r#"{"a": 1, "b": true, "c": null, "d": 1.5, "e": ["x", {"f": "y"}], "g [bad conditional]": "z"}"#).unwrap();
+		let expected: Object = r#"a 1 b 1 c "" d 1.5 e x e { f y } "g [bad conditional]" z"#.parse().unwrap();
+		assert_eq!(object, expected);
+
+		let mut bytes = Vec::new();
+		to_writer(&mut bytes, &object).unwrap();
+		assert_eq!(from_reader(&bytes[..]).unwrap(), object);
+
+		assert!(matches!(from_str(r#"{"a": [["nested"]]}"#), Err(VdfErr::Json(_))));
+		assert!(matches!(from_str(r#"["not an object"]"#), Err(VdfErr::Json(_))));
+		assert!(matches!(from_str(r#"{} {}"#), Err(VdfErr::Json(_))));
+	}
+}
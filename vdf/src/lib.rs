@@ -4,9 +4,24 @@
 
 #![allow(dead_code)]
 
-mod ast; //idk what to do with this
-
+pub mod binary;
+pub mod conditional;
+pub mod cst;
 pub mod de;
+pub mod diff;
 pub mod error;
+pub mod include;
+pub mod inline;
+#[cfg(feature = "json")]
+pub mod json;
 pub mod macros;
+pub mod p2c;
+pub mod query;
+pub mod repeated;
 pub mod ser;
+mod util;
+pub mod value;
+pub mod vmf;
+
+pub use value::Object;
+pub use value::Value;
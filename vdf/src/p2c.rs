@@ -0,0 +1,581 @@
+//! A typed model of Portal 2 Puzzle Maker `.p2c` puzzles.
+//!
+//! Like `vdf::vmf`, this goes through `vdf::value::Object`: the puzzle's voxels, items and connections are parsed into structs,
+//! and everything else is kept as an `Object` so it comes back out. Loading and saving a puzzle gives the same text back, keys in
+//! the same order, as long as the numbers were written the way Rust writes them (`0.50` comes back as `0.5`). Blocks and keys that
+//! weren't there stay missing, unless they're given something to hold.
+//!
+//! The voxels are stored as bit fields packed into strings. Each `Voxel` string in the `Voxels` block is one layer of the chamber
+//! along Z, with rows along Y separated by spaces and one hex digit per voxel along X. See `Voxel` for what the bits are.
+//!
+//! ```
+//! # use vdf::p2c::*;
+//! let mut puzzle = Puzzle::default();
+//! puzzle.set_title("Buttons");
+//! puzzle.voxels = VoxelGrid::new([4, 4, 4]);
+//! puzzle.voxels.set([1, 1, 0], Voxel { solid: true, portalable: [false, false, true] });
+//! puzzle.items.push(Item::new(0, "ITEM_BUTTON_FLOOR", [1, 1, 1]));
+//! puzzle.items.push(Item::new(1, "ITEM_ENTRY_DOOR", [0, 2, 1]));
+//! puzzle.connections.push(Connection::new(0, 1));
+//!
+//! let puzzle: Puzzle = puzzle.to_string().unwrap().parse().unwrap();
+//! assert!(puzzle.voxels.get([1, 1, 0]).unwrap().solid);
+//! assert_eq!(puzzle.connections_from(0).next().unwrap().receiver, 1);
+//! ```
+
+use std::str::FromStr;
+
+use crate::de::VdfDeserializer;
+use crate::error::VdfErr;
+use crate::ser::FormatSettings;
+use crate::util::join;
+use crate::util::parse_array;
+use crate::value::Kv;
+use crate::value::Object;
+use crate::value::Value;
+
+/// The `portal2_puzzle` block.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Puzzle {
+	pub voxels: VoxelGrid,
+	pub items: Vec<Item>,
+	pub connections: Vec<Connection>,
+	/// Everything besides the voxels, items and connections, like `AppID`, `Title` and `Coop`, in order.
+	pub rest: Object,
+	/// Where the `Voxels`, `Items` and `Connections` blocks were among `rest`, and whether they were there at all.
+	layout: Layout,
+	/// Anything in the `Items` block that isn't an `Item`, with how many items came before it.
+	items_rest: Leftovers,
+	/// The same for the `Connections` block.
+	connections_rest: Leftovers,
+}
+
+const BLOCKS: &[&str] = &["Voxels", "Items", "Connections"];
+
+/// The chamber, one voxel per 128 Hammer units.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VoxelGrid {
+	size: [usize; 3],
+	voxels: Vec<Voxel>,
+}
+
+/// One voxel, written as a hex digit: bit 0 is `solid`, and bits 1 to 3 are `portalable` along X, Y and Z.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Voxel {
+	/// Filled in, rather than open space in the chamber.
+	pub solid: bool,
+	/// Whether the faces of the voxel that point along X, Y and Z are portalable.
+	pub portalable: [bool; 3],
+}
+
+/// Something placed in the chamber, from the `Items` block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+	/// What connections refer to the item by.
+	pub index: u32,
+	/// Which item from `editoritems.txt` it is, like `ITEM_BUTTON_FLOOR`.
+	pub item_type: String,
+	pub deletable: bool,
+	pub voxel_pos: [usize; 3],
+	pub local_pos: [f64; 3],
+	pub angles: [f64; 3],
+	pub facing: [f64; 3],
+	/// The item's settings, like `ButtonType` or `StartEnabled`.
+	pub properties: Object,
+	/// Anything else, in order.
+	pub extra: Object,
+	layout: Layout,
+}
+
+/// An antline (or a tractor beam polarity link, or...) from one item to another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Connection {
+	/// The `index` of the item sending the signal.
+	pub sender: u32,
+	/// The `index` of the item receiving it.
+	pub receiver: u32,
+	/// Like `CONNECTION_STANDARD`.
+	pub connection_type: String,
+	/// Anything else, in order.
+	pub extra: Object,
+	layout: Layout,
+}
+
+impl Puzzle {
+	/// Reads the contents of the `portal2_puzzle` block.
+	pub fn from_object(object: &Object) -> Result<Self, VdfErr> {
+		let (blocks, rest, layout) = Layout::split(object, BLOCKS, true);
+		let voxels = match blocks.get("Voxels") {
+			Some(_) => VoxelGrid::from_object(blocks.require_obj("Voxels")?)?,
+			None => VoxelGrid::default(),
+		};
+		let (items, items_rest) = read_list(&blocks, "Items", "Item", Item::from_object)?;
+		let (connections, connections_rest) = read_list(&blocks, "Connections", "Connection", Connection::from_object)?;
+		Ok(Puzzle { voxels, items, connections, rest, layout, items_rest, connections_rest })
+	}
+
+	/// Writes the contents of the `portal2_puzzle` block. Blocks that weren't there when the puzzle was read are only written if
+	/// there's something in them now.
+	pub fn to_object(&self) -> Object {
+		let mut blocks = Object::new();
+		if self.layout.has("Voxels") || self.voxels != VoxelGrid::default() {
+			blocks.push("Voxels", self.voxels.to_object());
+		}
+		if self.layout.has("Items") || !self.items.is_empty() || !self.items_rest.is_empty() {
+			blocks.push("Items", write_list("Item", self.items.iter().map(Item::to_object), &self.items_rest));
+		}
+		if self.layout.has("Connections") || !self.connections.is_empty() || !self.connections_rest.is_empty() {
+			blocks.push("Connections", write_list("Connection", self.connections.iter().map(Connection::to_object), &self.connections_rest));
+		}
+		self.layout.arrange(blocks, &self.rest, true)
+	}
+
+	/// Writes the puzzle the way the Puzzle Maker does.
+	pub fn to_string(&self) -> Result<String, VdfErr> {
+		let mut out = Object::new();
+		out.push("portal2_puzzle", self.to_object());
+		out.to_string(&settings())
+	}
+
+	pub fn title(&self) -> Option<&str> {
+		self.rest.get("Title").and_then(Value::as_str)
+	}
+
+	pub fn set_title(&mut self, title: impl Into<String>) {
+		self.rest.insert("Title", title.into());
+	}
+
+	pub fn description(&self) -> Option<&str> {
+		self.rest.get("Description").and_then(Value::as_str)
+	}
+
+	pub fn set_description(&mut self, description: impl Into<String>) {
+		self.rest.insert("Description", description.into());
+	}
+
+	/// Whether it's a co-op puzzle.
+	pub fn coop(&self) -> bool {
+		self.rest.get("Coop").and_then(Value::as_str).is_some_and(|coop| coop != "0")
+	}
+
+	pub fn set_coop(&mut self, coop: bool) {
+		self.rest.insert("Coop", if coop { "1" } else { "0" });
+	}
+
+	/// The item with the given index.
+	pub fn item(&self, index: u32) -> Option<&Item> {
+		self.items.iter().find(|item| item.index == index)
+	}
+
+	pub fn item_mut(&mut self, index: u32) -> Option<&mut Item> {
+		self.items.iter_mut().find(|item| item.index == index)
+	}
+
+	/// Every item of the given type.
+	pub fn items_of_type<'a>(&'a self, item_type: &'a str) -> impl Iterator<Item = &'a Item> + 'a {
+		self.items.iter().filter(move |item| item.item_type == item_type)
+	}
+
+	/// Every connection the item with the given index sends.
+	pub fn connections_from(&self, index: u32) -> impl Iterator<Item = &Connection> {
+		self.connections.iter().filter(move |connection| connection.sender == index)
+	}
+
+	/// Every connection the item with the given index receives.
+	pub fn connections_to(&self, index: u32) -> impl Iterator<Item = &Connection> {
+		self.connections.iter().filter(move |connection| connection.receiver == index)
+	}
+
+	/// The index after the highest one in use, for adding a new item.
+	pub fn next_index(&self) -> u32 {
+		self.items.iter().map(|item| item.index + 1).max().unwrap_or(0)
+	}
+
+	/// Removes the item with the given index along with its connections, and returns it.
+	pub fn remove_item(&mut self, index: u32) -> Option<Item> {
+		let position = self.items.iter().position(|item| item.index == index)?;
+		self.connections.retain(|connection| connection.sender != index && connection.receiver != index);
+		Some(self.items.remove(position))
+	}
+
+	/// Removes connections to or from items that don't exist, and returns how many there were.
+	pub fn remove_dangling_connections(&mut self) -> usize {
+		let before = self.connections.len();
+		let items = &self.items;
+		let exists = |index: u32| items.iter().any(|item| item.index == index);
+		self.connections.retain(|connection| exists(connection.sender) && exists(connection.receiver));
+		before - self.connections.len()
+	}
+}
+
+impl FromStr for Puzzle {
+	type Err = VdfErr;
+
+	/// Parses a whole `.p2c` file.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let object = VdfDeserializer::with_settings(s, settings()).parse_object()?;
+		Puzzle::from_object(object.require_obj("portal2_puzzle")?)
+	}
+}
+
+/// The Puzzle Maker writes newlines in the description as-is rather than escaping them, and doesn't escape anything else either.
+fn settings() -> FormatSettings {
+	FormatSettings::p2c_like().with_escapes(false)
+}
+
+impl VoxelGrid {
+	/// A grid of the given size along X, Y and Z, where every voxel is open space.
+	pub fn new(size: [usize; 3]) -> Self {
+		VoxelGrid { size, voxels: vec![Voxel::default(); size[0] * size[1] * size[2]] }
+	}
+
+	pub fn size(&self) -> [usize; 3] {
+		self.size
+	}
+
+	fn offset(&self, [x, y, z]: [usize; 3]) -> Option<usize> {
+		let [width, depth, height] = self.size;
+		(x < width && y < depth && z < height).then(|| (z * depth + y) * width + x)
+	}
+
+	/// The voxel at a position, or `None` if it's outside the grid.
+	pub fn get(&self, position: [usize; 3]) -> Option<Voxel> {
+		self.offset(position).map(|offset| self.voxels[offset])
+	}
+
+	pub fn get_mut(&mut self, position: [usize; 3]) -> Option<&mut Voxel> {
+		self.offset(position).map(move |offset| &mut self.voxels[offset])
+	}
+
+	/// Changes the voxel at a position.
+	///
+	/// # Panics
+	///
+	/// If the position is outside the grid.
+	pub fn set(&mut self, position: [usize; 3], voxel: Voxel) {
+		match self.get_mut(position) {
+			Some(v) => *v = voxel,
+			None => panic!("{:?} is outside of a voxel grid of size {:?}", position, self.size),
+		}
+	}
+
+	/// Whether the voxel at a position is filled in. Everything outside the grid is.
+	pub fn is_solid(&self, position: [usize; 3]) -> bool {
+		self.get(position).map_or(true, |voxel| voxel.solid)
+	}
+
+	/// Every position in the grid along with its voxel, X first, then Y, then Z.
+	pub fn iter(&self) -> impl Iterator<Item = ([usize; 3], Voxel)> + '_ {
+		let [width, depth, _] = self.size;
+		self.voxels.iter().enumerate().map(move |(i, voxel)| ([i % width, i / width % depth, i / (width * depth)], *voxel))
+	}
+
+	/// Reads the `Voxels` block. Every layer has to be the same size, and not empty.
+	pub fn from_object(object: &Object) -> Result<Self, VdfErr> {
+		let mut grid = VoxelGrid::default();
+		for kv in object {
+			let invalid = |value: &str| VdfErr::InvalidValue { key: kv.key.clone(), value: value.to_string(), expected: "a layer of voxels" };
+			let layer = match (kv.key.as_str(), &kv.value) {
+				("Voxel", Value::Str(layer)) if kv.condition.is_none() && !layer.is_empty() => layer,
+				(_, Value::Str(value)) => return Err(invalid(value)),
+				(_, Value::Obj(_)) => return Err(invalid("a block")),
+			};
+			let rows = layer.split(' ').collect::<Vec<_>>();
+			if grid.size[2] == 0 {
+				grid.size = [rows[0].len(), rows.len(), 0];
+			}
+			if rows.len() != grid.size[1] || rows.iter().any(|row| row.len() != grid.size[0]) {
+				return Err(invalid(layer));
+			}
+			for c in rows.concat().chars() {
+				grid.voxels.push(Voxel::from_digit(c).ok_or_else(|| invalid(layer))?);
+			}
+			grid.size[2] += 1;
+		}
+		Ok(grid)
+	}
+
+	pub fn to_object(&self) -> Object {
+		let [width, depth, _] = self.size;
+		let mut out = Object::new();
+		if width * depth == 0 {
+			return out;
+		}
+		for layer in self.voxels.chunks(width * depth) {
+			let rows = layer.chunks(width).map(|row| row.iter().map(|voxel| voxel.to_digit()).collect::<String>());
+			out.push("Voxel", rows.collect::<Vec<_>>().join(" "));
+		}
+		out
+	}
+}
+
+impl Voxel {
+	/// Only lowercase hex digits, since that's what gets written.
+	fn from_digit(c: char) -> Option<Self> {
+		let bits = match c {
+			'0'..='9' | 'a'..='f' => c.to_digit(16)?,
+			_ => return None,
+		};
+		Some(Voxel { solid: bits & 1 != 0, portalable: [bits & 2 != 0, bits & 4 != 0, bits & 8 != 0] })
+	}
+
+	fn to_digit(self) -> char {
+		let bits = self.solid as u32 | (self.portalable[0] as u32) << 1 | (self.portalable[1] as u32) << 2 | (self.portalable[2] as u32) << 3;
+		std::char::from_digit(bits, 16).unwrap()
+	}
+}
+
+const ITEM_KEYS: &[&str] = &["Index", "Type", "Deletable", "VoxelPos", "LocalPos", "Angles", "Facing", "Properties"];
+const CONNECTION_KEYS: &[&str] = &["Sender", "Receiver", "Type"];
+
+impl Item {
+	/// A deletable item sitting in the middle of a voxel, facing up, with no properties.
+	pub fn new(index: u32, item_type: &str, voxel_pos: [usize; 3]) -> Self {
+		Item {
+			index,
+			item_type: item_type.to_string(),
+			deletable: true,
+			voxel_pos,
+			local_pos: [0.0; 3],
+			angles: [0.0; 3],
+			facing: [0.0, 0.0, 1.0],
+			properties: Object::new(),
+			extra: Object::new(),
+			layout: Layout::default(),
+		}
+	}
+
+	/// Keys with a conditional, and repeats of the keys it knows about, end up in `extra`.
+	pub fn from_object(object: &Object) -> Result<Self, VdfErr> {
+		let (known, extra, layout) = Layout::split(object, ITEM_KEYS, false);
+		Ok(Item {
+			index: known.parse_str("Index", "an index")?,
+			item_type: known.require_str("Type")?.to_string(),
+			deletable: known.parse_str::<u8>("Deletable", "0 or 1")? != 0,
+			voxel_pos: parse_array(&known, "VoxelPos", "three numbers")?,
+			local_pos: parse_array(&known, "LocalPos", "three numbers")?,
+			angles: parse_array(&known, "Angles", "three numbers")?,
+			facing: parse_array(&known, "Facing", "three numbers")?,
+			properties: match known.get("Properties") {
+				Some(_) => known.require_obj("Properties")?.clone(),
+				None => Object::new(),
+			},
+			extra,
+			layout,
+		})
+	}
+
+	/// New items always get a `Properties` block, even an empty one, like the Puzzle Maker does. Items that were read without
+	/// one only get one if they have properties now.
+	pub fn to_object(&self) -> Object {
+		let mut known = Object::new();
+		known.push("Index", self.index.to_string());
+		known.push("Type", self.item_type.as_str());
+		known.push("Deletable", if self.deletable { "1" } else { "0" });
+		known.push("VoxelPos", join(&self.voxel_pos));
+		known.push("LocalPos", join(&self.local_pos));
+		known.push("Angles", join(&self.angles));
+		known.push("Facing", join(&self.facing));
+		if self.layout.has("Properties") || !self.properties.is_empty() {
+			known.push("Properties", self.properties.clone());
+		}
+		self.layout.arrange(known, &self.extra, false)
+	}
+
+	/// Looks up one of the item's properties.
+	pub fn get(&self, key: &str) -> Option<&str> {
+		self.properties.get(key).and_then(Value::as_str)
+	}
+
+	/// Changes one of the item's properties, or adds it to the end.
+	pub fn set(&mut self, key: &str, value: impl Into<String>) {
+		self.properties.insert(key, value.into());
+	}
+}
+
+impl Connection {
+	/// A `CONNECTION_STANDARD` connection.
+	pub fn new(sender: u32, receiver: u32) -> Self {
+		Connection { sender, receiver, connection_type: "CONNECTION_STANDARD".to_string(), extra: Object::new(), layout: Layout::default() }
+	}
+
+	pub fn from_object(object: &Object) -> Result<Self, VdfErr> {
+		let (known, extra, layout) = Layout::split(object, CONNECTION_KEYS, false);
+		Ok(Connection {
+			sender: known.parse_str("Sender", "an index")?,
+			receiver: known.parse_str("Receiver", "an index")?,
+			connection_type: known.require_str("Type")?.to_string(),
+			extra,
+			layout,
+		})
+	}
+
+	pub fn to_object(&self) -> Object {
+		let mut known = Object::new();
+		known.push("Sender", self.sender.to_string());
+		known.push("Receiver", self.receiver.to_string());
+		known.push("Type", self.connection_type.as_str());
+		self.layout.arrange(known, &self.extra, false)
+	}
+}
+
+/// Where the keys a struct knows about were among the ones it doesn't, so they go back in the same places. Each known key that
+/// was there is listed in order, with how many of the other pairs came before it.
+///
+/// `None` is the order the Puzzle Maker writes, with every known key there: all together, before the other pairs or after them if
+/// `rest_first`.
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Layout(Option<Vec<(&'static str, usize)>>);
+
+impl Layout {
+	/// Sorts an object into the first unconditional pair for each of `keys`, and everything else.
+	fn split(object: &Object, keys: &[&'static str], rest_first: bool) -> (Object, Object, Layout) {
+		let mut known = Object::new();
+		let mut rest = Object::new();
+		let mut order = Vec::new();
+		for kv in object {
+			match keys.iter().find(|key| **key == kv.key) {
+				Some(key) if kv.condition.is_none() && known.get(key).is_none() => {
+					order.push((*key, rest.len()));
+					known.extend(Some(kv.clone()));
+				},
+				_ => rest.extend(Some(kv.clone())),
+			}
+		}
+		let usual = if rest_first { rest.len() } else { 0 };
+		let order = if order.iter().map(|(key, _)| key).eq(keys) && order.iter().all(|(_, before)| *before == usual) { None } else { Some(order) };
+		(known, rest, Layout(order))
+	}
+
+	/// Whether the key was there, or would be in a new one.
+	fn has(&self, key: &str) -> bool {
+		self.0.as_ref().map_or(true, |order| order.iter().any(|(k, _)| *k == key))
+	}
+
+	/// Puts the known pairs back among the rest. Any that weren't there when it was read go at the end.
+	fn arrange(&self, known: Object, rest: &Object, rest_first: bool) -> Object {
+		let order = match &self.0 {
+			Some(order) => order,
+			None if rest_first => return rest.iter().chain(&known).cloned().collect::<Vec<_>>().into(),
+			None => return known.iter().chain(rest).cloned().collect::<Vec<_>>().into(),
+		};
+		let mut out = Object::new();
+		let mut rest = rest.iter().enumerate().peekable();
+		for (key, before) in order {
+			while let Some((_, kv)) = rest.next_if(|(i, _)| i < before) {
+				out.extend(Some(kv.clone()));
+			}
+			out.extend(known.iter().find(|kv| kv.key == *key).cloned());
+		}
+		out.extend(rest.map(|(_, kv)| kv.clone()));
+		out.extend(known.iter().filter(|kv| !order.iter().any(|(key, _)| *key == kv.key)).cloned());
+		out
+	}
+}
+
+/// The pairs in a block like `Items` that aren't one of the repeated blocks, each with how many of those came before it.
+type Leftovers = Vec<(usize, Kv)>;
+
+/// Reads a block like `Items`, full of repeated `Item` blocks, if it's there.
+fn read_list<T>(object: &Object, key: &str, entry: &str, parse: fn(&Object) -> Result<T, VdfErr>) -> Result<(Vec<T>, Leftovers), VdfErr> {
+	let mut list = Vec::new();
+	let mut rest = Vec::new();
+	if object.get(key).is_none() {
+		return Ok((list, rest));
+	}
+	for kv in object.require_obj(key)? {
+		match &kv.value {
+			Value::Obj(o) if kv.key == entry && kv.condition.is_none() => list.push(parse(o)?),
+			_ => rest.push((list.len(), kv.clone())),
+		}
+	}
+	Ok((list, rest))
+}
+
+/// Puts the leftovers back among the repeated blocks.
+fn write_list(entry: &str, list: impl Iterator<Item = Object>, rest: &Leftovers) -> Object {
+	let mut out = Object::new();
+	let mut rest = rest.iter().peekable();
+	for (i, object) in list.enumerate() {
+		while let Some((_, kv)) = rest.next_if(|(before, _)| *before <= i) {
+			out.extend(Some(kv.clone()));
+		}
+		out.push(entry, object);
+	}
+	out.extend(rest.map(|(_, kv)| kv.clone()));
+	out
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn voxels() {
+		let grid = VoxelGrid::from_object(&"Voxel \"01 23\" Voxel \"45 ef\"".parse().unwrap()).unwrap();
+		assert_eq!(grid.size(), [2, 2, 2]);
+		assert_eq!(grid.get([1, 0, 0]), Some(Voxel { solid: true, portalable: [false; 3] }));
+		assert_eq!(grid.get([0, 1, 0]), Some(Voxel { solid: false, portalable: [true, false, false] }));
+		assert_eq!(grid.get([0, 0, 1]), Some(Voxel { solid: false, portalable: [false, true, false] }));
+		assert_eq!(grid.get([1, 1, 1]), Some(Voxel { solid: true, portalable: [true; 3] }));
+		assert_eq!(grid.get([2, 0, 0]), None);
+		assert!(grid.is_solid([0, 0, 5]));
+		assert_eq!(
+			grid.iter().filter(|(_, voxel)| voxel.solid).map(|(position, _)| position).collect::<Vec<_>>(),
+			[[1, 0, 0], [1, 1, 0], [1, 0, 1], [1, 1, 1]]
+		);
+		assert_eq!(grid.to_object().to_string(&settings()).unwrap(), "\"Voxel\"\t\"01 23\"\n\"Voxel\"\t\"45 ef\"\n");
+
+		for bad in &[
+			"Voxel \"\"",
+			"Voxel [$X360] \"00\"",
+			"Voxel \"01 2\"",
+			"Voxel \"01 23\" Voxel \"01\"",
+			"Voxel \"0F\"",
+			"Voxel \"0g\"",
+			"Layer \"00\"",
+			"Voxel { }",
+		] {
+			assert!(matches!(VoxelGrid::from_object(&bad.parse().unwrap()), Err(VdfErr::InvalidValue { expected: "a layer of voxels", .. })), "{}", bad);
+		}
+	}
+
+	#[test]
+	fn key_order() {
+		let text = "\"portal2_puzzle\"\n{\n\t\"Items\"\n\t{\n\t}\n\t\"Title\"\t\"Backwards\"\n\t\"Voxels\"\n\t{\n\t}\n\t\"Coop\"\t\"1\"\n\t\"Connections\"\n\t{\n\t}\n}\n";
+		let mut puzzle: Puzzle = text.parse().unwrap();
+		assert_eq!(puzzle.to_string().unwrap(), text);
+
+		puzzle.rest.remove_all("Coop");
+		puzzle.rest.push("AppID", "644");
+		assert_eq!(puzzle.to_object().iter().map(|kv| kv.key.as_str()).collect::<Vec<_>>(), ["Items", "Title", "Voxels", "AppID", "Connections"]);
+
+		//Only the blocks that were there, and everything inside them
+		let items = "Items { Note x Item { Type ITEM_CUBE Index 0 Deletable 1 VoxelPos \"1 1 1\" LocalPos \"0 0 0\" Angles \"0 0 0\" \
+			Facing \"0 0 1\" Type [$X360] ITEM_SPHERE Properties { A 1 } Properties { B 2 } } Item [$X360] { } }"
+			.parse::<Object>()
+			.unwrap();
+		let puzzle = Puzzle::from_object(&items).unwrap();
+		assert_eq!(puzzle.items[0].item_type, "ITEM_CUBE");
+		assert_eq!(puzzle.items[0].get("A"), Some("1"));
+		assert_eq!(puzzle.to_object(), items);
+
+		let connection = "Receiver 1 Sender 0 Type CONNECTION_STANDARD".parse::<Object>().unwrap();
+		assert_eq!(Connection::from_object(&connection).unwrap().to_object(), connection);
+	}
+
+	#[test]
+	fn repair() {
+		let mut puzzle = Puzzle {
+			items: (0..3).map(|i| Item::new(i, "ITEM_BUTTON_FLOOR", [0, 0, 0])).collect(),
+			connections: vec![Connection::new(0, 1), Connection::new(1, 2), Connection::new(2, 7)],
+			..Puzzle::default()
+		};
+
+		assert_eq!(puzzle.next_index(), 3);
+		assert_eq!(puzzle.remove_dangling_connections(), 1);
+		assert_eq!(puzzle.remove_item(1).map(|item| item.index), Some(1));
+		assert!(puzzle.connections.is_empty());
+		assert_eq!(puzzle.remove_item(1), None);
+	}
+}
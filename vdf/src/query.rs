@@ -0,0 +1,418 @@
+//! A little path language for picking pairs out of a `vdf::Object`, for when writing serde structs would be overkill.
+//!
+//! A query is a list of steps separated by `/`, each one going one block deeper, like `ItemData/Item/Editor/SubType`. Each step
+//! matches keys, and can be followed by any number of filters in square brackets:
+//!
+//! * `Item` matches the key `Item`. `*` is a wildcard for any number of characters: `*` matches every key, and `Sub*` matches
+//!   `SubType` and `SubTypeProperty`.
+//! * `"Item Name"` in quotes matches it exactly, spaces, slashes, `*`s and all. Quoted strings can use `\"` and `\\`.
+//! * `[2]` keeps the third of the pairs matched so far (counting from 0), and `[-1]` keeps the last one.
+//! * `[Type=ITEM_BUTTON*]` keeps the blocks that have a `Type` key with a string value matching the pattern, and `[Type!=ITEM_CUBE]`
+//!   the ones that don't. The left side can be a whole query of its own, like `[Editor/MovementHandle=QUAD]`.
+//! * `[Exporting]` keeps the blocks that have anything at all at `Exporting`.
+//! * `Skin [$X360]`, with a space before it, only matches pairs with that exact conditional. Without one, the conditional doesn't
+//!   matter, unless there's an index too: `Skin[1]` is the second `Skin` without a conditional.
+//!
+//! Filters apply left to right, so `Item[Type=ITEM_CUBE][0]` is the first cube, and `Item[0][Type=ITEM_CUBE]` is the first item,
+//! if it's a cube. That's also the syntax `vdf::diff::KeyPath` is written with, so the path of a change selects the pair it's about.
+//!
+//! ```
+//! # use vdf::value::Object;
+//! let mut items: Object = r#"
+//!     "Item" { "Type" "ITEM_BUTTON_FLOOR" "Editor" { "SubType" { "Model" { "ModelName" "buttonweight.mdl" } } } }
+//!     "Item" { "Type" "ITEM_BUTTON_PEDESTAL" "Editor" { "SubType" { "Model" { "ModelName" "underground.mdl" } } } }
+//!     "Item" { "Type" "ITEM_CUBE" "Editor" { "SubType" { "Model" { "ModelName" "cube.mdl" } } } }
+//! "#.parse().unwrap();
+//!
+//! let query = "Item[Type=ITEM_BUTTON*]/Editor/SubType/Model/ModelName";
+//! let models: Vec<_> = items.select(query).unwrap().into_iter().filter_map(|kv| kv.value.as_str()).collect();
+//! assert_eq!(models, vec!["buttonweight.mdl", "underground.mdl"]);
+//!
+//! for kv in items.select_mut(query).unwrap() {
+//!     kv.value = "placeholder.mdl".into();
+//! }
+//! assert_eq!(items.select(query).unwrap()[1].value.as_str(), Some("placeholder.mdl"));
+//! ```
+
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use crate::error::VdfErr;
+use crate::value::Kv;
+use crate::value::Object;
+use crate::value::Value;
+
+/// A parsed query. Parse once with `Query::new` (or `str::parse`) to reuse it; `Object::select` parses every time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+	steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Step {
+	key: Pattern,
+	condition: Option<String>,
+	filters: Vec<Filter>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Filter {
+	Index(isize),
+	Has(Query),
+	Compare { path: Query, pattern: Pattern, negate: bool },
+}
+
+/// A string to match against, where `Star` is the `*` wildcard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Pattern(Vec<Piece>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+	Text(String),
+	Star,
+}
+
+impl Query {
+	pub fn new(text: &str) -> Result<Self, VdfErr> {
+		let mut parser = Parser { text, rest: text };
+		let query = parser.query()?;
+		match parser.rest.chars().next() {
+			None => Ok(query),
+			Some(c) => Err(parser.error(format!("unexpected {:?}", c))),
+		}
+	}
+
+	/// Every pair in the object that the query leads to, in order.
+	pub fn select<'a>(&self, object: &'a Object) -> Vec<&'a Kv> {
+		let mut out = Vec::new();
+		select_in(&self.steps, object, &mut out);
+		out
+	}
+
+	/// Every pair in the object that the query leads to, in order, for editing.
+	///
+	/// Every step goes one block deeper, so the pairs never overlap.
+	pub fn select_mut<'a>(&self, object: &'a mut Object) -> Vec<&'a mut Kv> {
+		let mut out = Vec::new();
+		select_mut_in(&self.steps, object, &mut out);
+		out
+	}
+
+	/// Whether the query leads to anything in the value. (Only blocks have anything in them.)
+	fn finds(&self, value: &Value) -> bool {
+		value.as_obj().is_some_and(|object| !self.select(object).is_empty())
+	}
+}
+
+impl FromStr for Query {
+	type Err = VdfErr;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Query::new(s)
+	}
+}
+
+fn select_in<'a>(steps: &[Step], object: &'a Object, out: &mut Vec<&'a Kv>) {
+	let (step, rest) = match steps.split_first() {
+		Some(split) => split,
+		None => return,
+	};
+
+	for (_, kv) in step.pick(object) {
+		if rest.is_empty() {
+			out.push(kv);
+		} else if let Value::Obj(o) = &kv.value {
+			select_in(rest, o, out);
+		}
+	}
+}
+
+fn select_mut_in<'a>(steps: &[Step], object: &'a mut Object, out: &mut Vec<&'a mut Kv>) {
+	let (step, rest) = match steps.split_first() {
+		Some(split) => split,
+		None => return,
+	};
+
+	let picks: Vec<usize> = step.pick(object).into_iter().map(|(i, _)| i).collect();
+	for (i, kv) in object.iter_mut().enumerate() {
+		if !picks.contains(&i) {
+			continue;
+		}
+		if rest.is_empty() {
+			out.push(kv);
+		} else if let Value::Obj(o) = &mut kv.value {
+			select_mut_in(rest, o, out);
+		}
+	}
+}
+
+impl Step {
+	/// The pairs in the object this step matches, along with their positions.
+	fn pick<'a>(&self, object: &'a Object) -> Vec<(usize, &'a Kv)> {
+		//Indexes count the pairs without a conditional, the same as diff paths do
+		let indexed = self.filters.iter().any(|filter| matches!(filter, Filter::Index(_)));
+		let mut picks: Vec<(usize, &Kv)> = object
+			.iter()
+			.enumerate()
+			.filter(|(_, kv)| self.key.matches(&kv.key))
+			.filter(|(_, kv)| match &self.condition {
+				Some(condition) => kv.condition.as_ref().map(|c| c.as_str()) == Some(condition.as_str()),
+				None => !indexed || kv.condition.is_none(),
+			})
+			.collect();
+
+		for filter in &self.filters {
+			picks = match filter {
+				Filter::Index(n) => {
+					let n = if *n < 0 { picks.len() as isize + n } else { *n };
+					match usize::try_from(n).ok().and_then(|n| picks.get(n)) {
+						Some(pick) => vec![*pick],
+						None => Vec::new(),
+					}
+				},
+				Filter::Has(path) => picks.into_iter().filter(|(_, kv)| path.finds(&kv.value)).collect(),
+				Filter::Compare { path, pattern, negate } => picks
+					.into_iter()
+					.filter(|(_, kv)| {
+						let found =
+							kv.value.as_obj().is_some_and(|o| path.select(o).iter().any(|found| found.value.as_str().is_some_and(|s| pattern.matches(s))));
+						found != *negate
+					})
+					.collect(),
+			}
+		}
+
+		picks
+	}
+}
+
+impl Pattern {
+	fn matches(&self, text: &str) -> bool {
+		matches(&self.0, text)
+	}
+}
+
+fn matches(pieces: &[Piece], text: &str) -> bool {
+	match pieces.split_first() {
+		None => text.is_empty(),
+		Some((Piece::Text(literal), rest)) => text.strip_prefix(literal.as_str()).is_some_and(|text| matches(rest, text)),
+		//Try every place the star could stop
+		Some((Piece::Star, rest)) => text.char_indices().map(|(i, _)| i).chain(Some(text.len())).any(|i| matches(rest, &text[i..])),
+	}
+}
+
+struct Parser<'a> {
+	text: &'a str,
+	rest: &'a str,
+}
+
+impl<'a> Parser<'a> {
+	fn error(&self, problem: String) -> VdfErr {
+		VdfErr::BadQuery { query: self.text.to_string(), problem }
+	}
+
+	fn peek(&self) -> Option<char> {
+		self.rest.chars().next()
+	}
+
+	fn eat(&mut self, c: char) -> bool {
+		match self.rest.strip_prefix(c) {
+			Some(rest) => {
+				self.rest = rest;
+				true
+			},
+			None => false,
+		}
+	}
+
+	fn skip_whitespace(&mut self) {
+		self.rest = self.rest.trim_start();
+	}
+
+	fn query(&mut self) -> Result<Query, VdfErr> {
+		let mut steps = vec![self.step()?];
+		while self.eat('/') {
+			steps.push(self.step()?);
+		}
+		Ok(Query { steps })
+	}
+
+	fn step(&mut self) -> Result<Step, VdfErr> {
+		let key = self.pattern("a key")?;
+		let mut step = Step { key, condition: None, filters: Vec::new() };
+
+		loop {
+			if self.eat('[') {
+				step.filters.push(self.filter()?);
+			} else if self.rest.trim_start().starts_with('[') && self.peek().is_some_and(char::is_whitespace) {
+				self.skip_whitespace();
+				self.eat('[');
+				let end = self.rest.find(']').ok_or_else(|| self.error("unclosed conditional".to_string()))?;
+				step.condition = Some(self.rest[..end].to_string());
+				self.rest = &self.rest[end + 1..];
+			} else {
+				return Ok(step);
+			}
+		}
+	}
+
+	/// Everything between the square brackets of a filter, and the closing bracket.
+	fn filter(&mut self) -> Result<Filter, VdfErr> {
+		self.skip_whitespace();
+		let number_end = self.rest.find(|c: char| !(c.is_ascii_digit() || c == '-')).unwrap_or(self.rest.len());
+		let filter = match self.rest[..number_end].parse::<isize>() {
+			Ok(n) if self.rest[number_end..].trim_start().starts_with(']') => {
+				self.rest = &self.rest[number_end..];
+				Filter::Index(n)
+			},
+			_ => {
+				let path = self.query()?;
+				self.skip_whitespace();
+				let negate = self.eat('!');
+				if self.eat('=') {
+					self.skip_whitespace();
+					Filter::Compare { path, pattern: self.pattern("a value")?, negate }
+				} else if negate {
+					return Err(self.error("expected = after !".to_string()));
+				} else {
+					Filter::Has(path)
+				}
+			},
+		};
+
+		self.skip_whitespace();
+		if self.eat(']') {
+			Ok(filter)
+		} else {
+			Err(self.error("expected ]".to_string()))
+		}
+	}
+
+	/// A quoted string, which is matched exactly, or a bare word, where `*` is a wildcard.
+	fn pattern(&mut self, what: &str) -> Result<Pattern, VdfErr> {
+		if self.eat('"') {
+			let mut text = String::new();
+			let mut chars = self.rest.char_indices();
+			loop {
+				match chars.next() {
+					Some((i, '"')) => {
+						self.rest = &self.rest[i + 1..];
+						return Ok(Pattern(vec![Piece::Text(text)]));
+					},
+					Some((_, '\\')) => match chars.next() {
+						Some((_, c)) => text.push(c),
+						None => break,
+					},
+					Some((_, c)) => text.push(c),
+					None => break,
+				}
+			}
+			return Err(self.error("unclosed quote".to_string()));
+		}
+
+		let end = self
+			.rest
+			.char_indices()
+			.find(|&(i, c)| c.is_whitespace() || "/[]=\"".contains(c) || (c == '!' && self.rest[i + 1..].starts_with('=')))
+			.map_or(self.rest.len(), |(i, _)| i);
+		if end == 0 {
+			return Err(self.error(format!("expected {}", what)));
+		}
+
+		let mut pieces = Vec::new();
+		for (i, text) in self.rest[..end].split('*').enumerate() {
+			if i != 0 {
+				pieces.push(Piece::Star);
+			}
+			if !text.is_empty() {
+				pieces.push(Piece::Text(text.to_string()));
+			}
+		}
+		self.rest = &self.rest[end..];
+		Ok(Pattern(pieces))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use crate::diff;
+
+	fn keys(object: &Object, query: &str) -> Vec<String> {
+		object.select(query).unwrap().iter().map(|kv| format!("{}={}", kv.key, kv.value.as_str().unwrap_or("{}"))).collect()
+	}
+
+	#[test]
+	fn selecting() {
+		let object: Object = r#"
+			a { x 1 y 2 }
+			b { x 3 "z z" 4 }
+			b { x 5 }
+			"c/d" 6
+			e 7 [$X360]
+			e 8 [!$X360]
+		"#
+		.parse()
+		.unwrap();
+
+		assert_eq!(keys(&object, "a/x"), vec!["x=1"]);
+		assert_eq!(keys(&object, "*/x"), vec!["x=1", "x=3", "x=5"]);
+		assert_eq!(keys(&object, "b/*"), vec!["x=3", "z z=4", "x=5"]);
+		assert_eq!(keys(&object, r#"b/"z z""#), vec!["z z=4"]);
+		assert_eq!(keys(&object, "b[1]/x"), vec!["x=5"]);
+		assert_eq!(keys(&object, "b[-1]/x"), vec!["x=5"]);
+		assert_eq!(keys(&object, "b[2]/x"), Vec::<String>::new());
+		assert_eq!(keys(&object, "*[x=3]/*"), vec!["x=3", "z z=4"]);
+		assert_eq!(keys(&object, "*[x!=3]/x"), vec!["x=1", "x=5"]);
+		assert_eq!(keys(&object, "*[ x = * ][-1]/x"), vec!["x=5"]);
+		assert_eq!(keys(&object, r#"*["z z"]"#), vec!["b={}"]);
+		assert_eq!(keys(&object, r#""c/d""#), vec!["c/d=6"]);
+		assert_eq!(keys(&object, "e"), vec!["e=7", "e=8"]);
+		assert_eq!(keys(&object, "e [!$X360]"), vec!["e=8"]);
+		assert_eq!(keys(&object, "e[0]"), Vec::<String>::new());
+		assert_eq!(keys(&object, "a/x/y"), Vec::<String>::new());
+	}
+
+	#[test]
+	fn patterns() {
+		let pattern = |text: &str| Parser { text, rest: text }.pattern("").unwrap();
+		assert!(pattern("ITEM_*").matches("ITEM_BUTTON"));
+		assert!(pattern("ITEM_*").matches("ITEM_"));
+		assert!(!pattern("ITEM_*").matches("ITEM"));
+		assert!(pattern("*_*_*").matches("ITEM_BUTTON_FLOOR"));
+		assert!(!pattern("*_*_*").matches("ITEM_CUBE"));
+		assert!(pattern("*.mdl").matches("props/cube.mdl"));
+		assert!(!pattern(r#""*.mdl""#).matches("props/cube.mdl"));
+		assert!(pattern(r#""*.mdl""#).matches("*.mdl"));
+	}
+
+	#[test]
+	fn diff_paths() {
+		let old: Object = "Item { Type A Pos 1 } Item { Type B Pos 2 } Skin 1 [$X360]".parse().unwrap();
+		let new: Object = "Item { Type A Pos 1 } Item { Type B Pos 3 } Skin 2 [$X360]".parse().unwrap();
+		for change in diff::diff(&old, &new, &diff::DiffOptions::new().with_id_key("Type")) {
+			let path = change.path.to_string();
+			assert_eq!(old.select(&path).unwrap().len(), 1, "{}", path);
+		}
+
+		//The same key with and without a conditional, and keys that need quoting
+		let old: Object = "e 1 [$X360] e 2 e 3 f 4 [$X360] f 5 \"a\\nb\" 6 \"c\\\"d\\\\\" 7 \"g!=h\" 8".parse().unwrap();
+		let new: Object = "e 1 [$X360] e 2 e 9 f 4 [$X360] f 9 \"a\\nb\" 9 \"c\\\"d\\\\\" 9 \"g!=h\" 9".parse().unwrap();
+		let changes = diff::diff(&old, &new, &diff::DiffOptions::new());
+		assert_eq!(changes.len(), 5);
+		for change in changes {
+			let path = change.path.to_string();
+			let selected = old.select(&path).unwrap();
+			assert_eq!(selected.len(), 1, "{}", path);
+			assert!(matches!(&change.kind, diff::ChangeKind::Changed { old, .. } if *old == selected[0].value), "{}", path);
+		}
+	}
+
+	#[test]
+	fn errors() {
+		for bad in &["", "a/", "a[", "a[0", "a[b!=]", "a [$X360", "\"a", "a]", "a[=b]"] {
+			assert!(matches!(Query::new(bad), Err(VdfErr::BadQuery { .. })), "{:?}", bad);
+		}
+	}
+}
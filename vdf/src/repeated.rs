@@ -0,0 +1,81 @@
+//! Serde `with` module for writing a `Vec<T>` as the same key repeated over and over.
+//!
+//! Valve's files don't have arrays. Instead, they repeat a key: `editoritems.txt` has an `"ItemData"` block full of `"Item"`
+//! blocks, and every `"Item"` has one or more `"SubType"` blocks. Put `#[serde(with = "vdf::repeated")]` on a `Vec` field to read
+//! and write it like that, using the field's name (or its `rename`) as the key:
+//!
+//! ```
+//! #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+//! struct ItemData {
+//!     #[serde(rename = "Item", with = "vdf::repeated", default)]
+//!     items: Vec<Item>,
+//! }
+//!
+//! #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+//! struct Item {
+//!     #[serde(rename = "Type")]
+//!     item_type: String,
+//! }
+//!
+//! let data = ItemData { items: vec![Item { item_type: "ITEM_BUTTON_PEDESTAL".into() }, Item { item_type: "ITEM_CUBE".into() }] };
+//! let text = vdf::ser::to_string(&data).unwrap();
+//! assert_eq!(text.matches("\"Item\"").count(), 2);
+//! assert_eq!(vdf::de::from_str::<ItemData>(&text).unwrap(), data);
+//! ```
+//!
+//! When reading, every pair in the block with that key is collected, even if other keys are mixed in between them. An empty
+//! `Vec` writes nothing at all, so pair this with `#[serde(default)]` if the key might not appear.
+//!
+//! Other Serde formats see a plain sequence.
+
+use serde::de;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+/// The magic newtype struct name that `VdfSerializer` and `VdfDeserializer` look out for.
+pub(crate) const NAME: &str = "$vdf::repeated";
+
+pub fn serialize<S, T>(value: &[T], serializer: S) -> Result<S::Ok, S::Error>
+where
+	S: Serializer,
+	T: Serialize,
+{
+	serializer.serialize_newtype_struct(NAME, value)
+}
+
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+	D: Deserializer<'de>,
+	T: Deserialize<'de>,
+{
+	struct Visitor<T>(std::marker::PhantomData<T>);
+
+	impl<'de, T> de::Visitor<'de> for Visitor<T>
+	where
+		T: Deserialize<'de>,
+	{
+		type Value = Vec<T>;
+
+		fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+			formatter.write_str("a repeated key")
+		}
+
+		fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+		where
+			D: Deserializer<'de>,
+		{
+			Vec::deserialize(deserializer)
+		}
+
+		fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+		where
+			A: de::SeqAccess<'de>,
+		{
+			Vec::deserialize(de::value::SeqAccessDeserializer::new(seq))
+		}
+	}
+
+	deserializer.deserialize_newtype_struct(NAME, Visitor(std::marker::PhantomData))
+}
@@ -2,12 +2,20 @@
 
 #![allow(unused_variables)] //for now
 
+use std::fmt;
+use std::fmt::Write;
+use std::io;
+
 use serde::ser;
 use serde::ser::Impossible;
 use serde::serde_if_integer128;
 use serde::Serializer;
 
+use crate::conditional::Conditional;
 use crate::error::VdfErr;
+use crate::inline;
+use crate::inline::InlineSerializer;
+use crate::repeated;
 
 //a lot of these doc comments are cribbed from serde-json lol. Hey, ecosystem-wide consistency is good.
 
@@ -18,11 +26,11 @@ use crate::error::VdfErr;
 /// Serialization can fail if `T`'s implementation of `Serialize` decides to fail.
 pub fn to_string<T>(value: &T) -> Result<String, VdfErr>
 where
-	T: serde::Serialize,
+	T: ?Sized + serde::Serialize,
 {
 	let mut ser = VdfSerializer::default();
 	value.serialize(&mut ser)?;
-	Ok(ser.out)
+	Ok(ser.into_inner())
 }
 
 /// Serialize the given data structure as a VDF String, where the name of the top-level block is provided.
@@ -42,39 +50,223 @@ where
 /// ```
 pub fn to_string_with_toplevel_block<T>(value: &T, toplevel_block_name: &str) -> Result<String, VdfErr>
 where
-	T: serde::Serialize,
+	T: ?Sized + serde::Serialize,
 {
 	let mut ser = VdfSerializer::default();
 	ser.serialize_str(toplevel_block_name)?;
 	value.serialize(&mut ser)?;
-	Ok(ser.out)
+	Ok(ser.into_inner())
+}
+
+/// Serialize the given data structure as VDF into the IO stream.
+///
+/// Output is written in lots of little pieces, so wrapping files and sockets in a `BufWriter` is a good idea.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to fail, or if writing to the stream fails.
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<(), VdfErr>
+where
+	W: io::Write,
+	T: ?Sized + serde::Serialize,
+{
+	let mut ser = VdfSerializer::with_writer(IoWriter::new(writer), FormatSettings::p2c_like());
+	let result = value.serialize(&mut ser);
+	ser.into_inner().finish(result)
+}
+
+/// Serialize the given data structure as VDF into the IO stream, where the name of the top-level block is provided.
+///
+/// See `to_string_with_toplevel_block`.
+pub fn to_writer_with_toplevel_block<W, T>(writer: W, value: &T, toplevel_block_name: &str) -> Result<(), VdfErr>
+where
+	W: io::Write,
+	T: ?Sized + serde::Serialize,
+{
+	let mut ser = VdfSerializer::with_writer(IoWriter::new(writer), FormatSettings::p2c_like());
+	let result = ser.serialize_str(toplevel_block_name).and_then(|_| value.serialize(&mut ser));
+	ser.into_inner().finish(result)
 }
 
 /// Thing what do the seralize!!!
-pub struct VdfSerializer {
-	pub out: String, //todo should this be public lol. Really I should use a Writer
+///
+/// Writes into anything that implements `fmt::Write`, which is a `String` unless you say otherwise. To write into an
+/// `io::Write`, wrap it in an `IoWriter` (or just use `to_writer`).
+pub struct VdfSerializer<W = String> {
+	out: Sink<W>,
 	state: State,
 	indent_depth: usize,
 	format_settings: FormatSettings,
+	/// A conditional to write after the next value (or between the next block's key and its opening brace).
+	conditional: Option<Conditional>,
+	/// Set by `vdf::repeated`. The next sequence is written as its key repeated once per element, instead of as a block.
+	repeated: bool,
+	/// The indent depth of an enum variant being written at the top level of the file, without a block around it.
+	bare_variant: Option<usize>,
 }
 
 impl VdfSerializer {
-	/// Create a serializer with the given Settings.
+	/// Create a serializer with the given Settings, that writes into a `String`.
 	///
 	/// Note that `VdfSerializer::default` can be used to create one with the default `editoritems.txt`-like settings.
 	pub fn with_settings(settings: FormatSettings) -> Self {
-		VdfSerializer { out: String::new(), state: State::WaitingForKey, indent_depth: 0, format_settings: settings }
+		VdfSerializer::with_writer(String::new(), settings)
+	}
+}
+
+impl<W> VdfSerializer<W>
+where
+	W: fmt::Write,
+{
+	/// Create a serializer with the given Settings, that writes into the given writer.
+	pub fn with_writer(out: W, settings: FormatSettings) -> Self {
+		let out = Sink { inner: out, held: Vec::new(), in_key: false };
+		VdfSerializer { out, state: State::WaitingForKey, indent_depth: 0, format_settings: settings, conditional: None, repeated: false, bare_variant: None }
+	}
+
+	/// Get the writer back, containing everything serialized so far.
+	///
+	/// With `with_aligned_values`, blocks that haven't been closed yet are still held back, and aren't in there.
+	pub fn into_inner(self) -> W {
+		self.out.inner
 	}
 }
 
 impl Default for VdfSerializer {
 	fn default() -> Self {
-		VdfSerializer { out: String::new(), state: State::WaitingForKey, indent_depth: 0, format_settings: FormatSettings::p2c_like() }
+		VdfSerializer::with_settings(FormatSettings::p2c_like())
+	}
+}
+
+/// Adapts an `io::Write` into a `fmt::Write`, so `VdfSerializer` can write into files and such.
+///
+/// `fmt::Error` can't carry any information, so the underlying IO error is stashed in here and brought back out by `finish`.
+pub struct IoWriter<W> {
+	inner: W,
+	error: Option<io::Error>,
+}
+
+impl<W> IoWriter<W>
+where
+	W: io::Write,
+{
+	pub fn new(inner: W) -> Self {
+		IoWriter { inner, error: None }
+	}
+
+	/// Swaps the meaningless `fmt::Error` in a serialization result for the IO error that caused it, if there was one.
+	pub fn finish<T>(mut self, result: Result<T, VdfErr>) -> Result<T, VdfErr> {
+		match (result, self.error.take()) {
+			(Err(VdfErr::Fmt(_)), Some(io)) => Err(VdfErr::Io(io)),
+			(result, _) => result,
+		}
+	}
+
+	pub fn into_inner(self) -> W {
+		self.inner
+	}
+}
+
+impl<W> fmt::Write for IoWriter<W>
+where
+	W: io::Write,
+{
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		self.inner.write_all(s.as_bytes()).map_err(|e| {
+			self.error = Some(e);
+			fmt::Error
+		})
+	}
+}
+
+/// Where `VdfSerializer` writes to. Usually that's straight into the writer, but when values are being lined up, the contents
+/// of each block are held back until the block ends and the longest key in it is known.
+struct Sink<W> {
+	inner: W,
+	/// The contents of each block that's being held back, innermost last.
+	held: Vec<Vec<Held>>,
+	/// If `true`, writes go into the `Held::Key` at the end of the innermost block.
+	in_key: bool,
+}
+
+enum Held {
+	Text(String),
+	/// The key of a key-value pair, which gets padded out to the length of the longest one.
+	Key(String),
+}
+
+impl<W> Sink<W>
+where
+	W: fmt::Write,
+{
+	/// Start holding back the contents of a block.
+	fn hold(&mut self) {
+		self.held.push(Vec::new());
+	}
+
+	/// Everything written until `end_key` is the key of a pair.
+	fn begin_key(&mut self) {
+		if let Some(block) = self.held.last_mut() {
+			block.push(Held::Key(String::new()));
+			self.in_key = true;
+		}
+	}
+
+	fn end_key(&mut self) {
+		self.in_key = false;
+	}
+
+	/// Write out the block that's been held back the longest, with its keys padded with spaces so the values line up.
+	fn release(&mut self) -> fmt::Result {
+		let block = self.held.pop().unwrap_or_default();
+		let width = block.iter().map(|held| if let Held::Key(key) = held { key.chars().count() } else { 0 }).max().unwrap_or(0);
+
+		for held in block {
+			match held {
+				Held::Text(text) => self.write_str(&text)?,
+				Held::Key(key) => write!(self, "{:width$}", key, width = width)?,
+			}
+		}
+		Ok(())
 	}
 }
 
-#[derive(Clone, PartialEq, Eq)]
+impl<W> fmt::Write for Sink<W>
+where
+	W: fmt::Write,
+{
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		let block = match self.held.last_mut() {
+			Some(block) => block,
+			None => return self.inner.write_str(s),
+		};
+
+		match block.last_mut() {
+			Some(Held::Key(key)) if self.in_key => key.push_str(s),
+			Some(Held::Text(text)) => text.push_str(s),
+			_ => block.push(Held::Text(s.to_string())),
+		}
+		Ok(())
+	}
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
 /// Settings that control the look of the generated VDF file.
+///
+/// Start from one of the presets and change whatever you need with the `with_` methods:
+///
+/// ```
+/// use vdf::ser::FormatSettings;
+/// use vdf::ser::KeyQuoteRule;
+/// use vdf::ser::LineEnding;
+///
+/// let settings = FormatSettings::p2c_like()
+///     .with_key_quote_rule(KeyQuoteRule::Never)
+///     .with_indent("    ")
+///     .with_separator(" ")
+///     .with_aligned_values(true)
+///     .with_line_ending(LineEnding::CrLf);
+/// ```
 pub struct FormatSettings {
 	/// How should keys be quoted?
 	key_quote_rule: KeyQuoteRule,
@@ -87,9 +279,19 @@ pub struct FormatSettings {
 	/// If `true`, curly braces are placed at the same indentation level as their contents, emulating how BEEMOD writes vdf files.
 	bump_braces: bool,
 	/// The string used for indentation. Must be some kind of whitespace. One copy of the string is output for each indentation level.
-	indent_str: &'static str,
+	indent_str: String,
 	/// The string used to space out keys and values. Must be some kind of whitespace.
-	inter_str: &'static str,
+	inter_str: String,
+	/// If `true`, keys in each block are padded with spaces so all the values start in the same column.
+	align_values: bool,
+	/// What goes at the end of each line.
+	line_ending: LineEnding,
+	/// If `true`, the escape sequences `\"`, `\\`, `\n` and `\t` are used inside quoted strings, both when writing and when reading.
+	pub(crate) escapes: bool,
+	/// How sequences are written, and read back.
+	pub(crate) sequence_format: SequenceFormat,
+	/// Whether newtype structs write their name, and are expected to have it when reading.
+	pub(crate) newtype_format: NewtypeFormat,
 }
 
 impl FormatSettings {
@@ -100,6 +302,7 @@ impl FormatSettings {
 	/// * Numeric bools.
 	/// * Tab indentation.
 	/// * Spaces between keys and values.
+	/// * No escape sequences. Hammer writes backslashes verbatim.
 	pub fn vmf_like() -> Self {
 		FormatSettings {
 			key_quote_rule: KeyQuoteRule::NotBlocks,
@@ -107,8 +310,13 @@ impl FormatSettings {
 			value_quote_rule: ValueQuoteRule::Always,
 			bool_format: BoolFormat::Numeric,
 			bump_braces: false,
-			indent_str: "\t",
-			inter_str: " ",
+			indent_str: "\t".into(),
+			inter_str: " ".into(),
+			align_values: false,
+			line_ending: LineEnding::Lf,
+			escapes: false,
+			sequence_format: SequenceFormat::Indexed,
+			newtype_format: NewtypeFormat::Named,
 		}
 	}
 
@@ -118,6 +326,7 @@ impl FormatSettings {
 	/// * Numeric bools.
 	/// * Tab indentation.
 	/// * Tabs separate keys and values.
+	/// * Escape sequences in quoted strings.
 	///
 	/// This is also how the vanilla `editoritems.txt` is formatted, and seems to be the format that a lot of Valve's more modern stuff uses,
 	/// like steam controller .vdf files.
@@ -128,8 +337,13 @@ impl FormatSettings {
 			value_quote_rule: ValueQuoteRule::Always,
 			bool_format: BoolFormat::Numeric,
 			bump_braces: false,
-			indent_str: "\t",
-			inter_str: "\t",
+			indent_str: "\t".into(),
+			inter_str: "\t".into(),
+			align_values: false,
+			line_ending: LineEnding::Lf,
+			escapes: true,
+			sequence_format: SequenceFormat::Indexed,
+			newtype_format: NewtypeFormat::Named,
 		}
 	}
 
@@ -140,6 +354,7 @@ impl FormatSettings {
 	/// * Tab indentation.
 	/// * Spaces separate keys and values.
 	/// * Opening and closing curly braces gain an extra level of indentation.
+	/// * Escape sequences in quoted strings.
 	pub fn beemod_like() -> Self {
 		FormatSettings {
 			key_quote_rule: KeyQuoteRule::Always,
@@ -147,8 +362,13 @@ impl FormatSettings {
 			value_quote_rule: ValueQuoteRule::Always,
 			bool_format: BoolFormat::Numeric,
 			bump_braces: true,
-			indent_str: "\t",
-			inter_str: " ",
+			indent_str: "\t".into(),
+			inter_str: " ".into(),
+			align_values: false,
+			line_ending: LineEnding::Lf,
+			escapes: true,
+			sequence_format: SequenceFormat::Indexed,
+			newtype_format: NewtypeFormat::Named,
 		}
 	}
 
@@ -158,6 +378,7 @@ impl FormatSettings {
 	/// * Nonnumeric values quoted.
 	/// * Tab indentation.
 	/// * Tabs separate keys and values.
+	/// * Escape sequences in quoted strings.
 	pub fn gameinfo_like() -> Self {
 		FormatSettings {
 			key_quote_rule: KeyQuoteRule::Never,
@@ -165,10 +386,119 @@ impl FormatSettings {
 			value_quote_rule: ValueQuoteRule::Nonnumeric, //TODO: gameinfo.txt also doesn't quote SearchPaths
 			bool_format: BoolFormat::Numeric,
 			bump_braces: false,
-			indent_str: "\t",
-			inter_str: "\t",
+			indent_str: "\t".into(),
+			inter_str: "\t".into(),
+			align_values: false,
+			line_ending: LineEnding::Lf,
+			escapes: true,
+			sequence_format: SequenceFormat::Indexed,
+			newtype_format: NewtypeFormat::Named,
 		}
 	}
+
+	/// Look up a preset by name: `"vmf"`, `"p2c"`, `"beemod"` or `"gameinfo"`. Handy for command-line tools.
+	pub fn preset(name: &str) -> Option<Self> {
+		match name {
+			"vmf" => Some(FormatSettings::vmf_like()),
+			"p2c" => Some(FormatSettings::p2c_like()),
+			"beemod" => Some(FormatSettings::beemod_like()),
+			"gameinfo" => Some(FormatSettings::gameinfo_like()),
+			_ => None,
+		}
+	}
+
+	/// Change how keys are quoted.
+	pub fn with_key_quote_rule(mut self, key_quote_rule: KeyQuoteRule) -> Self {
+		self.key_quote_rule = key_quote_rule;
+		self
+	}
+
+	/// Quote top-level keys differently from the rest, or `None` to treat them like any other key.
+	///
+	/// `gameinfo_like` uses this to quote the `"GameInfo"` key and nothing else.
+	pub fn with_toplevel_key_quote_rule(mut self, toplevel_key_quote_rule: Option<KeyQuoteRule>) -> Self {
+		self.toplevel_key_quote_rule = toplevel_key_quote_rule;
+		self
+	}
+
+	/// Change how values are quoted.
+	pub fn with_value_quote_rule(mut self, value_quote_rule: ValueQuoteRule) -> Self {
+		self.value_quote_rule = value_quote_rule;
+		self
+	}
+
+	/// Change how bools are written.
+	pub fn with_bool_format(mut self, bool_format: BoolFormat) -> Self {
+		self.bool_format = bool_format;
+		self
+	}
+
+	/// If `true`, curly braces get an extra level of indentation, like BEEMOD does it.
+	pub fn with_bump_braces(mut self, bump_braces: bool) -> Self {
+		self.bump_braces = bump_braces;
+		self
+	}
+
+	/// Change the string written once per level of indentation, like `"\t"` or `"    "`.
+	///
+	/// # Panics
+	///
+	/// If the string has anything besides whitespace in it.
+	pub fn with_indent(mut self, indent: impl Into<String>) -> Self {
+		let indent = indent.into();
+		assert!(indent.chars().all(char::is_whitespace), "indentation must be whitespace, not {:?}", indent);
+		self.indent_str = indent;
+		self
+	}
+
+	/// Change the string written between keys and values, like `"\t"` or `" "`.
+	///
+	/// # Panics
+	///
+	/// If the string is empty, or has anything besides whitespace in it.
+	pub fn with_separator(mut self, separator: impl Into<String>) -> Self {
+		let separator = separator.into();
+		assert!(!separator.is_empty() && separator.chars().all(char::is_whitespace), "the separator must be whitespace, not {:?}", separator);
+		self.inter_str = separator;
+		self
+	}
+
+	/// Turn escape sequences in quoted strings on or off. The deserializer reads them back the same way.
+	pub fn with_escapes(mut self, escapes: bool) -> Self {
+		self.escapes = escapes;
+		self
+	}
+
+	/// If `true`, keys are padded with spaces so every value in a block starts in the same column, like a lot of hand-written
+	/// files do.
+	///
+	/// That means each block has to be held in memory until it ends, so the output comes out in bigger chunks.
+	pub fn with_aligned_values(mut self, align_values: bool) -> Self {
+		self.align_values = align_values;
+		self
+	}
+
+	/// Change what goes at the end of each line.
+	pub fn with_line_ending(mut self, line_ending: LineEnding) -> Self {
+		self.line_ending = line_ending;
+		self
+	}
+
+	/// Change how sequences are written. The deserializer reads them back the same way.
+	///
+	/// All the presets use `SequenceFormat::Indexed`.
+	pub fn with_sequence_format(mut self, sequence_format: SequenceFormat) -> Self {
+		self.sequence_format = sequence_format;
+		self
+	}
+
+	/// Change whether newtype structs write their name. The deserializer reads them back the same way.
+	///
+	/// All the presets use `NewtypeFormat::Named`.
+	pub fn with_newtype_format(mut self, newtype_format: NewtypeFormat) -> Self {
+		self.newtype_format = newtype_format;
+		self
+	}
 }
 
 /// How should keys be quoted? Note that keys with whitespace are always quoted.
@@ -202,23 +532,68 @@ pub enum BoolFormat {
 	Numeric,
 }
 
+/// How should sequences (`Vec`s, slices and so on) be serialized?
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Clone)]
+pub enum SequenceFormat {
+	/// A block with the elements under the keys `"0"`, `"1"`, `"2"`...
+	Indexed,
+	/// The sequence's own key, repeated once per element. Like the `"solid"`s in a VMF, or `vdf::repeated`.
+	///
+	/// An empty sequence writes nothing at all, so the field needs `#[serde(default)]` to be read back. At the top level of
+	/// the file there's no key to repeat, so sequences there are `Indexed` instead.
+	Repeated,
+	/// One string with the elements separated by spaces, like `"origin" "1 2 3"`. Only works for numbers and strings without
+	/// whitespace in them.
+	Inline,
+}
+
+/// What goes at the end of each line?
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Clone)]
+pub enum LineEnding {
+	/// `\n`, like pretty much everything.
+	Lf,
+	/// `\r\n`, like Windows.
+	CrLf,
+}
+
+impl LineEnding {
+	fn as_str(&self) -> &'static str {
+		match self {
+			LineEnding::Lf => "\n",
+			LineEnding::CrLf => "\r\n",
+		}
+	}
+}
+
+/// How should newtype structs (`struct Wrapper(Yea);`) be serialized?
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Clone)]
+pub enum NewtypeFormat {
+	/// The name of the struct is written as a key, with the contents as its value. At the top level of the file this is
+	/// `"Wrapper" { ... }`, and as the value of a pair it's `"key" { "Wrapper" { ... } }`.
+	///
+	/// This is one way of making a top-level block like the `"ItemData"` in `editoritems.txt`.
+	Named,
+	/// The wrapper disappears and only the contents are written, like most other serde formats.
+	Transparent,
+}
+
+#[derive(Default)]
 enum State {
+	#[default]
 	WaitingForKey,
 	WaitingForValue(String), //TODO: If I can do this with &str, that would be great.
 	WritingNestedValue(usize),
 }
 
-impl Default for State {
-	fn default() -> Self {
-		State::WaitingForKey
-	}
-}
-
-impl VdfSerializer {
-	fn indent(&mut self) {
+impl<W> VdfSerializer<W>
+where
+	W: fmt::Write,
+{
+	fn indent(&mut self) -> Result<(), VdfErr> {
 		for i in 0..self.indent_depth {
-			self.out.push_str(self.format_settings.indent_str);
+			self.out.write_str(&self.format_settings.indent_str)?;
 		}
+		Ok(())
 	}
 
 	#[inline]
@@ -232,11 +607,12 @@ impl VdfSerializer {
 	}
 
 	#[inline]
-	fn newline(&mut self) {
-		self.out.push('\n');
+	fn newline(&mut self) -> Result<(), VdfErr> {
+		self.out.write_str(self.format_settings.line_ending.as_str())?;
+		Ok(())
 	}
 
-	fn write_key(&mut self, key: &str, is_block: bool) {
+	fn write_key(&mut self, key: &str, is_block: bool) -> Result<(), VdfErr> {
 		//Spaghetti lmao I'm sorry.
 
 		let top = &self.format_settings.toplevel_key_quote_rule;
@@ -247,83 +623,113 @@ impl VdfSerializer {
 			&self.format_settings.key_quote_rule
 		};
 
-		let quote_key =
-			*rule == KeyQuoteRule::Always || (*rule == KeyQuoteRule::NotBlocks && !is_block) || key.is_empty() || key.chars().any(|c| c.is_ascii_whitespace());
-
-		if quote_key {
-			self.out.push('"');
-			self.out.push_str(key);
-			self.out.push('"');
-		} else {
-			self.out.push_str(key);
-		}
+		let quote_key = *rule == KeyQuoteRule::Always || (*rule == KeyQuoteRule::NotBlocks && !is_block) || needs_quotes(key);
+		self.write_str(key, quote_key)
 	}
 
-	fn write_value(&mut self, value: &str, numeric: bool) {
+	fn write_value(&mut self, value: &str, numeric: bool) -> Result<(), VdfErr> {
 		let rule = &self.format_settings.value_quote_rule;
 
-		let quote_value = *rule == ValueQuoteRule::Always
-			|| (*rule == ValueQuoteRule::Nonnumeric && !numeric)
-			|| value.is_empty()
-			|| value.chars().any(|c| c.is_ascii_whitespace());
+		let quote_value = *rule == ValueQuoteRule::Always || (*rule == ValueQuoteRule::Nonnumeric && !numeric) || needs_quotes(value);
+		self.write_str(value, quote_value)
+	}
+
+	fn write_str(&mut self, s: &str, quoted: bool) -> Result<(), VdfErr> {
+		write_string(&mut self.out, s, quoted, self.format_settings.escapes)
+	}
+
+	/// Attach a conditional like `[$WIN32]` to the key-value pair that's currently being written.
+	pub(crate) fn set_conditional(&mut self, conditional: Option<&Conditional>) {
+		self.conditional = conditional.cloned();
+	}
+
+	fn write_conditional(&mut self) -> Result<(), VdfErr> {
+		if let Some(conditional) = self.conditional.take() {
+			write!(self.out, "{}{}", self.format_settings.inter_str, conditional)?;
+		}
+		Ok(())
+	}
 
-		if quote_value {
-			self.out.push('"');
-			self.out.push_str(value);
-			self.out.push('"');
+	/// Data-carrying variants are a block with one entry in it, named after the variant. Same as binary VDF.
+	/// At the top level of the file there's no key for the block, so the variant's name is just the first key.
+	fn begin_variant(&mut self, variant: &str) -> Result<(), VdfErr> {
+		if let State::WaitingForValue(_) = self.state {
+			self.begin_block()?;
 		} else {
-			self.out.push_str(value);
+			self.bare_variant = Some(self.indent_depth);
 		}
+		self.accept_str(variant, false)
 	}
 
-	fn begin_block(&mut self) {
+	fn end_variant(&mut self) -> Result<(), VdfErr> {
+		if self.bare_variant == Some(self.indent_depth) {
+			self.bare_variant = None;
+			Ok(())
+		} else {
+			self.end_block()
+		}
+	}
+
+	pub(crate) fn begin_block(&mut self) -> Result<(), VdfErr> {
 		self.state = match std::mem::take(&mut self.state) {
 			State::WaitingForValue(key) => {
-				self.indent();
-				self.write_key(&key, true);
+				self.indent()?;
+				self.write_key(&key, true)?;
+				self.write_conditional()?;
 
 				State::WaitingForKey
 			},
 			other => other, //unchanged
 		};
 
-		self.newline();
+		self.newline()?;
 		if self.format_settings.bump_braces {
 			self.increase_indent();
-			self.indent();
-			self.out.push('{');
+			self.indent()?;
+			self.out.write_char('{')?;
 		} else {
-			self.indent();
-			self.out.push('{');
+			self.indent()?;
+			self.out.write_char('{')?;
 			self.increase_indent();
 		}
 
-		self.newline();
+		self.newline()?;
+		if self.format_settings.align_values {
+			self.out.hold();
+		}
+		Ok(())
 	}
 
 	//call after writing the last value inside this block.
-	fn end_block(&mut self) {
+	pub(crate) fn end_block(&mut self) -> Result<(), VdfErr> {
+		if self.format_settings.align_values {
+			self.out.release()?;
+		}
+
 		if self.format_settings.bump_braces {
-			self.indent();
+			self.indent()?;
 			self.decrease_indent();
 		} else {
 			self.decrease_indent();
-			self.indent();
+			self.indent()?;
 		}
 
-		self.out.push('}');
-		self.newline();
+		self.out.write_char('}')?;
+		self.newline()
 	}
 
-	fn accept_str(&mut self, s: &str, numeric: bool) -> Result<(), VdfErr> {
+	pub(crate) fn accept_str(&mut self, s: &str, numeric: bool) -> Result<(), VdfErr> {
 		self.state = match std::mem::take(&mut self.state) {
 			State::WaitingForKey => State::WaitingForValue(s.to_string()),
 			State::WaitingForValue(key) => {
-				self.indent();
-				self.write_key(&key, false);
-				self.out.push_str(self.format_settings.inter_str);
-				self.write_value(s, numeric);
-				self.newline();
+				self.indent()?;
+				self.out.begin_key();
+				self.write_key(&key, false)?;
+				self.out.end_key();
+				self.out.write_str(&self.format_settings.inter_str)?;
+				self.write_value(s, numeric)?;
+				self.write_conditional()?;
+				self.newline()?;
 
 				State::WaitingForKey
 			},
@@ -336,6 +742,51 @@ impl VdfSerializer {
 	}
 }
 
+/// Whether a string is a plain decimal number, like `-12` or `0.25`: an optional sign, digits, and optionally a `.` and more digits.
+/// Unlike `str::parse::<f64>`, words like `inf` and `NaN` don't count.
+pub(crate) fn is_numeric(s: &str) -> bool {
+	let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+	let unsigned = s.strip_prefix(|c| c == '-' || c == '+').unwrap_or(s);
+	match unsigned.split_once('.') {
+		Some((whole, fraction)) => digits(whole) && digits(fraction),
+		None => digits(unsigned),
+	}
+}
+
+/// Strings that are empty, contain whitespace, or contain characters with special meaning must always be quoted.
+pub(crate) fn needs_quotes(s: &str) -> bool {
+	s.is_empty() || s.chars().any(|c| c.is_ascii_whitespace() || c == '"' || c == '{' || c == '}')
+}
+
+/// Writes a key or value, with double quotes around it if `quoted`, and escape sequences inside them if `escapes`.
+pub(crate) fn write_string<W>(out: &mut W, s: &str, quoted: bool, escapes: bool) -> Result<(), VdfErr>
+where
+	W: fmt::Write + ?Sized,
+{
+	if !quoted {
+		out.write_str(s)?;
+	} else if escapes {
+		out.write_char('"')?;
+		for c in s.chars() {
+			match c {
+				'"' => out.write_str("\\\"")?,
+				'\\' => out.write_str("\\\\")?,
+				'\n' => out.write_str("\\n")?,
+				'\t' => out.write_str("\\t")?,
+				_ => out.write_char(c)?,
+			}
+		}
+		out.write_char('"')?;
+	} else if s.contains('"') {
+		//There's no way to write this string
+		return Err(VdfErr::Unescapable(s.to_string()));
+	} else {
+		write!(out, "\"{}\"", s)?;
+	}
+
+	Ok(())
+}
+
 macro_rules! use_to_string {
 	( $func:ident | $type:ty | $numeric:literal) => {
 		fn $func(self, v: $type) -> Result<Self::Ok, Self::Error> {
@@ -344,17 +795,20 @@ macro_rules! use_to_string {
 	};
 }
 
-impl<'a> ser::Serializer for &'a mut VdfSerializer {
+impl<'a, W> ser::Serializer for &'a mut VdfSerializer<W>
+where
+	W: fmt::Write,
+{
 	type Ok = ();
 	type Error = VdfErr;
 
-	type SerializeSeq = VdfSeqSerializer<'a>;
-	type SerializeTuple = Impossible<(), Self::Error>;
-	type SerializeTupleStruct = Impossible<(), Self::Error>;
-	type SerializeTupleVariant = Impossible<(), Self::Error>;
+	type SerializeSeq = VdfSeqSerializer<'a, W>;
+	type SerializeTuple = VdfSeqSerializer<'a, W>;
+	type SerializeTupleStruct = VdfSeqSerializer<'a, W>;
+	type SerializeTupleVariant = VdfSeqSerializer<'a, W>;
 	type SerializeMap = Self;
 	type SerializeStruct = Self;
-	type SerializeStructVariant = Impossible<(), Self::Error>;
+	type SerializeStructVariant = Self;
 
 	fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
 		match self.format_settings.bool_format {
@@ -393,9 +847,9 @@ impl<'a> ser::Serializer for &'a mut VdfSerializer {
 		self.serialize_str("")
 	}
 
-	fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error>
+	fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
 	where
-		T: serde::Serialize,
+		T: ?Sized + serde::Serialize,
 	{
 		value.serialize(self)
 	}
@@ -412,32 +866,81 @@ impl<'a> ser::Serializer for &'a mut VdfSerializer {
 		self.serialize_str(variant)
 	}
 
-	fn serialize_newtype_struct<T: ?Sized>(self, name: &'static str, value: &T) -> Result<Self::Ok, Self::Error>
+	fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Self::Ok, Self::Error>
 	where
-		T: serde::Serialize,
+		T: ?Sized + serde::Serialize,
 	{
-		self.serialize_str(name)?; //TODO maybe make this an option (separate from the formatter options)
-		value.serialize(self)
+		if name == repeated::NAME {
+			self.repeated = true;
+			return value.serialize(self);
+		}
+
+		if let Some(brackets) = inline::brackets_for(name) {
+			let mut s = String::new();
+			value.serialize(InlineSerializer { out: &mut s, brackets: Some(brackets) })?;
+			return self.accept_str(&s, false);
+		}
+
+		match (&self.format_settings.newtype_format, &self.state) {
+			(NewtypeFormat::Transparent, _) => value.serialize(self),
+			//As the value of a pair, the name and contents go inside a block. Otherwise there'd be two keys in a row.
+			(NewtypeFormat::Named, State::WaitingForValue(_)) => {
+				self.begin_block()?;
+				self.serialize_str(name)?;
+				value.serialize(&mut *self)?;
+				self.end_block()
+			},
+			(NewtypeFormat::Named, _) => {
+				self.serialize_str(name)?;
+				value.serialize(self)
+			},
+		}
 	}
 
-	fn serialize_newtype_variant<T: ?Sized>(self, name: &'static str, variant_index: u32, variant: &'static str, value: &T) -> Result<Self::Ok, Self::Error>
+	fn serialize_newtype_variant<T>(self, name: &'static str, variant_index: u32, variant: &'static str, value: &T) -> Result<Self::Ok, Self::Error>
 	where
-		T: serde::Serialize,
+		T: ?Sized + serde::Serialize,
 	{
-		todo!("newtype variant")
+		self.begin_variant(variant)?;
+		value.serialize(&mut *self)?;
+		self.end_variant()
 	}
 
 	fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
-		self.begin_block();
-		Ok(VdfSeqSerializer { ser: self, index: 0 })
+		let format = if std::mem::take(&mut self.repeated) { SequenceFormat::Repeated } else { self.format_settings.sequence_format.clone() };
+
+		let mode = match (format, std::mem::take(&mut self.state)) {
+			(SequenceFormat::Repeated, State::WaitingForValue(key)) => SeqMode::Repeated(key),
+			(SequenceFormat::Inline, state @ State::WaitingForValue(_)) => {
+				self.state = state;
+				SeqMode::Inline(String::new())
+			},
+			//Nothing to repeat (or put inline) at the top level of the file, so fall back to a regular block.
+			(_, state) => {
+				self.state = state;
+				self.begin_block()?;
+				SeqMode::Indexed
+			},
+		};
+
+		Ok(VdfSeqSerializer { ser: self, index: 0, mode })
 	}
 
 	fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
-		todo!("tuple")
+		//Tuples are things like vectors and colors, which always go inline when they can
+		let mode = match self.state {
+			State::WaitingForValue(_) => SeqMode::Inline(String::new()),
+			_ => {
+				self.begin_block()?;
+				SeqMode::Indexed
+			},
+		};
+
+		Ok(VdfSeqSerializer { ser: self, index: 0, mode })
 	}
 
 	fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct, Self::Error> {
-		todo!("tuple struct")
+		self.serialize_tuple(len)
 	}
 
 	fn serialize_tuple_variant(
@@ -447,16 +950,17 @@ impl<'a> ser::Serializer for &'a mut VdfSerializer {
 		variant: &'static str,
 		len: usize,
 	) -> Result<Self::SerializeTupleVariant, Self::Error> {
-		todo!("tuple variant")
+		self.begin_variant(variant)?;
+		self.serialize_tuple(len)
 	}
 
 	fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
-		self.begin_block();
+		self.begin_block()?;
 		Ok(self)
 	}
 
 	fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct, Self::Error> {
-		self.begin_block();
+		self.begin_block()?;
 		Ok(self)
 	}
 
@@ -467,73 +971,328 @@ impl<'a> ser::Serializer for &'a mut VdfSerializer {
 		variant: &'static str,
 		len: usize,
 	) -> Result<Self::SerializeStructVariant, Self::Error> {
-		todo!("struct variant")
+		self.begin_variant(variant)?;
+		self.serialize_struct(name, len)
 	}
 }
 
-impl<'a> ser::SerializeStruct for &'a mut VdfSerializer {
+impl<W> ser::SerializeStruct for &mut VdfSerializer<W>
+where
+	W: fmt::Write,
+{
 	type Ok = ();
 	type Error = VdfErr;
 
-	fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+	fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
 	where
-		T: serde::Serialize,
+		T: ?Sized + serde::Serialize,
 	{
 		self.serialize_str(key)?;
 		value.serialize(&mut **self)
 	}
 
 	fn end(self) -> Result<Self::Ok, Self::Error> {
-		self.end_block();
-		Ok(())
+		self.end_block()
 	}
 }
 
-pub struct VdfSeqSerializer<'a> {
-	ser: &'a mut VdfSerializer,
+impl<W> ser::SerializeStructVariant for &mut VdfSerializer<W>
+where
+	W: fmt::Write,
+{
+	type Ok = ();
+	type Error = VdfErr;
+
+	fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+	where
+		T: ?Sized + serde::Serialize,
+	{
+		ser::SerializeStruct::serialize_field(self, key, value)
+	}
+
+	fn end(self) -> Result<Self::Ok, Self::Error> {
+		self.end_block()?;
+		self.end_variant()
+	}
+}
+
+pub struct VdfSeqSerializer<'a, W> {
+	ser: &'a mut VdfSerializer<W>,
 	index: u32,
+	mode: SeqMode,
+}
+
+/// The `SequenceFormat` a sequence is being written in, plus whatever's needed to write it.
+enum SeqMode {
+	Indexed,
+	/// Each element is written under this key.
+	Repeated(String),
+	/// Elements are collected into one string, written at the end.
+	Inline(String),
 }
 
-impl<'a> ser::SerializeSeq for VdfSeqSerializer<'a> {
+impl<'a, W> VdfSeqSerializer<'a, W>
+where
+	W: fmt::Write,
+{
+	fn element<T>(&mut self, value: &T) -> Result<(), VdfErr>
+	where
+		T: ?Sized + serde::Serialize,
+	{
+		self.index += 1;
+		match &mut self.mode {
+			SeqMode::Indexed => {
+				self.ser.serialize_u32(self.index - 1)?;
+				value.serialize(&mut *self.ser)
+			},
+			SeqMode::Repeated(key) => {
+				self.ser.state = State::WaitingForValue(key.clone());
+				value.serialize(&mut *self.ser)
+			},
+			SeqMode::Inline(joined) => {
+				if self.index > 1 {
+					joined.push(' ');
+				}
+				value.serialize(InlineSerializer::element(joined))
+			},
+		}
+	}
+
+	fn finish(self) -> Result<&'a mut VdfSerializer<W>, VdfErr> {
+		match self.mode {
+			SeqMode::Indexed => self.ser.end_block()?,
+			SeqMode::Repeated(_) => {},
+			SeqMode::Inline(joined) => {
+				let numeric = is_numeric(&joined);
+				self.ser.accept_str(&joined, numeric)?
+			},
+		}
+		Ok(self.ser)
+	}
+}
+
+impl<'a, W> ser::SerializeSeq for VdfSeqSerializer<'a, W>
+where
+	W: fmt::Write,
+{
 	type Ok = ();
 	type Error = VdfErr;
 
-	fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+	fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
 	where
-		T: serde::Serialize,
+		T: ?Sized + serde::Serialize,
 	{
-		self.ser.serialize_u32(self.index)?;
-		self.index += 1;
-		value.serialize(&mut *self.ser)
+		self.element(value)
 	}
 
 	fn end(self) -> Result<Self::Ok, Self::Error> {
-		self.ser.end_block();
-		Ok(())
+		self.finish().map(|_| ())
+	}
+}
+
+impl<'a, W> ser::SerializeTuple for VdfSeqSerializer<'a, W>
+where
+	W: fmt::Write,
+{
+	type Ok = ();
+	type Error = VdfErr;
+
+	fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+	where
+		T: ?Sized + serde::Serialize,
+	{
+		self.element(value)
+	}
+
+	fn end(self) -> Result<Self::Ok, Self::Error> {
+		self.finish().map(|_| ())
 	}
 }
 
-impl<'a> ser::SerializeMap for &'a mut VdfSerializer {
+impl<'a, W> ser::SerializeTupleStruct for VdfSeqSerializer<'a, W>
+where
+	W: fmt::Write,
+{
 	type Ok = ();
 	type Error = VdfErr;
 
-	fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<(), Self::Error>
+	fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
 	where
-		T: serde::Serialize,
+		T: ?Sized + serde::Serialize,
+	{
+		self.element(value)
+	}
+
+	fn end(self) -> Result<Self::Ok, Self::Error> {
+		self.finish().map(|_| ())
+	}
+}
+
+impl<'a, W> ser::SerializeTupleVariant for VdfSeqSerializer<'a, W>
+where
+	W: fmt::Write,
+{
+	type Ok = ();
+	type Error = VdfErr;
+
+	fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+	where
+		T: ?Sized + serde::Serialize,
+	{
+		self.element(value)
+	}
+
+	fn end(self) -> Result<Self::Ok, Self::Error> {
+		self.finish()?.end_variant()
+	}
+}
+
+impl<W> ser::SerializeMap for &mut VdfSerializer<W>
+where
+	W: fmt::Write,
+{
+	type Ok = ();
+	type Error = VdfErr;
+
+	fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
+	where
+		T: ?Sized + serde::Serialize,
 	{
 		key.serialize(&mut **self)
 	}
 
-	fn serialize_value<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+	fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
 	where
-		T: serde::Serialize,
+		T: ?Sized + serde::Serialize,
 	{
 		value.serialize(&mut **self)
 	}
 
 	fn end(self) -> Result<Self::Ok, Self::Error> {
-		self.end_block();
-		Ok(())
+		self.end_block()
+	}
+}
+
+/// Turns a scalar into a string, the same way `VdfSerializer` would write it. Used for things like map keys, where only strings,
+/// chars, numbers and unit variants make sense.
+pub(crate) struct ScalarSerializer;
+
+macro_rules! scalar_to_string {
+	( $func:ident | $type:ty ) => {
+		fn $func(self, v: $type) -> Result<Self::Ok, Self::Error> {
+			Ok(v.to_string())
+		}
+	};
+}
+
+impl ser::Serializer for ScalarSerializer {
+	type Ok = String;
+	type Error = VdfErr;
+
+	type SerializeSeq = Impossible<String, VdfErr>;
+	type SerializeTuple = Impossible<String, VdfErr>;
+	type SerializeTupleStruct = Impossible<String, VdfErr>;
+	type SerializeTupleVariant = Impossible<String, VdfErr>;
+	type SerializeMap = Impossible<String, VdfErr>;
+	type SerializeStruct = Impossible<String, VdfErr>;
+	type SerializeStructVariant = Impossible<String, VdfErr>;
+
+	fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+		Ok(if v { "1" } else { "0" }.to_string())
+	}
+
+	scalar_to_string!(serialize_i8 | i8);
+	scalar_to_string!(serialize_i16 | i16);
+	scalar_to_string!(serialize_i32 | i32);
+	scalar_to_string!(serialize_i64 | i64);
+	scalar_to_string!(serialize_u8 | u8);
+	scalar_to_string!(serialize_u16 | u16);
+	scalar_to_string!(serialize_u32 | u32);
+	scalar_to_string!(serialize_u64 | u64);
+	scalar_to_string!(serialize_f32 | f32);
+	scalar_to_string!(serialize_f64 | f64);
+	scalar_to_string!(serialize_char | char);
+	scalar_to_string!(serialize_str | &str);
+
+	fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+		std::str::from_utf8(v).map(str::to_string).map_err(VdfErr::Utf8)
+	}
+
+	fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+		Ok(String::new())
+	}
+
+	fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+	where
+		T: ?Sized + serde::Serialize,
+	{
+		value.serialize(self)
+	}
+
+	fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+		Ok(String::new())
+	}
+
+	fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Self::Error> {
+		Ok(String::new())
+	}
+
+	fn serialize_unit_variant(self, name: &'static str, variant_index: u32, variant: &'static str) -> Result<Self::Ok, Self::Error> {
+		Ok(variant.to_string())
+	}
+
+	fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Self::Ok, Self::Error>
+	where
+		T: ?Sized + serde::Serialize,
+	{
+		value.serialize(self)
+	}
+
+	fn serialize_newtype_variant<T>(self, name: &'static str, variant_index: u32, variant: &'static str, value: &T) -> Result<Self::Ok, Self::Error>
+	where
+		T: ?Sized + serde::Serialize,
+	{
+		Err(VdfErr::ExpectedString)
+	}
+
+	fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+		Err(VdfErr::ExpectedString)
+	}
+
+	fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+		Err(VdfErr::ExpectedString)
+	}
+
+	fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct, Self::Error> {
+		Err(VdfErr::ExpectedString)
+	}
+
+	fn serialize_tuple_variant(
+		self,
+		name: &'static str,
+		variant_index: u32,
+		variant: &'static str,
+		len: usize,
+	) -> Result<Self::SerializeTupleVariant, Self::Error> {
+		Err(VdfErr::ExpectedString)
+	}
+
+	fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+		Err(VdfErr::ExpectedString)
+	}
+
+	fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct, Self::Error> {
+		Err(VdfErr::ExpectedString)
+	}
+
+	fn serialize_struct_variant(
+		self,
+		name: &'static str,
+		variant_index: u32,
+		variant: &'static str,
+		len: usize,
+	) -> Result<Self::SerializeStructVariant, Self::Error> {
+		Err(VdfErr::ExpectedString)
 	}
 }
 
@@ -654,4 +1413,91 @@ mod test {
 
 		println!("{}", to_string(&hey).unwrap());
 	}
+
+	#[test]
+	fn to_writer_matches_to_string() {
+		let mut map = HashMap::new();
+		map.insert("key", vec!["a", "b"]);
+
+		let mut bytes = Vec::new();
+		to_writer_with_toplevel_block(&mut bytes, &map, "Top").unwrap();
+		assert_eq!(String::from_utf8(bytes).unwrap(), to_string_with_toplevel_block(&map, "Top").unwrap());
+
+		//A writer that runs out of space partway through
+		let mut buf = [0u8; 8];
+		match to_writer(&mut buf[..], &map) {
+			Err(VdfErr::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::WriteZero),
+			other => panic!("expected an IO error, got {:?}", other),
+		}
+	}
+
+	#[test]
+	fn custom_settings() {
+		#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+		struct Editor {
+			#[serde(rename = "Type")]
+			ty: String,
+			#[serde(rename = "MovementHandle")]
+			movement_handle: String,
+			#[serde(rename = "InvertSkin")]
+			invert_skin: bool,
+			#[serde(rename = "Model")]
+			model: Model,
+		}
+
+		#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+		struct Model {
+			#[serde(rename = "ModelName")]
+			model_name: String,
+		}
+
+		let editor =
+			Editor { ty: "ITEM_CUBE".into(), movement_handle: "HANDLE_4_DIRECTIONS".into(), invert_skin: true, model: Model { model_name: "cube.mdl".into() } };
+
+		let settings = FormatSettings::p2c_like()
+			.with_key_quote_rule(KeyQuoteRule::Never)
+			.with_value_quote_rule(ValueQuoteRule::Nonnumeric)
+			.with_bool_format(BoolFormat::Word)
+			.with_indent("  ")
+			.with_separator(" ")
+			.with_aligned_values(true)
+			.with_line_ending(LineEnding::CrLf);
+
+		let mut ser = VdfSerializer::with_settings(settings.clone());
+		editor.serialize(&mut ser).unwrap();
+		let text = ser.into_inner();
+		assert_eq!(
+			text,
+			"\r\n{\r\n  Type           \"ITEM_CUBE\"\r\n  MovementHandle \"HANDLE_4_DIRECTIONS\"\r\n  InvertSkin     \"true\"\r\n  Model\r\n  {\r\n    ModelName \"cube.mdl\"\r\n  }\r\n}\r\n"
+		);
+		assert_eq!(crate::de::from_str_with_settings::<Editor>(&text, settings).unwrap(), editor);
+
+		//Streaming still works, even if the output has to wait for each block to end
+		let mut bytes = Vec::new();
+		let mut ser = VdfSerializer::with_writer(IoWriter::new(&mut bytes), FormatSettings::vmf_like().with_aligned_values(true));
+		editor.serialize(&mut ser).unwrap();
+		assert!(String::from_utf8(bytes).unwrap().contains("\"Type\"           \"ITEM_CUBE\"\n"));
+	}
+
+	#[test]
+	fn inline_numeric() {
+		#[derive(serde::Serialize)]
+		struct Numbers {
+			single: (f32,),
+			limit: (&'static str,),
+			words: Vec<&'static str>,
+		}
+
+		let settings = FormatSettings::gameinfo_like().with_sequence_format(SequenceFormat::Inline);
+		let numbers = Numbers { single: (0.5,), limit: ("inf",), words: vec!["NaN"] };
+		let mut ser = VdfSerializer::with_settings(settings);
+		numbers.serialize(&mut ser).unwrap();
+		assert_eq!(ser.into_inner(), "\n{\n\tsingle\t0.5\n\tlimit\t\"inf\"\n\twords\t\"NaN\"\n}\n");
+	}
+
+	#[test]
+	#[should_panic]
+	fn non_whitespace_separator() {
+		let _ = FormatSettings::p2c_like().with_separator(" = ");
+	}
 }
@@ -0,0 +1,33 @@
+//! Little helpers for the typed models in `vdf::vmf` and `vdf::p2c`, which pack numbers into strings a lot.
+
+use std::convert::TryInto;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::VdfErr;
+use crate::value::Object;
+
+/// Parses every space-separated word of a value. `key` and `expected` are for the error message.
+pub(crate) fn parse_all<T>(key: &str, value: &str, expected: &'static str) -> Result<Vec<T>, VdfErr>
+where
+	T: FromStr,
+{
+	value.split_whitespace().map(|s| s.parse().map_err(|_| VdfErr::InvalidValue { key: key.to_string(), value: value.to_string(), expected })).collect()
+}
+
+/// Parses the value of the first pair with the given key as exactly `N` space-separated words, like `"0 180 0"`.
+pub(crate) fn parse_array<T, const N: usize>(object: &Object, key: &str, expected: &'static str) -> Result<[T; N], VdfErr>
+where
+	T: FromStr,
+{
+	let value = object.require_str(key)?;
+	parse_all(key, value, expected)?.try_into().map_err(|_| VdfErr::InvalidValue { key: key.to_string(), value: value.to_string(), expected })
+}
+
+/// Values separated by spaces.
+pub(crate) fn join<T>(values: &[T]) -> String
+where
+	T: fmt::Display,
+{
+	values.iter().map(T::to_string).collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,611 @@
+//! An owned, order-preserving VDF document tree, for when you don't know (or don't care about) the structure of a file ahead of time.
+//!
+//! Valve's files repeat keys all the time, so an `Object` is a list of key-value pairs rather than a map. Lookups by key are linear.
+
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::Index;
+use std::ops::IndexMut;
+use std::str::FromStr;
+
+use serde::de;
+use serde::ser::SerializeMap;
+use serde::Deserialize;
+use serde::Serialize;
+use serde::Serializer;
+
+use crate::conditional::Conditional;
+use crate::conditional::Symbols;
+use crate::de::VdfDeserializer;
+use crate::error::VdfErr;
+use crate::query::Query;
+use crate::ser::is_numeric;
+use crate::ser::FormatSettings;
+use crate::ser::VdfSerializer;
+
+/// A block of key-value pairs, in the order they appeared in the file. Keys may be repeated.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Object {
+	items: Vec<Kv>,
+}
+
+/// One key-value pair in an `Object`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Kv {
+	pub key: String,
+	pub value: Value,
+	/// A platform conditional like `[$WIN32]` attached to this pair, if there is one.
+	pub condition: Option<Conditional>,
+}
+
+/// A value in a VDF file, which is either a string or a nested block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+	Str(String),
+	Obj(Object),
+}
+
+impl Object {
+	pub fn new() -> Self {
+		Object { items: Vec::new() }
+	}
+
+	/// The number of key-value pairs in this block, counting repeated keys separately.
+	pub fn len(&self) -> usize {
+		self.items.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.items.is_empty()
+	}
+
+	pub fn iter(&self) -> std::slice::Iter<'_, Kv> {
+		self.items.iter()
+	}
+
+	pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Kv> {
+		self.items.iter_mut()
+	}
+
+	/// Appends a key-value pair to the end of the block, even if the key is already present.
+	pub fn push<K, V>(&mut self, key: K, value: V)
+	where
+		K: Into<String>,
+		V: Into<Value>,
+	{
+		self.items.push(Kv::new(key, value));
+	}
+
+	/// Returns the value of the first pair with the given key.
+	pub fn get(&self, key: &str) -> Option<&Value> {
+		self.items.iter().find(|kv| kv.key == key).map(|kv| &kv.value)
+	}
+
+	/// Returns the value of the first pair with the given key.
+	pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+		self.items.iter_mut().find(|kv| kv.key == key).map(|kv| &mut kv.value)
+	}
+
+	/// Returns the values of every pair with the given key, in order.
+	pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a Value> + 'a {
+		self.items.iter().filter(move |kv| kv.key == key).map(|kv| &kv.value)
+	}
+
+	/// Returns `true` if at least one pair has the given key.
+	pub fn contains_key(&self, key: &str) -> bool {
+		self.items.iter().any(|kv| kv.key == key)
+	}
+
+	/// If a pair with the given key exists, replaces the value of the first one and returns the old value.
+	/// Otherwise, appends a new pair to the end of the block.
+	pub fn insert<K, V>(&mut self, key: K, value: V) -> Option<Value>
+	where
+		K: Into<String>,
+		V: Into<Value>,
+	{
+		let key = key.into();
+		match self.get_mut(&key) {
+			Some(existing) => Some(std::mem::replace(existing, value.into())),
+			None => {
+				self.items.push(Kv::new(key, value));
+				None
+			},
+		}
+	}
+
+	/// Removes every pair with the given key, returning their values in order.
+	pub fn remove_all(&mut self, key: &str) -> Vec<Value> {
+		let (removed, kept) = std::mem::take(&mut self.items).into_iter().partition(|kv| kv.key == key);
+		self.items = kept;
+		removed.into_iter().map(|kv| kv.value).collect()
+	}
+
+	/// Keeps only the pairs for which the predicate returns `true`, preserving their order.
+	pub fn retain<F>(&mut self, f: F)
+	where
+		F: FnMut(&Kv) -> bool,
+	{
+		self.items.retain(f);
+	}
+
+	/// Removes every pair (including pairs in nested blocks) with a conditional that's false for the given symbols.
+	/// The conditionals of the pairs that are left are removed, too.
+	pub fn evaluate_conditionals(&mut self, symbols: &Symbols) {
+		self.items.retain(|kv| kv.condition.as_ref().map_or(true, |c| c.is_true(symbols)));
+		for kv in &mut self.items {
+			kv.condition = None;
+			if let Value::Obj(o) = &mut kv.value {
+				o.evaluate_conditionals(symbols);
+			}
+		}
+	}
+
+	/// The string value of the first pair with the given key, or a `MissingKey` error. For typed models like `vdf::vmf`.
+	pub(crate) fn require_str(&self, key: &str) -> Result<&str, VdfErr> {
+		self.get(key).and_then(Value::as_str).ok_or_else(|| VdfErr::MissingKey(key.to_string()))
+	}
+
+	/// The block value of the first pair with the given key, or a `MissingKey` error.
+	pub(crate) fn require_obj(&self, key: &str) -> Result<&Object, VdfErr> {
+		self.get(key).and_then(Value::as_obj).ok_or_else(|| VdfErr::MissingKey(key.to_string()))
+	}
+
+	/// Parses the string value of the first pair with the given key. `expected` describes it for the error message.
+	pub(crate) fn parse_str<T>(&self, key: &str, expected: &'static str) -> Result<T, VdfErr>
+	where
+		T: FromStr,
+	{
+		let value = self.require_str(key)?;
+		value.parse().map_err(|_| VdfErr::InvalidValue { key: key.to_string(), value: value.to_string(), expected })
+	}
+
+	/// Every pair the `vdf::query` path leads to, in order.
+	pub fn select(&self, query: &str) -> Result<Vec<&Kv>, VdfErr> {
+		Ok(Query::new(query)?.select(self))
+	}
+
+	/// Every pair the `vdf::query` path leads to, in order, for editing.
+	pub fn select_mut(&mut self, query: &str) -> Result<Vec<&mut Kv>, VdfErr> {
+		Ok(Query::new(query)?.select_mut(self))
+	}
+
+	/// Write this object as a VDF document. The pairs are written at the top level of the file, without curly braces around them.
+	pub fn to_string(&self, settings: &FormatSettings) -> Result<String, VdfErr> {
+		let mut ser = VdfSerializer::with_settings(settings.clone());
+		self.write_items(&mut ser)?;
+		Ok(ser.into_inner())
+	}
+
+	fn write_items<W>(&self, ser: &mut VdfSerializer<W>) -> Result<(), VdfErr>
+	where
+		W: fmt::Write,
+	{
+		for kv in &self.items {
+			ser.serialize_str(&kv.key)?;
+			ser.set_conditional(kv.condition.as_ref());
+			match &kv.value {
+				//Strings that look like numbers are written like numbers, so `ValueQuoteRule::Nonnumeric` works
+				Value::Str(s) => ser.accept_str(s, is_numeric(s))?,
+				Value::Obj(o) => {
+					ser.begin_block()?;
+					o.write_items(ser)?;
+					ser.end_block()?;
+				},
+			}
+		}
+		Ok(())
+	}
+}
+
+impl FromStr for Object {
+	type Err = VdfErr;
+
+	/// Parse a VDF document. The top level of the file may or may not be surrounded with curly braces.
+	///
+	/// Conditionals are kept, but not evaluated. Use `VdfDeserializer::parse_object` for more control.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		VdfDeserializer::new(s).parse_object()
+	}
+}
+
+impl Kv {
+	pub fn new<K, V>(key: K, value: V) -> Self
+	where
+		K: Into<String>,
+		V: Into<Value>,
+	{
+		Kv { key: key.into(), value: value.into(), condition: None }
+	}
+}
+
+impl Value {
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			Value::Str(s) => Some(s),
+			Value::Obj(_) => None,
+		}
+	}
+
+	pub fn as_obj(&self) -> Option<&Object> {
+		match self {
+			Value::Str(_) => None,
+			Value::Obj(o) => Some(o),
+		}
+	}
+
+	pub fn as_obj_mut(&mut self) -> Option<&mut Object> {
+		match self {
+			Value::Str(_) => None,
+			Value::Obj(o) => Some(o),
+		}
+	}
+
+	pub fn is_str(&self) -> bool {
+		matches!(self, Value::Str(_))
+	}
+
+	pub fn is_obj(&self) -> bool {
+		matches!(self, Value::Obj(_))
+	}
+
+	/// If this is a block, returns the value of the first pair with the given key.
+	pub fn get(&self, key: &str) -> Option<&Value> {
+		self.as_obj().and_then(|o| o.get(key))
+	}
+
+	/// If this is a block, returns the value of the first pair with the given key.
+	pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+		self.as_obj_mut().and_then(|o| o.get_mut(key))
+	}
+}
+
+impl From<&str> for Value {
+	fn from(s: &str) -> Self {
+		Value::Str(s.to_string())
+	}
+}
+
+impl From<String> for Value {
+	fn from(s: String) -> Self {
+		Value::Str(s)
+	}
+}
+
+impl From<Object> for Value {
+	fn from(o: Object) -> Self {
+		Value::Obj(o)
+	}
+}
+
+impl From<Vec<Kv>> for Object {
+	fn from(vec: Vec<Kv>) -> Self {
+		Object { items: vec }
+	}
+}
+
+impl From<Kv> for Object {
+	fn from(k: Kv) -> Self {
+		Object { items: vec![k] }
+	}
+}
+
+impl<K, V> FromIterator<(K, V)> for Object
+where
+	K: Into<String>,
+	V: Into<Value>,
+{
+	fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+		Object { items: iter.into_iter().map(|(k, v)| Kv::new(k, v)).collect() }
+	}
+}
+
+impl Extend<Kv> for Object {
+	fn extend<I: IntoIterator<Item = Kv>>(&mut self, iter: I) {
+		self.items.extend(iter);
+	}
+}
+
+impl IntoIterator for Object {
+	type Item = Kv;
+	type IntoIter = std::vec::IntoIter<Kv>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.items.into_iter()
+	}
+}
+
+impl<'a> IntoIterator for &'a Object {
+	type Item = &'a Kv;
+	type IntoIter = std::slice::Iter<'a, Kv>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.items.iter()
+	}
+}
+
+/// Indexing by key returns the value of the first pair with that key.
+///
+/// # Panics
+///
+/// Panics if there's no pair with that key. Use `get` if you're not sure.
+impl Index<&str> for Object {
+	type Output = Value;
+
+	fn index(&self, key: &str) -> &Value {
+		self.get(key).unwrap_or_else(|| panic!("no key {:?} in object", key))
+	}
+}
+
+impl IndexMut<&str> for Object {
+	fn index_mut(&mut self, key: &str) -> &mut Value {
+		self.get_mut(key).unwrap_or_else(|| panic!("no key {:?} in object", key))
+	}
+}
+
+/// # Panics
+///
+/// Panics if this value is a string, or if there's no pair with that key.
+impl Index<&str> for Value {
+	type Output = Value;
+
+	fn index(&self, key: &str) -> &Value {
+		match self {
+			Value::Obj(o) => &o[key],
+			Value::Str(_) => panic!("tried to index a string value with key {:?}", key),
+		}
+	}
+}
+
+impl IndexMut<&str> for Value {
+	fn index_mut(&mut self, key: &str) -> &mut Value {
+		match self {
+			Value::Obj(o) => &mut o[key],
+			Value::Str(_) => panic!("tried to index a string value with key {:?}", key),
+		}
+	}
+}
+
+impl Serialize for Object {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		let mut map = serializer.serialize_map(Some(self.items.len()))?;
+		for kv in &self.items {
+			map.serialize_entry(&kv.key, &kv.value)?;
+		}
+		map.end()
+	}
+}
+
+impl Serialize for Value {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		match self {
+			Value::Str(s) => serializer.serialize_str(s),
+			Value::Obj(o) => o.serialize(serializer),
+		}
+	}
+}
+
+impl<'de> Deserialize<'de> for Object {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: de::Deserializer<'de>,
+	{
+		deserializer.deserialize_map(ObjectVisitor)
+	}
+}
+
+impl<'de> Deserialize<'de> for Value {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: de::Deserializer<'de>,
+	{
+		deserializer.deserialize_any(ValueVisitor)
+	}
+}
+
+struct ObjectVisitor;
+
+impl<'de> de::Visitor<'de> for ObjectVisitor {
+	type Value = Object;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("a block of key-value pairs")
+	}
+
+	fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+	where
+		A: de::MapAccess<'de>,
+	{
+		let mut items = Vec::with_capacity(map.size_hint().unwrap_or(0));
+		while let Some((key, value)) = map.next_entry::<String, Value>()? {
+			items.push(Kv { key, value, condition: None });
+		}
+		Ok(Object { items })
+	}
+}
+
+pub(crate) struct ValueVisitor;
+
+impl<'de> de::Visitor<'de> for ValueVisitor {
+	type Value = Value;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("a string or a block of key-value pairs")
+	}
+
+	fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		Ok(Value::Str(v.to_string()))
+	}
+
+	fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		Ok(Value::Str(v))
+	}
+
+	//Other formats have more types than VDF does; they're stored as strings, the same way the VDF serializer would write them.
+
+	fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		Ok(Value::Str(if v { "1" } else { "0" }.to_string()))
+	}
+
+	fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		Ok(Value::Str(v.to_string()))
+	}
+
+	fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		Ok(Value::Str(v.to_string()))
+	}
+
+	fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		Ok(Value::Str(v.to_string()))
+	}
+
+	fn visit_unit<E>(self) -> Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		Ok(Value::Str(String::new()))
+	}
+
+	fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+	where
+		A: de::MapAccess<'de>,
+	{
+		ObjectVisitor.visit_map(map).map(Value::Obj)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	const GAMEINFO: &str = r#""GameInfo"
+{
+	game	"Portal 2"
+	FileSystem
+	{
+		SteamAppId	620
+		SearchPaths
+		{
+			Game	update
+			Game	portal2_dlc2
+			Game	portal2_dlc1
+		}
+	}
+}
+"#;
+
+	#[test]
+	fn parse_and_index() {
+		let doc: Object = GAMEINFO.parse().unwrap();
+		assert_eq!(doc.len(), 1);
+		assert_eq!(doc["GameInfo"]["game"].as_str(), Some("Portal 2"));
+		assert_eq!(doc["GameInfo"]["FileSystem"]["SteamAppId"].as_str(), Some("620"));
+
+		let paths: Vec<_> = doc["GameInfo"]["FileSystem"]["SearchPaths"].as_obj().unwrap().get_all("Game").filter_map(Value::as_str).collect();
+		assert_eq!(paths, vec!["update", "portal2_dlc2", "portal2_dlc1"]);
+	}
+
+	#[test]
+	fn round_trip() {
+		let p2c = "\"portal2_puzzle\"\n{\n\t\"AppID\"\t\"644\"\n\t\"Voxels\"\n\t{\n\t\t\"Voxel\"\t\"a\"\n\t\t\"Voxel\"\t\"b\"\n\t}\n}\n";
+		assert_eq!(p2c.parse::<Object>().unwrap().to_string(&FormatSettings::p2c_like()).unwrap(), p2c);
+
+		let doc: Object = GAMEINFO.parse().unwrap();
+		for settings in &[FormatSettings::p2c_like(), FormatSettings::vmf_like(), FormatSettings::beemod_like(), FormatSettings::gameinfo_like()] {
+			let written = doc.to_string(settings).unwrap();
+			assert_eq!(written.parse::<Object>().unwrap(), doc);
+		}
+	}
+
+	#[test]
+	fn numeric_values() {
+		let doc: Object = "a 1 b -2.5 c inf d NaN e infinity f 1e5 g 1.".parse().unwrap();
+		assert_eq!(
+			doc.to_string(&FormatSettings::gameinfo_like()).unwrap(),
+			"\"a\"\t1\n\"b\"\t-2.5\n\"c\"\t\"inf\"\n\"d\"\t\"NaN\"\n\"e\"\t\"infinity\"\n\"f\"\t\"1e5\"\n\"g\"\t\"1.\"\n"
+		);
+	}
+
+	#[test]
+	fn edit() {
+		let mut doc = Object::new();
+		doc.push("Item", Object::from_iter(vec![("Type", "ITEM_BUTTON_PEDESTAL")]));
+		doc.push("Item", Object::from_iter(vec![("Type", "ITEM_BUTTON_FLOOR")]));
+		assert_eq!(doc.get_all("Item").count(), 2);
+
+		doc["Item"]["Type"] = "ITEM_CUBE".into();
+		assert_eq!(doc.get_all("Item").map(|i| i["Type"].as_str().unwrap()).collect::<Vec<_>>(), vec!["ITEM_CUBE", "ITEM_BUTTON_FLOOR"]);
+
+		assert_eq!(doc.insert("Name", "thing"), None);
+		assert_eq!(doc.insert("Name", "other thing"), Some(Value::from("thing")));
+		assert_eq!(doc.remove_all("Item").len(), 2);
+		assert_eq!(doc, Object::from_iter(vec![("Name", "other thing")]));
+	}
+
+	#[test]
+	fn conditionals() {
+		let input = r#""Resource"
+{
+	"font"	"Verdana"	[$WIN32]
+	"font"	"Helvetica"	[$OSX]
+	"Console"	[!$X360]
+	{
+		"wide"	"640"
+	}
+}
+"#;
+
+		let doc: Object = input.parse().unwrap();
+		let fonts: Vec<_> = doc["Resource"].as_obj().unwrap().iter().filter(|kv| kv.key == "font").map(|kv| kv.condition.as_ref().unwrap().as_str()).collect();
+		assert_eq!(fonts, vec!["$WIN32", "$OSX"]);
+		assert_eq!(doc.to_string(&FormatSettings::p2c_like()).unwrap(), input);
+
+		let osx: Symbols = vec!["$OSX"].into_iter().collect();
+		let filtered = VdfDeserializer::new(input).evaluate_conditionals(osx.clone()).parse_object().unwrap();
+		assert_eq!(filtered["Resource"].as_obj().unwrap().get_all("font").collect::<Vec<_>>(), vec![&Value::from("Helvetica")]);
+		assert_eq!(filtered["Resource"]["Console"]["wide"].as_str(), Some("640"));
+
+		let x360: Symbols = vec!["$X360"].into_iter().collect();
+		let mut evaluated = doc.clone();
+		evaluated.evaluate_conditionals(&x360);
+		assert_eq!(evaluated["Resource"].as_obj().unwrap().len(), 0);
+	}
+
+	#[test]
+	fn as_serde_target() {
+		#[derive(Deserialize)]
+		struct Partial {
+			game: String,
+			#[serde(rename = "FileSystem")]
+			filesystem: Object,
+		}
+
+		let partial: Partial = crate::de::from_str_with_toplevel_block(GAMEINFO, "GameInfo").unwrap();
+		assert_eq!(partial.game, "Portal 2");
+		assert_eq!(partial.filesystem.get_all("SteamAppId").count(), 1);
+		assert_eq!(crate::ser::to_string(&partial.filesystem).unwrap().parse::<Object>().unwrap(), partial.filesystem);
+	}
+}
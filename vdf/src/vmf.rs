@@ -0,0 +1,724 @@
+//! A typed model of Hammer `.vmf` map files.
+//!
+//! A VMF is a plain VDF file, so it could be read with serde structs, but entities can have any keys at all and the interesting
+//! bits (planes, texture axes, displacements) are numbers packed into strings. This goes through `vdf::value::Object` instead:
+//! the parts with a fixed shape are parsed into numbers, and everything else is kept as an `Object` so it comes back out.
+//!
+//! Reading and writing a file Hammer saved gives the same text back, as long as nobody rewrote the numbers in a funny way
+//! (`0.250` comes back as `0.25`). Keys this doesn't know about survive too, but they're written after the ones it does know.
+//!
+//! ```
+//! # use vdf::vmf::*;
+//! let mut vmf = Vmf::default();
+//! let mut entity = Entity::new(2, "prop_button");
+//! entity.set("targetname", "button");
+//! entity.connections.get_or_insert_with(Connections::default).push(Connection::new("OnPressed", "door", "Open"));
+//! vmf.entities.push(entity);
+//!
+//! let vmf: Vmf = vmf.to_string().unwrap().parse().unwrap();
+//! let button = vmf.entities_of_class("prop_button").next().unwrap();
+//! assert_eq!(button.connections.as_ref().unwrap()[0].target, "door");
+//! ```
+
+use std::convert::TryInto;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::de::VdfDeserializer;
+use crate::error::VdfErr;
+use crate::ser::FormatSettings;
+use crate::ser::LineEnding;
+use crate::util::join;
+use crate::util::parse_all;
+use crate::util::parse_array;
+use crate::value::Kv;
+use crate::value::Object;
+use crate::value::Value;
+
+/// A whole map.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Vmf {
+	/// The `versioninfo` block.
+	pub version_info: Option<Object>,
+	/// The `visgroups` block. Hammer always writes one, even if it's empty.
+	pub visgroups: Option<Vec<VisGroup>>,
+	/// The `viewsettings` block.
+	pub view_settings: Option<Object>,
+	pub world: World,
+	/// Every entity besides the world, including hidden ones.
+	pub entities: Vec<Entity>,
+	/// Anything else at the top of the file (`cameras`, `cordon`...), in order. Written after the entities.
+	pub extra: Object,
+}
+
+/// A visgroup, from the `visgroups` block. Things say which visgroups they're in with `visgroupid` keys in their `editor` block.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VisGroup {
+	pub name: String,
+	pub id: u32,
+	pub color: [u8; 3],
+	/// Visgroups can be nested.
+	pub children: Vec<VisGroup>,
+	/// Anything else, in order. Written after the children.
+	pub extra: Object,
+}
+
+/// The `world` block: `worldspawn`'s keyvalues, and all the brushes that aren't part of an entity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct World {
+	pub id: u32,
+	/// All the string keyvalues besides `id`, like `classname` and `skyname`, in order.
+	pub properties: Object,
+	pub solids: Vec<Solid>,
+	/// Any other blocks, like `group`s, in order. Written after the solids.
+	pub extra: Object,
+}
+
+/// A point or brush entity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entity {
+	pub id: u32,
+	/// All the string keyvalues besides `id`, like `classname`, `targetname` and `origin`, in order.
+	pub properties: Object,
+	/// The `connections` block. Hammer only writes one if there are any.
+	pub connections: Option<Connections>,
+	/// The brushes of a brush entity.
+	pub solids: Vec<Solid>,
+	/// Any other blocks, in order. Written after the solids.
+	pub extra: Object,
+	/// The `editor` block, with the color, visgroups and so on.
+	pub editor: Option<Object>,
+	/// Whether it's hidden in Hammer, which is saved as a `hidden` block around it.
+	pub hidden: bool,
+}
+
+/// The outputs of an entity.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Connections(pub Vec<Connection>);
+
+/// One output, like `"OnPressed" "door,Open,,0,-1"`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Connection {
+	pub output: String,
+	pub target: String,
+	pub input: String,
+	pub parameter: String,
+	pub delay: f64,
+	/// `-1` is forever.
+	pub times_to_fire: i32,
+	/// Older versions of Hammer separate the parts with commas, newer ones with the ESC character (`\x1b`).
+	pub separator: char,
+}
+
+/// A brush.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solid {
+	pub id: u32,
+	pub sides: Vec<Side>,
+	/// Any other blocks, in order. Written after the sides.
+	pub extra: Object,
+	pub editor: Option<Object>,
+	/// Whether it's hidden in Hammer, which is saved as a `hidden` block around it.
+	pub hidden: bool,
+}
+
+/// One face of a brush.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Side {
+	pub id: u32,
+	/// Three points on the plane, clockwise when looking at the front of the face.
+	pub plane: [[f64; 3]; 3],
+	pub material: String,
+	pub uaxis: TextureAxis,
+	pub vaxis: TextureAxis,
+	pub rotation: f64,
+	pub lightmap_scale: u32,
+	/// A bit field.
+	pub smoothing_groups: u32,
+	/// Anything else, in order. Written before the displacement.
+	pub extra: Object,
+	pub dispinfo: Option<DispInfo>,
+}
+
+/// How a texture is lined up along one direction, like `"[1 0 0 0] 0.25"`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureAxis {
+	pub axis: [f64; 3],
+	pub shift: f64,
+	pub scale: f64,
+}
+
+/// A displacement on a side. Rows are `2^power + 1` vertices long, and there are that many of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DispInfo {
+	pub power: u32,
+	pub start_position: [f64; 3],
+	pub flags: u32,
+	pub elevation: f64,
+	pub subdiv: bool,
+	pub normals: Vec<Vec<[f64; 3]>>,
+	pub distances: Vec<Vec<f64>>,
+	pub offsets: Vec<Vec<[f64; 3]>>,
+	pub offset_normals: Vec<Vec<[f64; 3]>>,
+	pub alphas: Vec<Vec<f64>>,
+	/// One row shorter than the others, and two tags per quad.
+	pub triangle_tags: Vec<Vec<u32>>,
+	pub allowed_verts: Vec<i32>,
+	/// Anything else, in order. Written at the end.
+	pub extra: Object,
+}
+
+impl Vmf {
+	pub fn from_object(object: &Object) -> Result<Self, VdfErr> {
+		let mut vmf = Vmf::default();
+		let mut world = None;
+		for kv in object {
+			match (kv.key.as_str(), &kv.value) {
+				("versioninfo", Value::Obj(o)) => vmf.version_info = Some(o.clone()),
+				("visgroups", Value::Obj(o)) => vmf.visgroups = Some(VisGroup::list(o)?),
+				("viewsettings", Value::Obj(o)) => vmf.view_settings = Some(o.clone()),
+				("world", Value::Obj(o)) => world = Some(World::from_object(o)?),
+				("entity", Value::Obj(o)) => vmf.entities.push(Entity::from_object(o, false)?),
+				("hidden", Value::Obj(o)) if is_hidden("entity", o) => {
+					for hidden in o.get_all("entity").filter_map(Value::as_obj) {
+						vmf.entities.push(Entity::from_object(hidden, true)?);
+					}
+				},
+				_ => vmf.extra.extend(Some(kv.clone())),
+			}
+		}
+		vmf.world = world.ok_or_else(|| VdfErr::MissingKey("world".to_string()))?;
+		Ok(vmf)
+	}
+
+	pub fn to_object(&self) -> Object {
+		let mut out = Object::new();
+		if let Some(version_info) = &self.version_info {
+			out.push("versioninfo", version_info.clone());
+		}
+		if let Some(visgroups) = &self.visgroups {
+			out.push("visgroups", visgroups.iter().map(|visgroup| ("visgroup", visgroup.to_object())).collect::<Object>());
+		}
+		if let Some(view_settings) = &self.view_settings {
+			out.push("viewsettings", view_settings.clone());
+		}
+		out.push("world", self.world.to_object());
+		for entity in &self.entities {
+			push_maybe_hidden(&mut out, "entity", entity.to_object(), entity.hidden);
+		}
+		out.extend(self.extra.iter().cloned());
+		out
+	}
+
+	/// Writes the map the way Hammer does, with CRLF line endings.
+	pub fn to_string(&self) -> Result<String, VdfErr> {
+		self.to_object().to_string(&FormatSettings::vmf_like().with_line_ending(LineEnding::CrLf))
+	}
+
+	/// Every entity with the given classname.
+	pub fn entities_of_class<'a>(&'a self, classname: &'a str) -> impl Iterator<Item = &'a Entity> + 'a {
+		self.entities.iter().filter(move |entity| entity.classname() == Some(classname))
+	}
+
+	/// Every entity with the given classname, for editing.
+	pub fn entities_of_class_mut<'a>(&'a mut self, classname: &'a str) -> impl Iterator<Item = &'a mut Entity> + 'a {
+		self.entities.iter_mut().filter(move |entity| entity.classname() == Some(classname))
+	}
+
+	/// Every entity with the given targetname.
+	pub fn entities_named<'a>(&'a self, targetname: &'a str) -> impl Iterator<Item = &'a Entity> + 'a {
+		self.entities.iter().filter(move |entity| entity.get("targetname") == Some(targetname))
+	}
+
+	/// The entity with the given id.
+	pub fn entity(&self, id: u32) -> Option<&Entity> {
+		self.entities.iter().find(|entity| entity.id == id)
+	}
+
+	/// Every brush in the map, in the world first and then in brush entities.
+	pub fn solids(&self) -> impl Iterator<Item = &Solid> {
+		self.world.solids.iter().chain(self.entities.iter().flat_map(|entity| &entity.solids))
+	}
+}
+
+impl FromStr for Vmf {
+	type Err = VdfErr;
+
+	/// Parses a VMF. Hammer doesn't write escape sequences, so backslashes are read as-is.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Vmf::from_object(&VdfDeserializer::with_settings(s, FormatSettings::vmf_like()).parse_object()?)
+	}
+}
+
+impl VisGroup {
+	/// Reads the `visgroups` block, which can't have anything besides `visgroup` blocks in it.
+	fn list(object: &Object) -> Result<Vec<Self>, VdfErr> {
+		let invalid = |kv: &Kv| VdfErr::InvalidValue { key: kv.key.clone(), value: kv.value.as_str().unwrap_or("a block").to_string(), expected: "a visgroup" };
+		object
+			.iter()
+			.map(|kv| match &kv.value {
+				Value::Obj(o) if kv.key == "visgroup" && kv.condition.is_none() => VisGroup::from_object(o),
+				_ => Err(invalid(kv)),
+			})
+			.collect()
+	}
+
+	pub fn from_object(object: &Object) -> Result<Self, VdfErr> {
+		let mut visgroup = VisGroup {
+			name: object.require_str("name")?.to_string(),
+			id: object.parse_str("visgroupid", "an id")?,
+			color: parse_array(object, "color", "a color")?,
+			children: Vec::new(),
+			extra: Object::new(),
+		};
+		for kv in object {
+			match (kv.key.as_str(), &kv.value) {
+				("name", Value::Str(_)) | ("visgroupid", Value::Str(_)) | ("color", Value::Str(_)) => {},
+				("visgroup", Value::Obj(o)) => visgroup.children.push(VisGroup::from_object(o)?),
+				_ => visgroup.extra.extend(Some(kv.clone())),
+			}
+		}
+		Ok(visgroup)
+	}
+
+	pub fn to_object(&self) -> Object {
+		let mut out = Object::new();
+		out.push("name", self.name.as_str());
+		out.push("visgroupid", self.id.to_string());
+		out.push("color", join(&self.color));
+		for child in &self.children {
+			out.push("visgroup", child.to_object());
+		}
+		out.extend(self.extra.iter().cloned());
+		out
+	}
+}
+
+impl World {
+	pub fn from_object(object: &Object) -> Result<Self, VdfErr> {
+		let mut world = World { id: object.parse_str("id", "an id")?, properties: Object::new(), solids: Vec::new(), extra: Object::new() };
+		for kv in object {
+			match (kv.key.as_str(), &kv.value) {
+				("id", Value::Str(_)) => {},
+				(_, Value::Str(_)) => world.properties.extend(Some(kv.clone())),
+				("solid", Value::Obj(o)) => world.solids.push(Solid::from_object(o, false)?),
+				("hidden", Value::Obj(o)) if is_hidden("solid", o) => {
+					for hidden in o.get_all("solid").filter_map(Value::as_obj) {
+						world.solids.push(Solid::from_object(hidden, true)?);
+					}
+				},
+				_ => world.extra.extend(Some(kv.clone())),
+			}
+		}
+		Ok(world)
+	}
+
+	pub fn to_object(&self) -> Object {
+		let mut out = Object::new();
+		out.push("id", self.id.to_string());
+		out.extend(self.properties.iter().cloned());
+		for solid in &self.solids {
+			push_maybe_hidden(&mut out, "solid", solid.to_object(), solid.hidden);
+		}
+		out.extend(self.extra.iter().cloned());
+		out
+	}
+
+	/// Looks up one of the `worldspawn` keyvalues.
+	pub fn get(&self, key: &str) -> Option<&str> {
+		self.properties.get(key).and_then(Value::as_str)
+	}
+}
+
+impl Default for World {
+	fn default() -> Self {
+		World { id: 1, properties: vec![Kv::new("classname", "worldspawn")].into(), solids: Vec::new(), extra: Object::new() }
+	}
+}
+
+impl Entity {
+	pub fn new(id: u32, classname: &str) -> Self {
+		Entity {
+			id,
+			properties: vec![Kv::new("classname", classname)].into(),
+			connections: None,
+			solids: Vec::new(),
+			extra: Object::new(),
+			editor: None,
+			hidden: false,
+		}
+	}
+
+	pub fn from_object(object: &Object, hidden: bool) -> Result<Self, VdfErr> {
+		let mut entity = Entity::new(object.parse_str("id", "an id")?, "");
+		entity.properties = Object::new();
+		entity.hidden = hidden;
+		for kv in object {
+			match (kv.key.as_str(), &kv.value) {
+				("id", Value::Str(_)) => {},
+				(_, Value::Str(_)) => entity.properties.extend(Some(kv.clone())),
+				("connections", Value::Obj(o)) => entity.connections = Some(Connections::from_object(o)?),
+				("solid", Value::Obj(o)) => entity.solids.push(Solid::from_object(o, false)?),
+				("hidden", Value::Obj(o)) if is_hidden("solid", o) => {
+					for hidden in o.get_all("solid").filter_map(Value::as_obj) {
+						entity.solids.push(Solid::from_object(hidden, true)?);
+					}
+				},
+				("editor", Value::Obj(o)) => entity.editor = Some(o.clone()),
+				_ => entity.extra.extend(Some(kv.clone())),
+			}
+		}
+		Ok(entity)
+	}
+
+	pub fn to_object(&self) -> Object {
+		let mut out = Object::new();
+		out.push("id", self.id.to_string());
+		out.extend(self.properties.iter().cloned());
+		if let Some(connections) = &self.connections {
+			out.push("connections", connections.to_object());
+		}
+		for solid in &self.solids {
+			push_maybe_hidden(&mut out, "solid", solid.to_object(), solid.hidden);
+		}
+		out.extend(self.extra.iter().cloned());
+		if let Some(editor) = &self.editor {
+			out.push("editor", editor.clone());
+		}
+		out
+	}
+
+	/// Looks up one of the entity's keyvalues.
+	pub fn get(&self, key: &str) -> Option<&str> {
+		self.properties.get(key).and_then(Value::as_str)
+	}
+
+	/// Changes one of the entity's keyvalues, or adds it to the end.
+	pub fn set(&mut self, key: &str, value: impl Into<String>) {
+		self.properties.insert(key, value.into());
+	}
+
+	pub fn classname(&self) -> Option<&str> {
+		self.get("classname")
+	}
+
+	pub fn origin(&self) -> Option<[f64; 3]> {
+		self.get("origin").and_then(numbers).and_then(|numbers| numbers.try_into().ok())
+	}
+}
+
+impl Connections {
+	pub fn from_object(object: &Object) -> Result<Self, VdfErr> {
+		let connections = object.iter().map(|kv| match &kv.value {
+			Value::Str(s) => Connection::parse(&kv.key, s),
+			Value::Obj(_) => Err(VdfErr::ExpectedString),
+		});
+		Ok(Connections(connections.collect::<Result<_, _>>()?))
+	}
+
+	pub fn to_object(&self) -> Object {
+		self.0.iter().map(|connection| (connection.output.as_str(), connection.value())).collect()
+	}
+
+	pub fn push(&mut self, connection: Connection) {
+		self.0.push(connection);
+	}
+
+	pub fn iter(&self) -> std::slice::Iter<'_, Connection> {
+		self.0.iter()
+	}
+}
+
+impl std::ops::Index<usize> for Connections {
+	type Output = Connection;
+
+	fn index(&self, index: usize) -> &Connection {
+		&self.0[index]
+	}
+}
+
+impl Connection {
+	/// An output with no parameter and no delay, that fires forever, written with commas.
+	pub fn new(output: &str, target: &str, input: &str) -> Self {
+		Connection {
+			output: output.to_string(),
+			target: target.to_string(),
+			input: input.to_string(),
+			parameter: String::new(),
+			delay: 0.0,
+			times_to_fire: -1,
+			separator: ',',
+		}
+	}
+
+	pub fn parse(output: &str, value: &str) -> Result<Self, VdfErr> {
+		let invalid = || VdfErr::InvalidValue { key: output.to_string(), value: value.to_string(), expected: "an output" };
+		let separator = if value.contains('\x1b') { '\x1b' } else { ',' };
+		match value.split(separator).collect::<Vec<_>>().as_slice() {
+			[target, input, parameter, delay, times_to_fire] => Ok(Connection {
+				output: output.to_string(),
+				target: target.to_string(),
+				input: input.to_string(),
+				parameter: parameter.to_string(),
+				delay: delay.parse().map_err(|_| invalid())?,
+				times_to_fire: times_to_fire.parse().map_err(|_| invalid())?,
+				separator,
+			}),
+			_ => Err(invalid()),
+		}
+	}
+
+	/// The value of the output's key-value pair, like `door,Open,,0,-1`.
+	pub fn value(&self) -> String {
+		let s = self.separator;
+		format!("{}{s}{}{s}{}{s}{}{s}{}", self.target, self.input, self.parameter, self.delay, self.times_to_fire, s = s)
+	}
+}
+
+impl Solid {
+	pub fn from_object(object: &Object, hidden: bool) -> Result<Self, VdfErr> {
+		let mut solid = Solid { id: object.parse_str("id", "an id")?, sides: Vec::new(), extra: Object::new(), editor: None, hidden };
+		for kv in object {
+			match (kv.key.as_str(), &kv.value) {
+				("id", Value::Str(_)) => {},
+				("side", Value::Obj(o)) => solid.sides.push(Side::from_object(o)?),
+				("editor", Value::Obj(o)) => solid.editor = Some(o.clone()),
+				_ => solid.extra.extend(Some(kv.clone())),
+			}
+		}
+		Ok(solid)
+	}
+
+	pub fn to_object(&self) -> Object {
+		let mut out = Object::new();
+		out.push("id", self.id.to_string());
+		for side in &self.sides {
+			out.push("side", side.to_object());
+		}
+		out.extend(self.extra.iter().cloned());
+		if let Some(editor) = &self.editor {
+			out.push("editor", editor.clone());
+		}
+		out
+	}
+}
+
+const SIDE_KEYS: &[&str] = &["id", "plane", "material", "uaxis", "vaxis", "rotation", "lightmapscale", "smoothing_groups", "dispinfo"];
+
+impl Side {
+	pub fn from_object(object: &Object) -> Result<Self, VdfErr> {
+		let plane = parse_numbers(object, "plane", 9, "three points")?;
+		Ok(Side {
+			id: object.parse_str("id", "an id")?,
+			plane: [[plane[0], plane[1], plane[2]], [plane[3], plane[4], plane[5]], [plane[6], plane[7], plane[8]]],
+			material: object.require_str("material")?.to_string(),
+			uaxis: TextureAxis::from_object(object, "uaxis")?,
+			vaxis: TextureAxis::from_object(object, "vaxis")?,
+			rotation: object.parse_str("rotation", "a number")?,
+			lightmap_scale: object.parse_str("lightmapscale", "a number")?,
+			smoothing_groups: object.parse_str("smoothing_groups", "a number")?,
+			extra: object.iter().filter(|kv| !SIDE_KEYS.contains(&kv.key.as_str())).cloned().collect::<Vec<_>>().into(),
+			dispinfo: object.get("dispinfo").and_then(Value::as_obj).map(DispInfo::from_object).transpose()?,
+		})
+	}
+
+	pub fn to_object(&self) -> Object {
+		let mut out = Object::new();
+		out.push("id", self.id.to_string());
+		out.push("plane", format!("({}) ({}) ({})", join(&self.plane[0]), join(&self.plane[1]), join(&self.plane[2])));
+		out.push("material", self.material.as_str());
+		out.push("uaxis", self.uaxis.to_string());
+		out.push("vaxis", self.vaxis.to_string());
+		out.push("rotation", self.rotation.to_string());
+		out.push("lightmapscale", self.lightmap_scale.to_string());
+		out.push("smoothing_groups", self.smoothing_groups.to_string());
+		out.extend(self.extra.iter().cloned());
+		if let Some(dispinfo) = &self.dispinfo {
+			out.push("dispinfo", dispinfo.to_object());
+		}
+		out
+	}
+}
+
+impl TextureAxis {
+	fn from_object(object: &Object, key: &str) -> Result<Self, VdfErr> {
+		let numbers = parse_numbers(object, key, 5, "a texture axis")?;
+		Ok(TextureAxis { axis: [numbers[0], numbers[1], numbers[2]], shift: numbers[3], scale: numbers[4] })
+	}
+}
+
+/// Like `[1 0 0 0] 0.25`.
+impl fmt::Display for TextureAxis {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "[{} {}] {}", join(&self.axis), self.shift, self.scale)
+	}
+}
+
+const DISPINFO_KEYS: &[&str] = &[
+	"power",
+	"startposition",
+	"flags",
+	"elevation",
+	"subdiv",
+	"normals",
+	"distances",
+	"offsets",
+	"offset_normals",
+	"alphas",
+	"triangle_tags",
+	"allowed_verts",
+];
+
+impl DispInfo {
+	pub fn from_object(object: &Object) -> Result<Self, VdfErr> {
+		let start = parse_numbers(object, "startposition", 3, "a point")?;
+		let allowed_verts = match object.get("allowed_verts").and_then(Value::as_obj).and_then(|o| o.iter().next()) {
+			Some(kv) => parse_all(&kv.key, kv.value.as_str().unwrap_or_default(), "numbers")?,
+			None => Vec::new(),
+		};
+		Ok(DispInfo {
+			power: object.parse_str("power", "a number")?,
+			start_position: [start[0], start[1], start[2]],
+			flags: object.parse_str("flags", "a number")?,
+			elevation: object.parse_str("elevation", "a number")?,
+			subdiv: object.parse_str::<u8>("subdiv", "0 or 1")? != 0,
+			normals: vector_rows(object, "normals")?,
+			distances: rows(object, "distances")?,
+			offsets: vector_rows(object, "offsets")?,
+			offset_normals: vector_rows(object, "offset_normals")?,
+			alphas: rows(object, "alphas")?,
+			triangle_tags: rows(object, "triangle_tags")?,
+			allowed_verts,
+			extra: object.iter().filter(|kv| !DISPINFO_KEYS.contains(&kv.key.as_str())).cloned().collect::<Vec<_>>().into(),
+		})
+	}
+
+	/// Blocks of rows that are empty aren't written.
+	pub fn to_object(&self) -> Object {
+		let flatten = |rows: &[Vec<[f64; 3]>]| rows.iter().map(|row| row.iter().flatten().copied().collect()).collect::<Vec<Vec<f64>>>();
+
+		let mut out = Object::new();
+		out.push("power", self.power.to_string());
+		out.push("startposition", format!("[{}]", join(&self.start_position)));
+		out.push("flags", self.flags.to_string());
+		out.push("elevation", self.elevation.to_string());
+		out.push("subdiv", if self.subdiv { "1" } else { "0" });
+		push_rows(&mut out, "normals", &flatten(&self.normals));
+		push_rows(&mut out, "distances", &self.distances);
+		push_rows(&mut out, "offsets", &flatten(&self.offsets));
+		push_rows(&mut out, "offset_normals", &flatten(&self.offset_normals));
+		push_rows(&mut out, "alphas", &self.alphas);
+		push_rows(&mut out, "triangle_tags", &self.triangle_tags);
+		if !self.allowed_verts.is_empty() {
+			out.push("allowed_verts", Object::from(vec![Kv::new(self.allowed_verts.len().to_string(), join(&self.allowed_verts))]));
+		}
+		out.extend(self.extra.iter().cloned());
+		out
+	}
+}
+
+/// Whether a `hidden` block is just a wrapper around things of the one kind.
+fn is_hidden(kind: &str, object: &Object) -> bool {
+	!object.is_empty() && object.iter().all(|kv| kv.key == kind && kv.value.is_obj() && kv.condition.is_none())
+}
+
+fn push_maybe_hidden(out: &mut Object, key: &str, object: Object, hidden: bool) {
+	if hidden {
+		out.push("hidden", Object::from(vec![Kv::new(key, object)]));
+	} else {
+		out.push(key, object);
+	}
+}
+
+/// All the numbers in a string, ignoring any brackets around them.
+fn numbers(text: &str) -> Option<Vec<f64>> {
+	text.split(|c: char| c.is_whitespace() || "()[]".contains(c)).filter(|s| !s.is_empty()).map(|s| s.parse().ok()).collect()
+}
+
+fn parse_numbers(object: &Object, key: &str, count: usize, expected: &'static str) -> Result<Vec<f64>, VdfErr> {
+	let value = object.require_str(key)?;
+	match numbers(value) {
+		Some(numbers) if numbers.len() == count => Ok(numbers),
+		_ => Err(VdfErr::InvalidValue { key: key.to_string(), value: value.to_string(), expected }),
+	}
+}
+
+/// The `row0`, `row1`... of a displacement block. A missing block has no rows.
+fn rows<T>(object: &Object, key: &str) -> Result<Vec<Vec<T>>, VdfErr>
+where
+	T: FromStr,
+{
+	let block = match object.get(key) {
+		Some(_) => object.require_obj(key)?,
+		None => return Ok(Vec::new()),
+	};
+	block.iter().map(|kv| parse_all(&kv.key, kv.value.as_str().ok_or(VdfErr::ExpectedString)?, "numbers")).collect()
+}
+
+fn vector_rows(object: &Object, key: &str) -> Result<Vec<Vec<[f64; 3]>>, VdfErr> {
+	rows::<f64>(object, key)?
+		.into_iter()
+		.map(|row| match row.len() % 3 {
+			0 => Ok(row.chunks(3).map(|v| [v[0], v[1], v[2]]).collect()),
+			_ => Err(VdfErr::InvalidValue { key: key.to_string(), value: join(&row), expected: "a row of vectors" }),
+		})
+		.collect()
+}
+
+fn push_rows<T>(out: &mut Object, key: &str, rows: &[Vec<T>])
+where
+	T: fmt::Display,
+{
+	if !rows.is_empty() {
+		out.push(key, rows.iter().enumerate().map(|(i, row)| (format!("row{}", i), join(row))).collect::<Object>());
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn connections() {
+		let old = Connection::parse("OnPressed", "door,Open,,0.5,1").unwrap();
+		assert_eq!((old.target.as_str(), old.input.as_str(), old.delay, old.times_to_fire, old.separator), ("door", "Open", 0.5, 1, ','));
+		assert_eq!(old.value(), "door,Open,,0.5,1");
+
+		let new = Connection::parse("OnTrigger", "relay\x1bTrigger\x1ba,b\x1b0\x1b-1").unwrap();
+		assert_eq!(new.parameter, "a,b");
+		assert_eq!(new.value(), "relay\x1bTrigger\x1ba,b\x1b0\x1b-1");
+
+		assert!(matches!(Connection::parse("OnPressed", "door,Open"), Err(VdfErr::InvalidValue { .. })));
+		assert!(matches!(Connection::parse("OnPressed", "door,Open,,soon,-1"), Err(VdfErr::InvalidValue { .. })));
+	}
+
+	#[test]
+	fn errors() {
+		let side = |plane: &str| {
+			format!(
+				r#"world {{ id 1 solid {{ id 2 side {{ id 3 plane "{}" material X uaxis "[1 0 0 0] 0.25" vaxis "[0 1 0 0] 0.25" rotation 0 lightmapscale 16 smoothing_groups 0 }} }} }}"#,
+				plane
+			)
+		};
+		assert!(side("(0 0 0) (1 0 0) (1 1 0)").parse::<Vmf>().is_ok());
+		assert!(matches!(side("(0 0 0) (1 0 0)").parse::<Vmf>(), Err(VdfErr::InvalidValue { expected: "three points", .. })));
+		assert!(matches!("entity { id 1 }".parse::<Vmf>(), Err(VdfErr::MissingKey(key)) if key == "world"));
+		assert!(matches!("world { classname worldspawn }".parse::<Vmf>(), Err(VdfErr::MissingKey(key)) if key == "id"));
+	}
+
+	#[test]
+	fn optional_blocks() {
+		let settings = FormatSettings::vmf_like().with_line_ending(LineEnding::CrLf);
+		for text in &[
+			"world { id 1 classname worldspawn }",
+			"visgroups { } world { id 1 classname worldspawn }",
+			"visgroups { visgroup { name a visgroupid 1 color \"1 2 3\" note hi } } world { id 1 classname worldspawn }",
+			"world { id 1 classname worldspawn } entity { id 2 classname logic_relay connections { } }",
+		] {
+			let object: Object = VdfDeserializer::with_settings(text, settings.clone()).parse_object().unwrap();
+			let vmf = Vmf::from_object(&object).unwrap();
+			assert_eq!(vmf.to_string().unwrap(), object.to_string(&settings).unwrap());
+		}
+		assert!(matches!("visgroups { note hi } world { id 1 }".parse::<Vmf>(), Err(VdfErr::InvalidValue { expected: "a visgroup", .. })));
+	}
+}
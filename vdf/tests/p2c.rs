@@ -0,0 +1,53 @@
+use vdf::p2c::*;
+
+const PUZZLE: &str = include_str!("data/puzzle.p2c");
+
+#[test]
+fn puzzle_round_trip() {
+	let puzzle: Puzzle = PUZZLE.parse().unwrap();
+	assert_eq!(puzzle.to_string().unwrap(), PUZZLE);
+}
+
+#[test]
+fn puzzle_model() {
+	let puzzle: Puzzle = PUZZLE.parse().unwrap();
+
+	assert_eq!(puzzle.title(), Some("Two Buttons"));
+	assert_eq!(puzzle.description(), Some("Press both.\nThen leave."));
+	assert!(!puzzle.coop());
+
+	assert_eq!(puzzle.voxels.size(), [4, 4, 4]);
+	assert!(puzzle.voxels.is_solid([0, 0, 0]));
+	assert!(!puzzle.voxels.is_solid([1, 1, 1]));
+	assert_eq!(puzzle.voxels.get([0, 1, 1]), Some(Voxel { solid: true, portalable: [true; 3] }));
+	assert_eq!(puzzle.voxels.iter().filter(|(_, voxel)| !voxel.solid).count(), 8);
+
+	let button = puzzle.items_of_type("ITEM_BUTTON_PEDESTAL").next().unwrap();
+	assert_eq!((button.voxel_pos, button.local_pos, button.angles), ([2, 1, 1], [0.0, 0.0, -64.0], [0.0, 90.0, 0.0]));
+	assert_eq!(button.get("TimerDelay"), Some("3"));
+	assert_eq!(puzzle.connections_to(1).map(|connection| connection.sender).collect::<Vec<_>>(), [2, 3]);
+	assert_eq!(
+		puzzle.rest.iter().map(|kv| kv.key.as_str()).collect::<Vec<_>>(),
+		["AppID", "Version", "Timestamp_Created", "Timestamp_Modified", "CompileTime", "Title", "Description", "PreviewDirty", "Coop", "Coop"]
+	);
+}
+
+#[test]
+fn generate() {
+	let mut puzzle: Puzzle = PUZZLE.parse().unwrap();
+	puzzle.set_title("Three Buttons");
+	puzzle.voxels.set([1, 1, 2], Voxel { solid: true, portalable: [false, false, true] });
+	let index = puzzle.next_index();
+	let mut button = Item::new(index, "ITEM_BUTTON_FLOOR", [1, 1, 3]);
+	button.set("ButtonType", "1");
+	puzzle.items.push(button);
+	puzzle.connections.push(Connection::new(index, 1));
+	puzzle.remove_item(2);
+
+	let text = puzzle.to_string().unwrap();
+	assert!(text.contains("\t\"Title\"\t\"Three Buttons\"\n"));
+	assert!(text.contains("\t\t\"Voxel\"\t\"ffff f90f f00f ffff\"\n"));
+	let reloaded: Puzzle = text.parse().unwrap();
+	assert_eq!(reloaded, puzzle);
+	assert_eq!(reloaded.connections_to(1).map(|connection| connection.sender).collect::<Vec<_>>(), [3, 4]);
+}
@@ -0,0 +1,72 @@
+use std::fs;
+use std::io::Write;
+use std::process::Command;
+use std::process::Stdio;
+
+const FMT: &str = env!("CARGO_BIN_EXE_vdf-fmt");
+
+fn run(args: &[&str], stdin: &str) -> (i32, String) {
+	let mut child = Command::new(FMT).args(args).stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::null()).spawn().unwrap();
+	//It might have already quit over a bad argument
+	let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+	let output = child.wait_with_output().unwrap();
+	(output.status.code().unwrap(), String::from_utf8(output.stdout).unwrap())
+}
+
+#[test]
+fn styles() {
+	let messy = "versioninfo { editorversion 400 mapversion \"1\" }";
+
+	let (status, out) = run(&["--style", "vmf"], messy);
+	assert_eq!(status, 0);
+	assert_eq!(out, "versioninfo\n{\n\t\"editorversion\" \"400\"\n\t\"mapversion\" \"1\"\n}\n");
+	assert_eq!(run(&["--style", "vmf", "--check"], &out), (0, String::new()));
+	assert_eq!(run(&["--style", "p2c", "--check"], &out).0, 1);
+
+	let (status, out) = run(&["--style", "gameinfo"], messy);
+	assert_eq!(status, 0);
+	assert_eq!(out, "\"versioninfo\"\n{\n\teditorversion\t400\n\tmapversion\t1\n}\n");
+
+	assert_eq!(run(&["--style", "nope"], messy).0, 2);
+	assert_eq!(run(&["--input-style", "nope"], messy).0, 2);
+	assert_eq!(run(&[], "a { b").0, 2);
+}
+
+#[test]
+fn input_style() {
+	//Hammer doesn't have escape sequences, so the backslash is just a backslash
+	let vmf = "entity { path \"C:\\new\" }";
+	assert_eq!(run(&["--input-style", "vmf", "--style", "p2c"], vmf), (0, "\"entity\"\n{\n\t\"path\"\t\"C:\\\\new\"\n}\n".to_string()));
+
+	//Which is the default for .vmf files
+	let dir = std::env::temp_dir().join(format!("vdf-fmt-vmf-test-{}", std::process::id()));
+	fs::create_dir_all(&dir).unwrap();
+	let file = dir.join("map.vmf");
+	fs::write(&file, vmf).unwrap();
+	assert_eq!(run(&["--style", "p2c", file.to_str().unwrap()], ""), (0, "\"entity\"\n{\n\t\"path\"\t\"C:\\\\new\"\n}\n".to_string()));
+	fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn comments() {
+	let commented = "// The cube\nItem { Type ITEM_CUBE }";
+	assert_eq!(run(&[], commented), (2, String::new()));
+	assert_eq!(run(&["--drop-comments"], commented), (0, "\"Item\"\n{\n\t\"Type\"\t\"ITEM_CUBE\"\n}\n".to_string()));
+	assert_eq!(run(&[], "Item { Type \"//not a comment\" }").0, 0);
+}
+
+#[test]
+fn in_place() {
+	let dir = std::env::temp_dir().join(format!("vdf-fmt-test-{}", std::process::id()));
+	fs::create_dir_all(&dir).unwrap();
+	let file = dir.join("editoritems.txt");
+	fs::write(&file, "ItemData { Item { Type ITEM_CUBE } }").unwrap();
+	let path = file.to_str().unwrap();
+
+	assert_eq!(run(&["--check", path], ""), (1, format!("{}\n", path)));
+	assert_eq!(run(&["--in-place", path], "").0, 0);
+	assert_eq!(fs::read_to_string(&file).unwrap(), "\"ItemData\"\n{\n\t\"Item\"\n\t{\n\t\t\"Type\"\t\"ITEM_CUBE\"\n\t}\n}\n");
+	assert_eq!(run(&["--check", path], ""), (0, String::new()));
+
+	fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,58 @@
+use std::fs;
+use std::process::Command;
+
+const MERGE: &str = env!("CARGO_BIN_EXE_vdf-merge");
+
+#[test]
+fn merge_driver() {
+	let dir = std::env::temp_dir().join(format!("vdf-merge-test-{}", std::process::id()));
+	fs::create_dir_all(&dir).unwrap();
+	let write = |name: &str, contents: &str| {
+		let file = dir.join(name);
+		fs::write(&file, contents).unwrap();
+		file.to_str().unwrap().to_string()
+	};
+	let run = |files: &[&String]| Command::new(MERGE).args(["--id-key", "Type"]).args(files).output().unwrap();
+
+	let base = write("base.txt", "ItemData { Item { Type ITEM_BUTTON } Item { Type ITEM_CUBE } }");
+	let ours = write("ours.txt", "ItemData { Item { Type ITEM_BUTTON Skin 1 } Item { Type ITEM_CUBE } }");
+	let theirs = write("theirs.txt", "ItemData { Item { Type ITEM_LASER } Item { Type ITEM_BUTTON } Item { Type ITEM_CUBE Skin 2 } }");
+
+	let output = run(&[&base, &ours, &theirs]);
+	assert_eq!(output.status.code(), Some(0));
+	assert_eq!(
+		fs::read_to_string(&ours).unwrap(),
+		"\"ItemData\"\n{\n\t\"Item\"\n\t{\n\t\t\"Type\"\t\"ITEM_LASER\"\n\t}\n\t\"Item\"\n\t{\n\t\t\"Type\"\t\"ITEM_BUTTON\"\n\t\t\"Skin\"\t\"1\"\n\t}\n\t\"Item\"\n\t{\n\t\t\"Type\"\t\"ITEM_CUBE\"\n\t\t\"Skin\"\t\"2\"\n\t}\n}\n"
+	);
+
+	let ours = write("ours.txt", "ItemData { Item { Type ITEM_BUTTON Skin 1 } }");
+	let theirs = write("theirs.txt", "ItemData { Item { Type ITEM_BUTTON Skin 2 } }");
+	let output = run(&[&base, &ours, &theirs]);
+	assert_eq!(output.status.code(), Some(1));
+	assert_eq!(
+		String::from_utf8(output.stderr).unwrap(),
+		"vdf-merge: conflict at ItemData/Item[Type=ITEM_BUTTON]/Skin: was (nothing), ours is \"1\", theirs is \"2\"\n"
+	);
+
+	//Hammer files don't have escape sequences, so backslashes have to be read as-is
+	let base = write("base.vmf", "entity { path \"C:\\\\x\" }");
+	let ours = write("ours.vmf", "entity { path \"C:\\\\x\" id 1 }");
+	let theirs = write("theirs.vmf", "entity { path \"C:\\\\y\" }");
+	let output = Command::new(MERGE).args(["--input-style", "vmf", "--style", "vmf"]).args([&base, &ours, &theirs]).output().unwrap();
+	assert_eq!(output.status.code(), Some(0));
+	assert_eq!(fs::read_to_string(&ours).unwrap(), "entity\n{\n\t\"path\" \"C:\\\\y\"\n\t\"id\" \"1\"\n}\n");
+
+	//Comments would be lost, so git gets to do a text merge instead
+	let base = write("base.txt", "ItemData { Item { Type ITEM_BUTTON } }");
+	let commented = write("commented.txt", "ItemData {\n\t// Cube item\n\tItem { Type ITEM_CUBE }\n}");
+	let output = run(&[&base, &commented, &theirs]);
+	assert_eq!(output.status.code(), Some(2));
+	assert_eq!(fs::read_to_string(&commented).unwrap(), "ItemData {\n\t// Cube item\n\tItem { Type ITEM_CUBE }\n}");
+	assert_eq!(run(&[&commented, &base, &theirs]).status.code(), Some(2));
+
+	let broken = write("broken.txt", "ItemData {");
+	assert_eq!(run(&[&base, &broken, &theirs]).status.code(), Some(2));
+	assert_eq!(run(&[&base, &theirs]).status.code(), Some(2));
+
+	fs::remove_dir_all(&dir).unwrap();
+}
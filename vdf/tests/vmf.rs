@@ -0,0 +1,59 @@
+use vdf::vmf::*;
+
+const HAMMER: &str = include_str!("data/hammer.vmf");
+
+#[test]
+fn hammer_round_trip() {
+	let vmf: Vmf = HAMMER.parse().unwrap();
+	assert_eq!(vmf.to_string().unwrap(), HAMMER);
+}
+
+#[test]
+fn hammer_model() {
+	let vmf: Vmf = HAMMER.parse().unwrap();
+
+	let visgroups = vmf.visgroups.as_ref().unwrap();
+	assert_eq!(visgroups[0].name, "Gameplay");
+	assert_eq!(visgroups[0].children[0].color, [112, 241, 240]);
+	assert_eq!(vmf.world.get("skyname"), Some("sky_black_nofog"));
+	assert_eq!(vmf.world.solids.iter().map(|solid| solid.hidden).collect::<Vec<_>>(), [false, true]);
+
+	let floor = &vmf.world.solids[0].sides[0];
+	assert_eq!(floor.plane, [[-64.0, 64.0, 0.0], [64.0, 64.0, 0.0], [64.0, -64.0, 0.0]]);
+	assert_eq!(floor.uaxis, TextureAxis { axis: [1.0, 0.0, 0.0], shift: 0.0, scale: 0.25 });
+	let dispinfo = floor.dispinfo.as_ref().unwrap();
+	assert_eq!(dispinfo.normals.len(), 5);
+	assert_eq!(dispinfo.normals[0][4], [0.0, 0.0, 1.0]);
+	assert_eq!(dispinfo.distances[2], [0.0, 4.0, 8.0, 4.0, 0.0]);
+	assert_eq!(dispinfo.triangle_tags[3].len(), 8);
+	assert_eq!(dispinfo.allowed_verts, [-1; 10]);
+
+	let button = vmf.entities_named("button").next().unwrap();
+	assert_eq!(button.origin(), Some([-32.0, 0.0, 0.0]));
+	assert_eq!(
+		button.connections.as_ref().unwrap().iter().map(|c| (c.target.as_str(), c.input.as_str(), c.delay)).collect::<Vec<_>>(),
+		[("door", "Open", 0.0), ("@relay", "Trigger", 0.5)]
+	);
+
+	let detail = vmf.entities_of_class("func_detail").next().unwrap();
+	assert!(detail.hidden);
+	assert_eq!(vmf.entity(17), Some(detail));
+	assert_eq!(vmf.solids().count(), 3);
+	assert_eq!(vmf.extra.iter().map(|kv| kv.key.as_str()).collect::<Vec<_>>(), ["cameras", "cordons"]);
+}
+
+#[test]
+fn edit() {
+	let mut vmf: Vmf = HAMMER.parse().unwrap();
+	for button in vmf.entities_of_class_mut("prop_button") {
+		button.set("Delay", "3");
+		button.connections.get_or_insert_with(Connections::default).push(Connection::new("OnPressed", "sound", "PlaySound"));
+	}
+	vmf.world.solids[0].sides[0].dispinfo.as_mut().unwrap().distances[2][2] = 16.0;
+
+	let text = vmf.to_string().unwrap();
+	assert!(text.contains("\t\"Delay\" \"3\"\r\n"));
+	assert!(text.contains("\t\t\"OnPressed\" \"sound,PlaySound,,0,-1\"\r\n"));
+	assert!(text.contains("\"row2\" \"0 4 16 4 0\""));
+	assert_eq!(text.parse::<Vmf>().unwrap(), vmf);
+}
@@ -1,10 +1,9 @@
 #![allow(dead_code)]
 
 use std::collections::BTreeMap;
-use std::u32;
 
+use serde::Deserialize;
 use serde::Serialize;
-use vdf::named_seq_func;
 
 #[test]
 fn main() {
@@ -25,7 +24,7 @@ fn main() {
 				palette: Palette {
 					tooltip: "PORTAL2_PuzzleEditor_Palette_pedestal_button".into(),
 					image: "palette/pedestal_button.png".into(),
-					position: "0 0 0".into(),
+					position: (0, 0, 0),
 				},
 				sounds: Sounds {
 					create: "P2Editor.PlaceButton".into(),
@@ -40,7 +39,7 @@ fn main() {
 		exporting: ExportingBlock {
 			instances: vec![Instance { name: "instances/p2editor/pedestal_button.vmf".into(), entity_count: 7, brush_count: 1, brush_side_count: 6 }],
 			target_name: "button".into(),
-			offset: "64 64 64".into(),
+			offset: (64, 64, 64),
 			occupied_voxels: (), //todo
 			embedded_voxels: (), //todo
 		},
@@ -50,21 +49,20 @@ fn main() {
 
 	let serialized = vdf::ser::to_string_with_toplevel_block(&data, "ItemData").expect("could not serialize");
 	println!("{}", serialized);
+	assert_eq!(vdf::de::from_str_with_toplevel_block::<ItemData>(&serialized, "ItemData").expect("could not deserialize"), data);
 
 	let mut funny = vdf::ser::VdfSerializer::with_settings(vdf::ser::FormatSettings::beemod_like());
 	data.serialize(&mut funny).expect("could not serialize");
-	println!("{}", funny.out);
+	println!("{}", funny.into_inner());
 }
 
-named_seq_func!(item_seq "Item");
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 struct ItemData {
-	#[serde(flatten)]
-	#[serde(serialize_with = "item_seq")]
+	#[serde(rename = "Item", with = "vdf::repeated")]
 	items: Vec<Item>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 struct Item {
 	item_class: ItemClass,
@@ -75,7 +73,7 @@ struct Item {
 	exporting: ExportingBlock,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 enum ItemClass {
 	#[serde(rename = "ItemPedestalButton")]
 	PedestalButton,
@@ -84,20 +82,17 @@ enum ItemClass {
 	//etc
 }
 
-named_seq_func!(subtype_seq "SubType");
-
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 struct EditorBlock {
 	#[serde(skip_serializing_if = "Option::is_none")]
 	sub_type_property: Option<SubTypeProperty>,
-	#[serde(flatten)]
-	#[serde(serialize_with = "subtype_seq")]
+	#[serde(rename = "SubType", with = "vdf::repeated")]
 	sub_types: Vec<SubTypeBlock>,
 	movement_handle: MovementHandle,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 enum SubTypeProperty {
 	#[serde(rename = "ButtonType")]
 	Button,
@@ -111,7 +106,7 @@ enum SubTypeProperty {
 	Paint,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 struct SubTypeBlock {
 	name: String,
@@ -120,7 +115,7 @@ struct SubTypeBlock {
 	sounds: Sounds,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 struct Model {
 	#[serde(rename = "ModelName")]
 	name: String,
@@ -128,15 +123,15 @@ struct Model {
 	texture: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 struct Palette {
 	tooltip: String,
 	image: String,
-	position: String, //TODO: (u8, u8, u8)
+	position: (u8, u8, u8),
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 struct Sounds {
 	#[serde(rename = "SOUND_CREATED")]
 	create: String,
@@ -148,7 +143,7 @@ struct Sounds {
 	delete: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 enum MovementHandle {
 	#[serde(rename = "HANDLE_NONE")]
 	None,
@@ -166,24 +161,24 @@ enum MovementHandle {
 	Catapult,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 struct PropertySettings {
 	default_value: String,
 	index: usize,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 struct ExportingBlock {
 	instances: Vec<Instance>,
 	target_name: String,
-	offset: String,      //TODO vec3
+	offset: (i32, i32, i32),
 	occupied_voxels: (), //TODO
 	embedded_voxels: (), //TODO
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 struct Instance {
 	name: String,